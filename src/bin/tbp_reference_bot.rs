@@ -0,0 +1,111 @@
+// A tiny bot that speaks the Tetris Bot Protocol, for trying out `[bot] kind = "tbp"` and for testing it. It drops
+// the current tetromino straight down wherever the heuristic bot's default weights like best, and never holds
+use std::io::{self, BufRead};
+
+use tetris_raylib_rs::{
+    config::BotWeights,
+    source::tbp::{
+        self, BotMessage, FrontendMessage, Location, Orientation, Spin, TbpError, TbpGame, TbpMove,
+    },
+};
+
+/// Best straight drop of the current tetromino, if there's anywhere left to put it
+fn suggest(game: &TbpGame, weights: &BotWeights) -> Option<TbpMove> {
+    let piece = *game.queue.first()?;
+    let top = game.field.heights().into_iter().max().unwrap_or(0) as i32;
+    let mut best: Option<(f32, TbpMove)> = None;
+    for orientation in Orientation::ALL.iter() {
+        for x in 0..tbp::TBP_WIDTH as i32 {
+            // No tetromino reaches more than two cells from its centre, so this clears the stack
+            let mut location = Location {
+                piece,
+                orientation: *orientation,
+                x,
+                y: top + 2,
+            };
+            let fits = |location: &Location| {
+                location
+                    .cells()
+                    .is_some_and(|cells| game.field.fits(&cells))
+            };
+            if !fits(&location) {
+                continue;
+            }
+            while fits(&Location {
+                y: location.y - 1,
+                ..location
+            }) {
+                location.y -= 1;
+            }
+
+            let mv = TbpMove {
+                location,
+                spin: Spin::None,
+            };
+            let mut after = game.clone();
+            let lines = after.play(&mv);
+            let score = after.field.score(weights, lines, location.y as f32);
+            if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                best = Some((score, mv));
+            }
+        }
+    }
+    best.map(|(_, mv)| mv)
+}
+
+fn run() -> Result<(), TbpError> {
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let mut writer = io::stdout();
+    tbp::send(
+        &mut writer,
+        &BotMessage::Info {
+            name: String::from("reference"),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            author: String::from(env!("CARGO_PKG_AUTHORS")),
+            features: Vec::new(),
+        },
+    )?;
+
+    let weights = BotWeights::default();
+    let mut game: Option<TbpGame> = None;
+    loop {
+        // Nothing's left to read once the game closes its end
+        if reader.fill_buf()?.is_empty() {
+            return Ok(());
+        }
+        match tbp::receive(&mut reader)? {
+            FrontendMessage::Rules {} => tbp::send(&mut writer, &BotMessage::Ready)?,
+            FrontendMessage::Start {
+                hold, queue, board, ..
+            } => game = Some(TbpGame::start(hold, queue, &board)),
+            FrontendMessage::Suggest => {
+                let moves = game
+                    .as_ref()
+                    .and_then(|game| suggest(game, &weights))
+                    .into_iter()
+                    .collect();
+                tbp::send(&mut writer, &BotMessage::Suggestion { moves })?;
+            }
+            FrontendMessage::Play { mv } => {
+                if let Some(game) = &mut game {
+                    game.play(&mv);
+                }
+            }
+            FrontendMessage::NewPiece { piece } => {
+                if let Some(game) = &mut game {
+                    game.queue.push(piece);
+                }
+            }
+            FrontendMessage::Stop => game = None,
+            FrontendMessage::Quit => return Ok(()),
+        }
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
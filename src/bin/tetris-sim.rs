@@ -0,0 +1,111 @@
+// Plays lots of games with a bot and no window, writing out how each went and how they went overall
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use tetris_raylib_rs::{
+    config::{BotKind, Config},
+    sim::{self, Summary},
+};
+
+const USAGE: &str = "usage: tetris-sim [--config <file>] [--bot heuristic|random|tbp] [--games <n>] [--seed <first seed>] \
+                     [--pieces <limit>] [--out <dir>]";
+
+/// What to simulate, from the command line
+struct Options {
+    // Rules and bot, the config file's defaults if there isn't one
+    config: Config,
+    bot: Option<BotKind>,
+    games: u64,
+    seed: u64,
+    pieces: u32,
+    out: PathBuf,
+}
+
+/// `value` of the option `arg` as a number of whichever type it's kept as
+fn number<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} has to be a number, got {}", arg, value))
+}
+
+fn parse(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        config: Config::default(),
+        bot: None,
+        games: 100,
+        seed: 0,
+        pieces: 1000,
+        out: PathBuf::from("sim"),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--config" => {
+                options.config = Config::load(Path::new(value)).map_err(|e| e.to_string())?
+            }
+            "--bot" => {
+                options.bot = Some(match value.as_str() {
+                    "heuristic" => BotKind::Heuristic,
+                    "random" => BotKind::Random,
+                    "tbp" => BotKind::Tbp,
+                    _ => return Err(format!("there's no {} bot", value)),
+                })
+            }
+            "--games" => options.games = number(arg, value)?,
+            "--seed" => options.seed = number(arg, value)?,
+            "--pieces" => options.pieces = number(arg, value)?,
+            "--out" => options.out = PathBuf::from(value),
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    if options.seed.checked_add(options.games).is_none() {
+        return Err(format!(
+            "--games {} from --seed {} runs past the last seed",
+            options.games, options.seed
+        ));
+    }
+    Ok(options)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let mut bot = options.config.settings().bot.clone();
+    if let Some(kind) = options.bot {
+        bot.kind = kind;
+    }
+    let seeds = options.seed..options.seed + options.games;
+    let games = match sim::run(&options.config, &bot, seeds, options.pieces) {
+        Ok(games) => games,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let summary = Summary::of(&games);
+    if let Err(e) = sim::write_stats(&options.out, &games, &summary) {
+        eprintln!("couldn't write stats to {}: {}", options.out.display(), e);
+        std::process::exit(1);
+    }
+    println!(
+        "{} games: {:.1} lines, {:.0} score and {:.1} pieces on average, {:.1}% of lines from tetrises, {} topped out",
+        summary.games,
+        summary.mean_lines,
+        summary.mean_score,
+        summary.mean_pieces,
+        summary.tetris_rate * 100.0,
+        summary.top_outs
+    );
+}
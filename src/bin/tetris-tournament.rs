@@ -0,0 +1,137 @@
+// Plays bots against each other in versus, printing a table of their results and Elo ratings
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use tetris_raylib_rs::{
+    config::Config,
+    tournament::{file_name, Entrant, Tournament},
+};
+
+const USAGE: &str = "usage: tetris-tournament --bot <name>=<bot> --bot <name>=<bot> [--bot ...] [--config <file>] \
+                     [--format round-robin|swiss] [--rounds <n>] [--games <n>] [--seed <first seed>] \
+                     [--pieces <limit>] [--out <dir>]\n\
+                     where each bot is heuristic, heuristic:<config file>, random or tbp:\"<command> [args]\"";
+
+enum Format {
+    RoundRobin,
+    Swiss,
+}
+
+/// What to play, from the command line
+struct Options {
+    // Rules to play by, which the bots default to
+    config: Config,
+    // Bots as given on the command line, read once the config is known
+    bots: Vec<String>,
+    format: Format,
+    // Rounds of Swiss, round robin always plays a single one
+    rounds: u32,
+    // Seeds that each pairing plays, once from either side
+    games: u64,
+    seed: u64,
+    pieces: u32,
+    out: PathBuf,
+}
+
+/// `value` of the option `arg` as a number of whichever type it's kept as
+fn number<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} has to be a number, got {}", arg, value))
+}
+
+fn parse(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        config: Config::default(),
+        bots: Vec::new(),
+        format: Format::RoundRobin,
+        rounds: 3,
+        games: 10,
+        seed: 0,
+        pieces: 500,
+        out: PathBuf::from("tournament"),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--config" => {
+                options.config = Config::load(Path::new(value)).map_err(|e| e.to_string())?
+            }
+            "--bot" => options.bots.push(value.clone()),
+            "--format" => {
+                options.format = match value.as_str() {
+                    "round-robin" => Format::RoundRobin,
+                    "swiss" => Format::Swiss,
+                    _ => return Err(format!("there's no {} format", value)),
+                }
+            }
+            "--rounds" => options.rounds = number(arg, value)?,
+            "--games" => options.games = number(arg, value)?,
+            "--seed" => options.seed = number(arg, value)?,
+            "--pieces" => options.pieces = number(arg, value)?,
+            "--out" => options.out = PathBuf::from(value),
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    if options.bots.len() < 2 {
+        return Err(String::from("a tournament needs at least 2 bots"));
+    }
+    if options.seed.checked_add(options.games).is_none() {
+        return Err(format!(
+            "--games {} from --seed {} runs past the last seed",
+            options.games, options.seed
+        ));
+    }
+    Ok(options)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (options, entrants) = match parse(&args).and_then(|options| {
+        let entrants = options
+            .bots
+            .iter()
+            .map(|bot| Entrant::parse(bot, &options.config))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Names go into the replays' file names, so they have to tell the bots apart there too
+        let mut names = HashSet::new();
+        for entrant in entrants.iter() {
+            if !names.insert(file_name(&entrant.name)) {
+                return Err(format!(
+                    "{} is named the same as another bot, once anything that can't be in a file name is left out",
+                    entrant.name
+                ));
+            }
+        }
+        Ok((options, entrants))
+    }) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let seeds = (options.seed..options.seed + options.games).collect();
+    let mut tournament = Tournament::new(&options.config, entrants, seeds, options.pieces)
+        .with_replays(&options.out.join("replays"));
+    let played = match options.format {
+        Format::RoundRobin => tournament.round_robin(),
+        Format::Swiss => tournament.swiss(options.rounds),
+    };
+    if let Err(e) = played {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = tournament.write_results(&options.out) {
+        eprintln!("couldn't write results to {}: {}", options.out.display(), e);
+        std::process::exit(1);
+    }
+    print!("{}", tournament);
+}
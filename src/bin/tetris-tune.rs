@@ -0,0 +1,176 @@
+// Evolves the heuristic bot's weights over generations of headless games, checkpointing as it goes
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use tetris_raylib_rs::{
+    config::Config,
+    tune::{self, Trial, Tuner},
+};
+
+const USAGE: &str =
+    "usage: tetris-tune [--config <file>] [--generations <n>] [--population <n>] [--elite <n>] \
+                     [--seeds <n>] [--seed <first seed>] [--pieces <limit>] [--sigma <spread>] \
+                     [--checkpoint <file>] [--resume]";
+
+/// How to tune, from the command line
+struct Options {
+    // Rules to play by, and the weights to start from
+    config: Config,
+    generations: u32,
+    population: usize,
+    elite: usize,
+    // Every candidate plays the same games, on seeds counting up from `seed`
+    seeds: u64,
+    seed: u64,
+    pieces: u32,
+    sigma: f32,
+    checkpoint: PathBuf,
+    // Carry on from the checkpoint instead of starting over
+    resume: bool,
+}
+
+/// `value` of the option `arg` as a number of whichever type it's kept as
+fn number<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} has to be a number, got {}", arg, value))
+}
+
+fn parse(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        config: Config::default(),
+        generations: 20,
+        population: 16,
+        elite: 4,
+        seeds: 8,
+        seed: 0,
+        pieces: 500,
+        sigma: 1.0,
+        checkpoint: PathBuf::from("tune.json"),
+        resume: false,
+    };
+    // Options that decide what's played, which a checkpoint already has
+    let mut fixed = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--resume" {
+            options.resume = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        if [
+            "--config",
+            "--population",
+            "--elite",
+            "--seeds",
+            "--seed",
+            "--pieces",
+            "--sigma",
+        ]
+        .contains(&arg.as_str())
+        {
+            fixed.push(arg.as_str());
+        }
+        match arg.as_str() {
+            "--config" => {
+                options.config = Config::load(Path::new(value)).map_err(|e| e.to_string())?
+            }
+            "--generations" => options.generations = number(arg, value)?,
+            "--population" => options.population = number(arg, value)?,
+            "--elite" => options.elite = number(arg, value)?,
+            "--seeds" => options.seeds = number(arg, value)?,
+            "--seed" => options.seed = number(arg, value)?,
+            "--pieces" => options.pieces = number(arg, value)?,
+            "--sigma" => options.sigma = number(arg, value)?,
+            "--checkpoint" => options.checkpoint = PathBuf::from(value),
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    if options.resume && !fixed.is_empty() {
+        return Err(format!(
+            "{} can't be given with --resume, the checkpoint already says what to play",
+            fixed.join(", ")
+        ));
+    }
+    if options.seed.checked_add(options.seeds).is_none() {
+        return Err(format!(
+            "--seeds {} from --seed {} runs past the last seed",
+            options.seeds, options.seed
+        ));
+    }
+    Ok(options)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let mut tuner = if options.resume {
+        match Tuner::load(&options.checkpoint) {
+            Ok(tuner) => tuner,
+            Err(e) => {
+                eprintln!(
+                    "couldn't resume from {}: {}",
+                    options.checkpoint.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let trial = Trial {
+            seeds: (options.seed..options.seed + options.seeds).collect(),
+            pieces: options.pieces,
+            settings: options.config.settings().clone(),
+        };
+        Tuner::new(
+            trial,
+            options.sigma,
+            options.population,
+            options.elite,
+            options.seed,
+        )
+    };
+
+    let trial = tuner.trial().clone();
+    let config = Config::from_settings(trial.settings.clone());
+    while tuner.generation() < options.generations {
+        let best = tuner
+            .step(|weights| tune::lines_fitness(&config, weights, &trial.seeds, trial.pieces))
+            .fitness;
+        let last = tuner.last();
+        let mean = last.iter().map(|candidate| candidate.fitness).sum::<f32>() / last.len() as f32;
+        println!(
+            "generation {}: best {:.1} lines, mean {:.1}, best so far {:.1}",
+            tuner.generation(),
+            best,
+            mean,
+            tuner.best().map_or(best, |best| best.fitness)
+        );
+        if let Err(e) = tuner.save(&options.checkpoint) {
+            eprintln!(
+                "couldn't write checkpoint to {}: {}",
+                options.checkpoint.display(),
+                e
+            );
+        }
+    }
+
+    if let Some(best) = tuner.best() {
+        let weights = toml::to_string(&best.weights).expect("weights are always valid toml");
+        println!(
+            "\nbest weights, clearing {:.1} lines a game:\n[bot.weights]\n{}",
+            best.fitness, weights
+        );
+    }
+}
@@ -0,0 +1,161 @@
+// A universe together with whatever drives it, so that single player, versus and replays all run the same way
+use std::sync::{Arc, Mutex};
+
+use super::crash::{self, SharedRecorder};
+use super::replay::Recorder;
+use super::source::{network::Message, Frame, InputSource, NetworkSender};
+use super::tetris_input::device::Device;
+use super::universe::Universe;
+use super::{Action, FrameInput};
+
+/// Most frames a board simulates in one go while catching up on a backlog
+const MAX_CATCH_UP: usize = 4;
+
+pub struct Board {
+    universe: Universe,
+    source: Box<dyn InputSource>,
+    // What a crash dump of the board is made of, only the local player's games get kept as replays too
+    recorder: Option<SharedRecorder>,
+    // Who our frames get sent to, in netplay
+    peer: Option<NetworkSender>,
+    // The source has run out, so there's nothing more to play
+    finished: bool,
+}
+
+impl Board {
+    pub fn new(universe: Universe, source: Box<dyn InputSource>) -> Self {
+        Board {
+            universe,
+            source,
+            recorder: None,
+            peer: None,
+            finished: false,
+        }
+    }
+
+    /// Records every game played on the board to the replay directory, dumping the recording if anything panics
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        let recorder = Arc::new(Mutex::new(recorder));
+        crash::track(&recorder);
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Records the board only in case something panics, for boards whose games aren't ours to keep
+    pub fn with_blackbox(self, recorder: Recorder) -> Self {
+        self.with_recorder(recorder.crash_only())
+    }
+
+    /// Sends every frame played on the board to `peer`
+    pub fn with_peer(mut self, peer: NetworkSender) -> Self {
+        self.peer = Some(peer);
+        self
+    }
+
+    /// Plays a frame, or a few if the source has fallen behind. Returns the new seed if the game was restarted
+    pub fn tick(&mut self, device: &dyn Device) -> Option<u64> {
+        let frames = 1 + self.source.backlog().min(MAX_CATCH_UP);
+        for _ in 0..frames {
+            if self.finished {
+                break;
+            }
+            let mut input = match self.source.next_frame(device, &self.universe) {
+                Frame::Input(input) => input,
+                Frame::Waiting => break,
+                Frame::Restart(seed) => {
+                    self.restart(seed);
+                    return Some(seed);
+                }
+                Frame::Finished => {
+                    self.finished = true;
+                    self.finish();
+                    break;
+                }
+            };
+
+            // Restarting picks a new seed, so it's never part of a recording
+            if input.actions.contains(&Action::Restart) && self.source.can_restart() {
+                let seed = rand::random();
+                self.restart(seed);
+                self.send(&Message::Restart { seed });
+                return Some(seed);
+            }
+            input.actions.retain(|action| *action != Action::Restart);
+            self.step(&input);
+        }
+        None
+    }
+
+    /// Simulates a single frame of `input`
+    fn step(&mut self, input: &FrameInput) {
+        if !self.universe.game().running() {
+            return;
+        }
+        self.send(&Message::Frame {
+            actions: input.actions.clone(),
+            down: input.down.clone(),
+        });
+
+        if let Some(recorder) = &self.recorder {
+            crash::lock(recorder).begin_frame(input);
+        }
+
+        self.universe.push_input(input);
+        self.universe.update();
+
+        if let Some(recorder) = &self.recorder {
+            let mut recorder = crash::lock(recorder);
+            recorder.record(input, &self.universe);
+            // The game just ended, so write it out
+            if !self.universe.game().running() {
+                recorder.finish();
+            }
+        }
+    }
+
+    fn send(&mut self, message: &Message) {
+        if let Some(peer) = &mut self.peer {
+            if let Err(e) = peer.send(message) {
+                eprintln!("lost connection to peer, {}", e);
+                self.peer = None;
+            }
+        }
+    }
+
+    /// Starts a new game on `seed`, with a new recording if the board is recorded
+    pub fn restart(&mut self, seed: u64) {
+        self.finish();
+        self.universe.restart(seed);
+        if let Some(recorder) = &self.recorder {
+            let mut recorder = crash::lock(recorder);
+            *recorder = recorder.restarted(&self.universe);
+        }
+    }
+
+    /// Writes out whatever was recorded of the current game
+    pub fn finish(&mut self) {
+        if let Some(recorder) = &self.recorder {
+            crash::lock(recorder).finish();
+        }
+    }
+
+    /// Swaps what drives the board, e.g. after the controls were rebound
+    pub fn set_source(&mut self, source: Box<dyn InputSource>) {
+        self.source = source;
+    }
+
+    /// Get a reference to the board's universe.
+    pub fn universe(&self) -> &Universe {
+        &self.universe
+    }
+
+    /// Get a mutable reference to the board's universe.
+    pub fn universe_mut(&mut self) -> &mut Universe {
+        &mut self.universe
+    }
+
+    /// Get whether the board's source has run out.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+}
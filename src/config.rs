@@ -1,3 +1,864 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use raylib::color::Color;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::source::tbp::TBP_HEIGHT;
+use super::tetris_input::{keys::input_from_name, Action};
+use super::tetromino::tetromino_type::TetrominoType;
+use super::universe::rotations::system::{RotationSystem, BUILTIN_SYSTEMS};
+use super::universe::{BUFFERS, DEFAULT_BUFFER, HEIGHTS, WIDTHS};
+
+/// Name of the directory that our config lives in, inside the user's config directory
+const CONFIG_DIR: &str = "tetris-rs";
+const CONFIG_FILE: &str = "config.toml";
+
+/// Written out on first run, kept in step with `Settings::default` by the tests
+pub const DEFAULT_CONFIG: &str = r#"# Configuration for tetris-rs
+# Anything left out falls back to its default, delete the file to get all of the defaults back
+
+[window]
+# Size of the window in pixels
+width = 1600
+height = 900
+fullscreen = false
+fps = 60
+title = "Tetris"
+
+[board]
+# Number of columns (4 to 40), and the number of visible rows (10 to 60)
+width = 10
+height = 20
+# Hidden rows above the visible ones, tetrominos spawn at the bottom of these
+buffer = 20
+# Hidden rows to draw faded above the board, from 0 to 2, so that tetrominos can be seen entering
+peek_rows = 1
+
+[handling]
+# Frames that left/right have to be held before they start repeating (delayed auto shift)
+das = 8
+# Frames between each repeated left/right move (auto repeat rate)
+arr = 4
+# Frames between each cell of soft drop
+sdf = 4
+# Frames that rotations have to be held before they repeat, and between each repeat
+rotate_delay = 8
+rotate_rate = 8
+# Same again for hard drop
+hard_drop_delay = 8
+hard_drop_rate = 8
+
+[rotation]
+# How tetrominos spawn and rotate, one of "srs", "ars" (as in TGM), "nrs" (as on the NES) or "classic" (SRS without kicks).
+# "pentominoes", "trominoes" and "mixed" (all of them and the tetrominos) play with other pieces.
+# Anything else is read as the path to a rotation system file
+system = "srs"
+# Kicks tried when a 180° rotation doesn't fit, one of "none", "srs_plus" (as in TETR.IO) or "nullpomino"
+kicks_180 = "srs_plus"
+
+[gamepad]
+# How far the analog stick has to be pushed, from 0.0 to 1.0, before it counts
+deadzone = 0.5
+
+[colors]
+# Colours in hex, without the leading #
+grid = "211A1E"
+line = "3A5683"
+i = "34344A"
+j = "5BC0EB"
+l = "FDE74C"
+o = "D4BEBE"
+s = "9BC53D"
+t = "C3423F"
+z = "4C6085"
+
+[audio]
+# Volumes, from 0.0 to 1.0
+master = 1.0
+music = 0.5
+sfx = 0.8
+
+[game]
+# Mode that the game starts in, one of "marathon", "versus_bot", "demo" (the bot playing on its own) or "netplay"
+mode = "marathon"
+# Initial rotation, tetrominos spawn already rotated while a rotate key is held down
+irs = true
+# Initial hold, tetrominos go straight into hold while the hold key is held down
+ihs = true
+# Lose as soon as any part of a tetromino locks above the visible board, rather than all of it
+partial_lock_out = false
+
+[bot]
+# Which bot plays in versus_bot and demo, "heuristic", "random" or "tbp" (a program speaking the Tetris Bot Protocol)
+kind = "heuristic"
+# Program to run for a "tbp" bot, and the arguments to run it with
+command = ""
+args = []
+# Tetrominos the bot places per second, higher is harder
+pps = 1.5
+
+[bot.weights]
+# How the heuristic bot weighs up the board after each placement it could make, positive is better
+landing_height = -4.5
+lines = 3.5
+height = -0.5
+holes = -8.0
+bumpiness = -0.25
+wells = -3.5
+row_transitions = -3.25
+column_transitions = -9.25
+t_slots = 2.0
+
+[network]
+# Address to listen on when hosting a netplay game, or to connect to when joining one
+address = "127.0.0.1:7878"
+# Whether this side waits for the other to connect, the other side has to set it to false
+host = true
+
+[keys]
+# Key names are raylib's without the KEY_ prefix, e.g. "LEFT", "Z", "SPACE", "KP_4", "LEFT_SHIFT"
+# Gamepad buttons follow the Xbox layout: "PAD_A", "PAD_B", "PAD_X", "PAD_Y", "PAD_LB", "PAD_RT", "PAD_START", ...
+# "PAD_UP", "PAD_DOWN", "PAD_LEFT" and "PAD_RIGHT" are the D-pad, "STICK_LEFT" etc. the left analog stick
+# Each action takes either one input or a list of them, and no input can be bound to two actions
+left = ["LEFT", "PAD_LEFT", "STICK_LEFT"]
+right = ["RIGHT", "PAD_RIGHT", "STICK_RIGHT"]
+soft_drop = ["DOWN", "PAD_DOWN", "STICK_DOWN"]
+hard_drop = ["SPACE", "PAD_UP"]
+rotate_cw = ["C", "UP", "PAD_A"]
+rotate_ccw = ["Z", "PAD_B"]
+rotate_180 = ["X", "PAD_Y"]
+hold = ["LEFT_SHIFT", "PAD_LB", "PAD_RB"]
+pause = ["P", "PAD_START"]
+restart = ["R", "PAD_BACK"]
+"#;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub fps: u32,
+    pub title: String,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            width: 1600,
+            height: 900,
+            fullscreen: false,
+            fps: 60,
+            title: String::from("Tetris"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BoardSettings {
+    pub width: u32,
+    pub height: u32,
+    pub buffer: u32,
+    pub peek_rows: u32,
+}
+
+impl Default for BoardSettings {
+    fn default() -> Self {
+        BoardSettings {
+            width: 10,
+            height: 20,
+            buffer: DEFAULT_BUFFER,
+            peek_rows: 1,
+        }
+    }
+}
+
+/// How quickly held keys repeat, all in frames
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Handling {
+    pub das: u32,
+    pub arr: u32,
+    pub sdf: u32,
+    pub rotate_delay: u32,
+    pub rotate_rate: u32,
+    pub hard_drop_delay: u32,
+    pub hard_drop_rate: u32,
+}
+
+impl Handling {
+    pub fn each(&self) -> [(&'static str, u32); 7] {
+        [
+            ("das", self.das),
+            ("arr", self.arr),
+            ("sdf", self.sdf),
+            ("rotate_delay", self.rotate_delay),
+            ("rotate_rate", self.rotate_rate),
+            ("hard_drop_delay", self.hard_drop_delay),
+            ("hard_drop_rate", self.hard_drop_rate),
+        ]
+    }
+
+    pub fn each_mut(&mut self) -> [(&'static str, &mut u32); 7] {
+        [
+            ("das", &mut self.das),
+            ("arr", &mut self.arr),
+            ("sdf", &mut self.sdf),
+            ("rotate_delay", &mut self.rotate_delay),
+            ("rotate_rate", &mut self.rotate_rate),
+            ("hard_drop_delay", &mut self.hard_drop_delay),
+            ("hard_drop_rate", &mut self.hard_drop_rate),
+        ]
+    }
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: 8,
+            arr: 4,
+            sdf: 4,
+            rotate_delay: 8,
+            rotate_rate: 8,
+            hard_drop_delay: 8,
+            hard_drop_rate: 8,
+        }
+    }
+}
+
+/// Hex colours for the board and for each tetromino
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ColorSettings {
+    pub grid: String,
+    pub line: String,
+    pub i: String,
+    pub j: String,
+    pub l: String,
+    pub o: String,
+    pub s: String,
+    pub t: String,
+    pub z: String,
+}
+
+impl ColorSettings {
+    fn each(&self) -> [(&'static str, &String); 9] {
+        [
+            ("grid", &self.grid),
+            ("line", &self.line),
+            ("i", &self.i),
+            ("j", &self.j),
+            ("l", &self.l),
+            ("o", &self.o),
+            ("s", &self.s),
+            ("t", &self.t),
+            ("z", &self.z),
+        ]
+    }
+}
+
+impl Default for ColorSettings {
+    fn default() -> Self {
+        ColorSettings {
+            grid: String::from("211A1E"),
+            line: String::from("3A5683"),
+            i: String::from("34344A"),
+            j: String::from("5BC0EB"),
+            l: String::from("FDE74C"),
+            o: String::from("D4BEBE"),
+            s: String::from("9BC53D"),
+            t: String::from("C3423F"),
+            z: String::from("4C6085"),
+        }
+    }
+}
+
+/// Which kick table 180° rotations use
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Kicks180 {
+    // Only turns around where there's room to do so in place
+    None,
+    #[default]
+    SrsPlus,
+    Nullpomino,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RotationSettings {
+    pub system: String,
+    pub kicks_180: Kicks180,
+}
+
+impl Default for RotationSettings {
+    fn default() -> Self {
+        RotationSettings {
+            system: String::from("srs"),
+            kicks_180: Kicks180::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GamepadSettings {
+    pub deadzone: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        GamepadSettings { deadzone: 0.5 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: 1.0,
+            music: 0.5,
+            sfx: 0.8,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    #[default]
+    Marathon,
+    // Side by side against a computer player, both getting the same tetrominos
+    VersusBot,
+    // Side by side against someone on another computer
+    Netplay,
+    // The bot playing on its own, starting over whenever it tops out
+    Demo,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GameSettings {
+    pub mode: GameMode,
+    pub irs: bool,
+    pub ihs: bool,
+    pub partial_lock_out: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            mode: GameMode::default(),
+            irs: true,
+            ihs: true,
+            partial_lock_out: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BotKind {
+    // Weighs up every placement it can reach, see `BotWeights`
+    #[default]
+    Heuristic,
+    // Drops tetrominos anywhere
+    Random,
+    // A program of its own that speaks the Tetris Bot Protocol, see `BotSettings::command`
+    Tbp,
+}
+
+/// What the heuristic bot makes of a board, each feature is multiplied by its weight and the total is the score
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BotWeights {
+    // Row that the tetromino locks on
+    pub landing_height: f32,
+    // Lines cleared by the tetromino
+    pub lines: f32,
+    // Sum of the heights of every column
+    pub height: f32,
+    // Empty cells with something above them
+    pub holes: f32,
+    // Sum of the differences in height between neighbouring columns
+    pub bumpiness: f32,
+    // Depth of every well, where a deeper well counts for more than its depth
+    pub wells: f32,
+    // Changes between filled and empty going along each row, the walls count as filled
+    pub row_transitions: f32,
+    // Changes between filled and empty going up each column, the floor counts as filled
+    pub column_transitions: f32,
+    // Holes that a T could spin into and clear with
+    pub t_slots: f32,
+}
+
+impl Default for BotWeights {
+    fn default() -> Self {
+        BotWeights {
+            landing_height: -4.5,
+            lines: 3.5,
+            height: -0.5,
+            holes: -8.0,
+            bumpiness: -0.25,
+            wells: -3.5,
+            row_transitions: -3.25,
+            column_transitions: -9.25,
+            t_slots: 2.0,
+        }
+    }
+}
+
+impl BotWeights {
+    /// Every weight along with its name in the config file
+    pub fn each(&self) -> [(&'static str, f32); 9] {
+        [
+            ("landing_height", self.landing_height),
+            ("lines", self.lines),
+            ("height", self.height),
+            ("holes", self.holes),
+            ("bumpiness", self.bumpiness),
+            ("wells", self.wells),
+            ("row_transitions", self.row_transitions),
+            ("column_transitions", self.column_transitions),
+            ("t_slots", self.t_slots),
+        ]
+    }
+
+    pub fn each_mut(&mut self) -> [(&'static str, &mut f32); 9] {
+        [
+            ("landing_height", &mut self.landing_height),
+            ("lines", &mut self.lines),
+            ("height", &mut self.height),
+            ("holes", &mut self.holes),
+            ("bumpiness", &mut self.bumpiness),
+            ("wells", &mut self.wells),
+            ("row_transitions", &mut self.row_transitions),
+            ("column_transitions", &mut self.column_transitions),
+            ("t_slots", &mut self.t_slots),
+        ]
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BotSettings {
+    pub kind: BotKind,
+    pub pps: f32,
+    // Program that a "tbp" bot runs as, and its arguments
+    pub command: String,
+    pub args: Vec<String>,
+    pub weights: BotWeights,
+}
+
+impl Default for BotSettings {
+    fn default() -> Self {
+        BotSettings {
+            kind: BotKind::default(),
+            pps: 1.5,
+            command: String::new(),
+            args: Vec::new(),
+            weights: BotWeights::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct NetworkSettings {
+    pub address: String,
+    pub host: bool,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            address: String::from("127.0.0.1:7878"),
+            host: true,
+        }
+    }
+}
+
+/// Names of the keys, gamepad buttons and stick directions bound to each action
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct KeySettings {
+    #[serde(deserialize_with = "one_or_many")]
+    pub left: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub right: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub soft_drop: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub hard_drop: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub rotate_cw: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub rotate_ccw: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub rotate_180: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub hold: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub pause: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub restart: Vec<String>,
+}
+
+impl KeySettings {
+    /// Get a reference to the names of the keys bound to `action`.
+    pub fn get(&self, action: Action) -> &Vec<String> {
+        match action {
+            Action::MoveLeft => &self.left,
+            Action::MoveRight => &self.right,
+            Action::SoftDrop => &self.soft_drop,
+            Action::HardDrop => &self.hard_drop,
+            Action::RotateCw => &self.rotate_cw,
+            Action::RotateCcw => &self.rotate_ccw,
+            Action::Rotate180 => &self.rotate_180,
+            Action::Hold => &self.hold,
+            Action::Pause => &self.pause,
+            Action::Restart => &self.restart,
+        }
+    }
+
+    /// Get a mutable reference to the names of the keys bound to `action`.
+    pub fn get_mut(&mut self, action: Action) -> &mut Vec<String> {
+        match action {
+            Action::MoveLeft => &mut self.left,
+            Action::MoveRight => &mut self.right,
+            Action::SoftDrop => &mut self.soft_drop,
+            Action::HardDrop => &mut self.hard_drop,
+            Action::RotateCw => &mut self.rotate_cw,
+            Action::RotateCcw => &mut self.rotate_ccw,
+            Action::Rotate180 => &mut self.rotate_180,
+            Action::Hold => &mut self.hold,
+            Action::Pause => &mut self.pause,
+            Action::Restart => &mut self.restart,
+        }
+    }
+
+    /// Names of the keys that are bound to more than one action, or more than once to the same action
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut seen: Vec<String> = Vec::new();
+        let mut conflicts: Vec<String> = Vec::new();
+        for (_, names) in self.each() {
+            for name in names.iter() {
+                let name = name.to_ascii_uppercase();
+                if seen.contains(&name) {
+                    if !conflicts.contains(&name) {
+                        conflicts.push(name);
+                    }
+                } else {
+                    seen.push(name);
+                }
+            }
+        }
+        conflicts
+    }
+
+    pub fn each(&self) -> impl Iterator<Item = (Action, &Vec<String>)> {
+        Action::ALL
+            .iter()
+            .map(move |action| (*action, self.get(*action)))
+    }
+}
+
+impl Default for KeySettings {
+    fn default() -> Self {
+        let keys = |names: &[&str]| names.iter().map(|name| String::from(*name)).collect();
+        KeySettings {
+            left: keys(&["LEFT", "PAD_LEFT", "STICK_LEFT"]),
+            right: keys(&["RIGHT", "PAD_RIGHT", "STICK_RIGHT"]),
+            soft_drop: keys(&["DOWN", "PAD_DOWN", "STICK_DOWN"]),
+            hard_drop: keys(&["SPACE", "PAD_UP"]),
+            rotate_cw: keys(&["C", "UP", "PAD_A"]),
+            rotate_ccw: keys(&["Z", "PAD_B"]),
+            rotate_180: keys(&["X", "PAD_Y"]),
+            hold: keys(&["LEFT_SHIFT", "PAD_LB", "PAD_RB"]),
+            pause: keys(&["P", "PAD_START"]),
+            restart: keys(&["R", "PAD_BACK"]),
+        }
+    }
+}
+
+/// Lets a binding be written as either `"Z"` or `["Z", "Y"]`
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(name) => vec![name],
+        OneOrMany::Many(names) => names,
+    })
+}
+
+/// Everything that can be set from the config file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub window: WindowSettings,
+    pub board: BoardSettings,
+    pub handling: Handling,
+    pub rotation: RotationSettings,
+    pub gamepad: GamepadSettings,
+    pub colors: ColorSettings,
+    pub audio: AudioSettings,
+    pub game: GameSettings,
+    pub bot: BotSettings,
+    pub network: NetworkSettings,
+    pub keys: KeySettings,
+}
+
+impl Settings {
+    /// Checks that every value is one that the game can actually run with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::Invalid(message));
+
+        let window = &self.window;
+        if window.width < 320 || window.height < 240 {
+            return invalid(format!(
+                "window.width and window.height must be at least 320x240, got {}x{}",
+                window.width, window.height
+            ));
+        }
+        if !(1..=1000).contains(&window.fps) {
+            return invalid(format!(
+                "window.fps must be between 1 and 1000, got {}",
+                window.fps
+            ));
+        }
+
+        let sizes = [
+            ("width", self.board.width, WIDTHS),
+            ("height", self.board.height, HEIGHTS),
+            ("buffer", self.board.buffer, BUFFERS),
+        ];
+        for (name, size, range) in sizes.iter() {
+            if !range.contains(size) {
+                return invalid(format!(
+                    "board.{} must be between {} and {}, got {}",
+                    name,
+                    range.start(),
+                    range.end(),
+                    size
+                ));
+            }
+        }
+        if self.board.peek_rows > 2 {
+            return invalid(format!(
+                "board.peek_rows must be between 0 and 2, got {}",
+                self.board.peek_rows
+            ));
+        }
+
+        let handling = &self.handling;
+        for (name, rate) in [
+            ("arr", handling.arr),
+            ("sdf", handling.sdf),
+            ("rotate_rate", handling.rotate_rate),
+            ("hard_drop_rate", handling.hard_drop_rate),
+        ]
+        .iter()
+        {
+            if *rate == 0 {
+                return invalid(format!("handling.{} must be at least 1 frame", name));
+            }
+        }
+
+        let system = match RotationSystem::from_setting(&self.rotation.system) {
+            Err(e) => {
+                return invalid(format!(
+                    "rotation.system isn't one of {:?} or a rotation system file: {}",
+                    BUILTIN_SYSTEMS, e
+                ))
+            }
+            Ok(system) => system,
+        };
+        if system.widest() > self.board.width {
+            return invalid(format!(
+                "board.width must fit the widest piece of rotation.system, which takes {} columns",
+                system.widest()
+            ));
+        }
+
+        // Any faster than a tetromino a frame and it can't keep up anyway
+        if !(0.1..=60.0).contains(&self.bot.pps) {
+            return invalid(format!(
+                "bot.pps must be between 0.1 and 60.0, got {}",
+                self.bot.pps
+            ));
+        }
+        for (name, weight) in self.bot.weights.each().iter() {
+            if !weight.is_finite() {
+                return invalid(format!(
+                    "bot.weights.{} must be a number, got {}",
+                    name, weight
+                ));
+            }
+        }
+        // The protocol only describes the tetrominos under SRS, on boards of the standard size
+        if self.bot.kind == BotKind::Tbp {
+            if self.bot.command.is_empty() {
+                return invalid(String::from(
+                    "bot.command has to say which program to run for a \"tbp\" bot",
+                ));
+            }
+            if self.board.width != 10 {
+                return invalid(format!(
+                    "board.width must be 10 for a \"tbp\" bot, got {}",
+                    self.board.width
+                ));
+            }
+            if self.board.height + self.board.buffer > TBP_HEIGHT as u32 {
+                return invalid(format!(
+                    "board.height and board.buffer can add up to at most {} for a \"tbp\" bot, got {}",
+                    TBP_HEIGHT,
+                    self.board.height + self.board.buffer
+                ));
+            }
+            if system.types() != TetrominoType::TETROMINOS {
+                return invalid(String::from(
+                    "rotation.system must play with the tetrominos for a \"tbp\" bot",
+                ));
+            }
+            if system != RotationSystem::srs() {
+                return invalid(format!(
+                    "rotation.system must be \"srs\" for a \"tbp\" bot, got \"{}\"",
+                    self.rotation.system
+                ));
+            }
+        }
+
+        if self.network.address.is_empty() {
+            return invalid(String::from("network.address can't be empty"));
+        }
+
+        if !(0.0..1.0).contains(&self.gamepad.deadzone) {
+            return invalid(format!(
+                "gamepad.deadzone must be at least 0.0 and less than 1.0, got {}",
+                self.gamepad.deadzone
+            ));
+        }
+
+        for (name, hex) in self.colors.each().iter() {
+            if parse_color(hex).is_none() {
+                return invalid(format!(
+                    "colors.{} must be a 6 digit hex colour like \"3A5683\", got \"{}\"",
+                    name, hex
+                ));
+            }
+        }
+
+        let audio = &self.audio;
+        for (name, volume) in [
+            ("master", audio.master),
+            ("music", audio.music),
+            ("sfx", audio.sfx),
+        ]
+        .iter()
+        {
+            if !(0.0..=1.0).contains(volume) {
+                return invalid(format!(
+                    "audio.{} must be between 0.0 and 1.0, got {}",
+                    name, volume
+                ));
+            }
+        }
+
+        let mut bound: Vec<(Action, &String)> = Vec::new();
+        for (action, names) in self.keys.each() {
+            for name in names.iter() {
+                if input_from_name(name).is_none() {
+                    return invalid(format!(
+                        "keys.{} isn't a key or gamepad input we know of: \"{}\"",
+                        action.name(),
+                        name
+                    ));
+                }
+                if let Some((other, _)) = bound
+                    .iter()
+                    .find(|(_, other_name)| other_name.eq_ignore_ascii_case(name))
+                {
+                    return invalid(format!(
+                        "keys.{} and keys.{} are both bound to \"{}\"",
+                        other.name(),
+                        action.name(),
+                        name
+                    ));
+                }
+                bound.push((action, name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn parse_color(hex: &str) -> Option<Color> {
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Color::from_hex(hex).ok()
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Write(toml::ser::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "couldn't access config file: {}", e),
+            ConfigError::Parse(e) => write!(f, "config file isn't valid: {}", e),
+            ConfigError::Write(e) => write!(f, "couldn't write config file: {}", e),
+            ConfigError::Invalid(message) => write!(f, "config file isn't valid: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(e: toml::ser::Error) -> Self {
+        ConfigError::Write(e)
+    }
+}
+
+#[derive(Clone)]
 pub struct Config {
     fps: u32,
     w: u32,
@@ -6,25 +867,222 @@ pub struct Config {
     actual_w: f64,
     canvas_l: f64,
     canvas_r: f64,
+    // Where the text beside the board goes, on its left and right
+    text_l: f64,
+    text_r: f64,
+    text_top: f64,
+    // Read from the settings up front, since it might come from a file
+    rotation_system: RotationSystem,
+    settings: Settings,
+}
+
+/// How many more `[` than `]` there are in `line`, leaving out strings and comments
+fn bracket_depth(line: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '#') => break,
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// `text` with the values of the `[name]` table swapped for the `key = value` lines of `values`, keeping its
+/// comments and anything else in it. Values the table didn't have yet go at the end of it, and a table that isn't
+/// there at all goes at the end of the file
+fn replace_table(text: &str, name: &str, values: &str) -> String {
+    let mut values: Vec<(&str, &str)> = values
+        .lines()
+        .filter_map(|line| Some((line.split('=').next()?.trim(), line)))
+        .filter(|(key, _)| !key.is_empty())
+        .collect();
+    let header = format!("[{}]", name);
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_table = false;
+    // Lines left of a value that spans several of them, which get dropped along with it
+    let mut skipping = false;
+    let mut depth = 0;
+    // Where the rest of the values go, after the last line of the table that isn't blank
+    let mut end = None;
+
+    for line in text.lines() {
+        if skipping {
+            depth += bracket_depth(line);
+            skipping = depth > 0;
+            continue;
+        }
+        let trimmed = line.trim();
+        if depth == 0 && trimmed.starts_with('[') {
+            in_table = trimmed.split('#').next().map(str::trim) == Some(header.as_str());
+            if in_table {
+                lines.push(String::from(line));
+                end = Some(lines.len());
+                continue;
+            }
+        }
+        let key = trimmed.split('=').next().map(str::trim);
+        match values.iter().position(|(name, _)| Some(*name) == key) {
+            Some(idx) if in_table && trimmed.contains('=') => {
+                lines.push(String::from(values.remove(idx).1));
+                depth = bracket_depth(line);
+                skipping = depth > 0;
+                depth = depth.max(0);
+            }
+            _ => {
+                depth = (depth + bracket_depth(line)).max(0);
+                lines.push(String::from(line));
+            }
+        }
+        if in_table && !trimmed.is_empty() {
+            end = Some(lines.len());
+        }
+    }
+
+    let rest = values.iter().map(|(_, line)| String::from(*line));
+    match end {
+        Some(end) => {
+            lines.splice(end..end, rest);
+        }
+        None => {
+            lines.push(String::new());
+            lines.push(header);
+            lines.extend(rest);
+        }
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
 }
 
 impl Config {
     pub fn new(fps: u32, w: u32, h: u32, title: String) -> Self {
+        Config::from_settings(Settings {
+            window: WindowSettings {
+                width: w,
+                height: h,
+                fps,
+                title,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
+    pub fn from_settings(settings: Settings) -> Self {
+        let w = settings.window.width;
         let actual_w = w as f64 * (9_f64 / 32_f64);
         let canvas_l = (w as f64 - actual_w) / 2_f64;
         let canvas_r = canvas_l + actual_w;
 
         Config {
-            fps,
+            fps: settings.window.fps,
             w,
-            h,
-            title,
+            h: settings.window.height,
+            title: settings.window.title.clone(),
             actual_w,
             canvas_l,
             canvas_r,
+            text_l: 150_f64,
+            text_r: w as f64 - 400_f64,
+            text_top: 150_f64,
+            // Settings are validated before they get here, tuner checkpoints included, which loads the rotation system.
+            // This only falls back for ones made in code
+            rotation_system: RotationSystem::from_setting(&settings.rotation.system).unwrap_or_default(),
+            settings,
         }
     }
 
+    /// The layout for the `idx`th of `count` boards shown side by side, each getting an equal slice of the window
+    pub fn viewport(&self, idx: usize, count: usize) -> Config {
+        if count <= 1 {
+            return self.clone();
+        }
+        let slot = self.w as f64 / count as f64;
+        let slot_l = slot * idx as f64;
+        let actual_w = self.actual_w.min(slot * 9_f64 / 16_f64);
+        let canvas_l = slot_l + (slot - actual_w) / 2_f64;
+        // Previews are drawn at half the size of the board's cells
+        let preview = actual_w / self.settings.board.width as f64 / 2_f64;
+
+        Config {
+            actual_w,
+            canvas_l,
+            canvas_r: canvas_l + actual_w,
+            // There's no room beside the board, so the text goes under the held tetromino
+            text_l: canvas_l - 6_f64 * preview,
+            text_r: canvas_l + actual_w / 2_f64,
+            text_top: 12_f64 * preview,
+            ..self.clone()
+        }
+    }
+
+    /// Where the config file lives, if the platform has a config directory
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    /// Reads and validates the config file at `path`
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let settings: Settings = toml::from_str(&fs::read_to_string(path)?)?;
+        settings.validate()?;
+        Ok(Config::from_settings(settings))
+    }
+
+    /// Loads the user's config, writing out the defaults if there isn't one yet.
+    /// Anything wrong with it gets reported and the defaults are used instead
+    pub fn load_or_default() -> Self {
+        let path = match Config::path() {
+            Some(path) => path,
+            None => return Config::default(),
+        };
+
+        if !path.exists() {
+            if let Err(e) = Config::write_default(&path) {
+                eprintln!("failed to write default config: {}", e);
+            }
+            return Config::default();
+        }
+
+        match Config::load(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{} ({}), using the defaults", e, path.display());
+                Config::default()
+            }
+        }
+    }
+
+    pub fn write_default(path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, DEFAULT_CONFIG)
+    }
+
+    /// Writes the `[keys]` and `[handling]` of `settings`, which is all the controls menu changes, into the config
+    /// file at `path`. Everything else in it, comments included, stays as it was. Without a file there it starts
+    /// from the commented default one
+    pub fn save(settings: &Settings, path: &Path) -> Result<(), ConfigError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::from(DEFAULT_CONFIG),
+            Err(e) => return Err(e.into()),
+        };
+        let text = replace_table(&text, "handling", &toml::to_string(&settings.handling)?);
+        let text = replace_table(&text, "keys", &toml::to_string(&settings.keys)?);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
     /// Get a reference to the config's fps.
     pub fn fps(&self) -> &u32 {
         &self.fps
@@ -59,9 +1117,34 @@ impl Config {
     pub fn canvas_r(&self) -> &f64 {
         &self.canvas_r
     }
+
+    /// Get a reference to the config's text l.
+    pub fn text_l(&self) -> &f64 {
+        &self.text_l
+    }
+
+    /// Get a reference to the config's text r.
+    pub fn text_r(&self) -> &f64 {
+        &self.text_r
+    }
+
+    /// Get a reference to the config's text top.
+    pub fn text_top(&self) -> &f64 {
+        &self.text_top
+    }
+
+    /// Get a reference to the config's rotation system.
+    pub fn rotation_system(&self) -> &RotationSystem {
+        &self.rotation_system
+    }
+
+    /// Get a reference to the config's settings.
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
 }
 impl Default for Config {
     fn default() -> Self {
-        Self::new(60, 1600, 900, String::from("Tetris"))
+        Config::from_settings(Settings::default())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,85 @@
+// Dumping the game to a file whenever something panics, so that the crash can be replayed exactly
+use std::{
+    fs, panic,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, TryLockError, Weak},
+};
+
+use super::replay::{now, Recorder, ReplayError};
+
+/// Where crash dumps get written to
+pub const CRASH_DIR: &str = "crashes";
+
+/// Recording of the game being played, shared between the board that records it and the blackbox
+pub type SharedRecorder = Arc<Mutex<Recorder>>;
+
+/// Recording of every board that's still around, in the order they were tracked, any of which can be dumped if
+/// something panics
+static BLACKBOX: Mutex<Vec<Weak<Mutex<Recorder>>>> = Mutex::new(Vec::new());
+
+/// Installs a panic hook that dumps the blackbox before the usual panic message
+pub fn install() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        match dump(Path::new(CRASH_DIR), &info.to_string()) {
+            Ok(paths) => {
+                for path in paths {
+                    eprintln!("wrote crash dump to {}", path.display());
+                }
+            }
+            Err(e) => eprintln!("failed to write crash dump: {}", e),
+        }
+        default_hook(info);
+    }));
+}
+
+/// Locks `mutex`, even if something panicked while holding it, since a recording is still worth having then
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Dumps `recorder` too if anything panics, for as long as its board is around
+pub fn track(recorder: &SharedRecorder) {
+    let mut blackbox = lock(&BLACKBOX);
+    // Boards that have gone away leave nothing to dump
+    blackbox.retain(|tracked| tracked.strong_count() > 0);
+    blackbox.push(Arc::downgrade(recorder));
+}
+
+/// Writes the recording of the board that was partway through a frame into `dir`, as a replay that ends on the
+/// crashing frame, which seeking to simulates from the closest snapshot. If no board was, the panic came from
+/// somewhere else and every board gets written. Each file is named after its board's place in the blackbox
+pub fn dump(dir: &Path, message: &str) -> Result<Vec<PathBuf>, ReplayError> {
+    // We might have panicked while holding a lock, in which case there's nothing consistent to write
+    let mut blackbox = match BLACKBOX.try_lock() {
+        Ok(blackbox) => blackbox,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return Ok(Vec::new()),
+    };
+    let recorders: Vec<SharedRecorder> = std::mem::take(&mut *blackbox)
+        .iter()
+        .filter_map(Weak::upgrade)
+        .collect();
+    let mut replays = Vec::new();
+    for (board, recorder) in recorders.iter().enumerate() {
+        let recorder = match recorder.try_lock() {
+            Ok(recorder) => recorder,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => continue,
+        };
+        replays.push((board, recorder.mid_frame(), recorder.crash_replay(message)));
+    }
+    if replays.iter().any(|(_, mid_frame, _)| *mid_frame) {
+        replays.retain(|(_, mid_frame, _)| *mid_frame);
+    }
+
+    let mut paths = Vec::new();
+    for (board, _, replay) in replays {
+        fs::create_dir_all(dir)?;
+        let name = format!("{}-{}-board{}.json", now(), replay.header.seed, board);
+        let path = dir.join(name);
+        replay.save(&path)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
@@ -0,0 +1,307 @@
+// A reinforcement learning environment on top of the headless engine, in the style of OpenAI Gym: reset a game, then
+// step it with inputs or whole placements, getting back what the board looks like and a reward for each step
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::sim::parallel_map_mut;
+use crate::source::heuristic::Field;
+use crate::tetromino::{coord::Coord, tetromino_type::TetrominoType};
+use crate::universe::{game::GameOverReason, rotations::placement::Placement, Universe};
+use crate::Action;
+
+/// What an agent does with a step
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GymAction {
+    /// Presses a key for a single frame
+    Input(Action),
+    /// Puts the current tetromino at a placement, holding first if it's for whatever hold brings in, and hard
+    /// drops it. See `Env::placements` for the ones that can be reached
+    Place(Placement),
+}
+
+/// Everything an agent gets to see about a game
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Observation {
+    // Rows from the bottom up, hidden rows included, with `true` where the stack is
+    pub board: Vec<Vec<bool>>,
+    // The tetromino being played, and the cells it covers
+    pub piece: Placement,
+    pub cells: Vec<Coord>,
+    // Next queue, in order
+    pub queue: Vec<TetrominoType>,
+    pub hold: Option<TetrominoType>,
+    pub can_hold: bool,
+    pub combo: u32,
+    pub back_to_back: bool,
+}
+
+/// What happened during a step, besides the reward
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Info {
+    // Lines cleared and score gained during the step
+    pub lines: u32,
+    pub score: u32,
+    // Totals for the whole game so far
+    pub pieces: u32,
+    pub frames: u32,
+    pub game_over: Option<GameOverReason>,
+    // The action was a placement that can't be reached, so the tetromino was hard dropped where it was instead
+    pub invalid: bool,
+}
+
+/// How much each thing that happens in a step is worth, the reward is the sum of them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Rewards {
+    // Per line cleared
+    pub lines: f32,
+    // Per point of score gained
+    pub score: f32,
+    // Per step that doesn't end the game
+    pub survival: f32,
+    // Per hole made, negative to penalise them. Filling holes back in earns the opposite
+    pub holes: f32,
+    // Once, on the step that ends the game
+    pub game_over: f32,
+    // Per placement that can't be reached
+    pub invalid: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Rewards {
+            lines: 1.0,
+            score: 0.0,
+            survival: 0.0,
+            holes: 0.0,
+            game_over: 0.0,
+            invalid: 0.0,
+        }
+    }
+}
+
+/// Result of a step: what the game looks like after it, the reward, whether the game is over and what happened
+pub type StepResult = (Observation, f32, bool, Info);
+
+/// A single game for an agent to play
+#[derive(Clone)]
+pub struct Env {
+    universe: Universe,
+    rewards: Rewards,
+    // Holes after the last step, so that rewards only count the ones a step made
+    holes: u32,
+    frames: u32,
+}
+
+impl Env {
+    /// A game with the standard rules, rewarded by `rewards`
+    pub fn new(rewards: Rewards) -> Self {
+        Env::with_universe(Universe::from_seed(0), rewards)
+    }
+
+    /// A game with the rules from `config`, rewarded by `rewards`
+    pub fn from_config(config: &Config, rewards: Rewards) -> Self {
+        Env::with_universe(Universe::from_config(0, config), rewards)
+    }
+
+    fn with_universe(universe: Universe, rewards: Rewards) -> Self {
+        Env {
+            universe,
+            rewards,
+            holes: 0,
+            frames: 0,
+        }
+    }
+
+    /// Starts a new game on `seed`
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.universe.restart(seed);
+        self.holes = 0;
+        self.frames = 0;
+        self.observation()
+    }
+
+    /// Plays `action` for one frame. Stepping a game that's over does nothing
+    pub fn step(&mut self, action: GymAction) -> StepResult {
+        let before = self.universe.game().clone();
+        let mut invalid = false;
+        if *before.running() {
+            match action {
+                GymAction::Input(action) => self.universe.push_action(action),
+                GymAction::Place(placement) => {
+                    let inputs = self.inputs_to(&placement).unwrap_or_else(|| {
+                        invalid = true;
+                        vec![Action::HardDrop]
+                    });
+                    for input in inputs {
+                        self.universe.push_action(input);
+                    }
+                }
+            }
+            self.universe.update();
+            self.frames += 1;
+        }
+
+        let game = self.universe.game();
+        let done = !*game.running();
+        let holes = Field::from_universe(&self.universe).features().holes;
+        let info = Info {
+            lines: game.lines_cleared() - before.lines_cleared(),
+            score: game.score() - before.score(),
+            pieces: *game.pieces(),
+            frames: self.frames,
+            game_over: *game.game_over(),
+            invalid,
+        };
+
+        let rewards = &self.rewards;
+        let mut reward = rewards.lines * info.lines as f32
+            + rewards.score * info.score as f32
+            + rewards.holes * (holes as f32 - self.holes as f32);
+        if invalid {
+            reward += rewards.invalid;
+        }
+        if !*before.running() {
+            reward = 0.0;
+        } else if done {
+            reward += rewards.game_over;
+        } else {
+            reward += rewards.survival;
+        }
+        self.holes = holes;
+        (self.observation(), reward, done, info)
+    }
+
+    /// Every placement that the current tetromino, or whatever hold would bring in, can be locked at.
+    /// Each placement's piece says which of the two it's for
+    pub fn placements(&self) -> Vec<Placement> {
+        self.starts()
+            .iter()
+            .flat_map(|(_, start)| self.universe.moves(start))
+            .map(|m| m.placement)
+            .collect()
+    }
+
+    /// Where each tetromino that can be placed next starts, along with what to press first to get it
+    fn starts(&self) -> Vec<(Option<Action>, Placement)> {
+        let universe = &self.universe;
+        let focused = *universe.focused();
+        let mut starts = vec![(None, focused)];
+        let swap = (*universe.held()).or_else(|| universe.preview().front().copied());
+        if let Some(swap) = swap.filter(|swap| universe.can_hold() && *swap != focused.piece) {
+            starts.push((
+                Some(Action::Hold),
+                universe
+                    .rotation_system()
+                    .spawn_placement(swap, universe.dim()),
+            ));
+        }
+        starts
+    }
+
+    /// Inputs that lock a tetromino into the cells of `placement`, if it can get there
+    fn inputs_to(&self, placement: &Placement) -> Option<Vec<Action>> {
+        let rotation_system = self.universe.rotation_system();
+        let sorted = |placement: &Placement| {
+            let mut cells = rotation_system.cells(placement);
+            cells.sort_by_key(|coord| (coord.x, coord.y));
+            cells
+        };
+        let (first, start) = self
+            .starts()
+            .into_iter()
+            .find(|(_, start)| start.piece == placement.piece)?;
+        if placement.rotation as usize >= rotation_system.piece(placement.piece).states.len() {
+            return None;
+        }
+        // Placements are compared by their cells, a rotation that looks the same counts as the same placement
+        let target = sorted(placement);
+        let m = self
+            .universe
+            .moves(&start)
+            .into_iter()
+            .find(|m| sorted(&m.placement) == target)?;
+        Some(first.into_iter().chain(m.inputs).collect())
+    }
+
+    /// What the game looks like right now
+    pub fn observation(&self) -> Observation {
+        let universe = &self.universe;
+        let dim = universe.dim();
+        let mut board = vec![vec![false; *dim.w() as usize]; dim.total_h() as usize];
+        for tetromino in universe.stagnant_tetrominos().iter() {
+            for coord in tetromino.coords().iter() {
+                if let Some((x, y)) = dim.cell(coord) {
+                    board[y as usize][x as usize] = true;
+                }
+            }
+        }
+        let game = universe.game();
+        Observation {
+            board,
+            piece: *universe.focused(),
+            cells: universe.rotation_system().cells(universe.focused()),
+            queue: universe.preview().iter().copied().collect(),
+            hold: *universe.held(),
+            can_hold: universe.can_hold(),
+            combo: *game.combo(),
+            back_to_back: *game.back_to_back(),
+        }
+    }
+
+    /// Get a reference to the env's universe.
+    pub fn universe(&self) -> &Universe {
+        &self.universe
+    }
+}
+
+/// Many games stepped side by side, spread over every core. Games that end start over straight away on a new seed,
+/// the step that ended them still says so
+pub struct VecEnv {
+    envs: Vec<Env>,
+    // Seed that the next game to start over gets, counting up from the ones `reset` handed out
+    next_seed: u64,
+}
+
+impl VecEnv {
+    /// `count` copies of `env`
+    pub fn new(env: Env, count: usize) -> Self {
+        VecEnv {
+            envs: vec![env; count],
+            next_seed: 0,
+        }
+    }
+
+    /// Starts every game over, on `seed`, `seed + 1` and so on
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.next_seed = seed.wrapping_add(self.envs.len() as u64);
+        self.envs
+            .iter_mut()
+            .enumerate()
+            .map(|(idx, env)| env.reset(seed.wrapping_add(idx as u64)))
+            .collect()
+    }
+
+    /// Steps every game with its own action, `actions` has to have one for each game. A game that ends gets
+    /// the observation of the one it started over on, but is still marked as done
+    pub fn step(&mut self, actions: &[GymAction]) -> Vec<StepResult> {
+        assert_eq!(actions.len(), self.envs.len(), "every game needs an action");
+        let mut games: Vec<(&mut Env, GymAction)> =
+            self.envs.iter_mut().zip(actions.iter().copied()).collect();
+        let mut results = parallel_map_mut(&mut games, |(env, action)| env.step(*action));
+
+        for (env, result) in self.envs.iter_mut().zip(results.iter_mut()) {
+            if result.2 {
+                result.0 = env.reset(self.next_seed);
+                self.next_seed = self.next_seed.wrapping_add(1);
+            }
+        }
+        results
+    }
+
+    /// Get a reference to the vec env's games.
+    pub fn envs(&self) -> &[Env] {
+        &self.envs
+    }
+}
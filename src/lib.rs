@@ -1,7 +1,17 @@
 mod tetris_input;
-mod tetromino;
+pub mod tetromino;
 mod tests;
+pub mod board;
 pub mod config;
+pub mod crash;
+pub mod gym;
+pub mod menu;
+pub mod replay;
+pub mod save;
+pub mod sim;
+pub mod source;
+pub mod tournament;
+pub mod tune;
 
 pub mod universe;
 
@@ -12,6 +22,7 @@ use tetromino::*;
 use config::Config;
 
 use tetris_input::*;
+pub use tetris_input::{device, Action, FrameInput};
 
 
 
@@ -1,11 +1,163 @@
+use std::{net::TcpListener, path::Path};
+
 use raylib::prelude::*;
-use tetris_raylib_rs::{config::Config, universe::Universe};
+use tetris_raylib_rs::{
+    board::Board,
+    config::{Config, GameMode},
+    crash,
+    device::RaylibDevice,
+    menu::{ControlsMenu, MENU_KEY},
+    replay::{Player, Recorder, Replay},
+    save::{self, Autosave, SAVE_PATH},
+    source::{
+        network::{self, NetworkError},
+        BotSource, InputSource, KeyboardSource,
+    },
+    universe::Universe,
+};
+
+/// Either playing live (and recording it), watching a replay, or deciding whether to resume a saved game
+enum Mode {
+    // The local player's board always comes first
+    Live(Vec<Board>),
+    Playback(Box<Player>, Box<Universe>),
+    Resume(Box<Universe>),
+}
+
+/// The local player's board, playing `universe` from the keyboard and gamepad while recording it
+fn local_board(config: &Config, universe: Universe) -> Board {
+    let recorder = Recorder::new(&universe, &config.settings().handling);
+    Board::new(
+        universe,
+        Box::new(KeyboardSource::from_settings(config.settings())),
+    )
+    .with_recorder(recorder)
+}
+
+/// Someone else's board, or the bot's, driven by `source` and only recorded in case it crashes
+fn opponent_board(
+    config: &Config,
+    universe: Universe,
+    source: Box<dyn InputSource>,
+    player: &str,
+) -> Board {
+    let recorder = Recorder::new(&universe, &config.settings().handling).with_player(player);
+    Board::new(universe, source).with_blackbox(recorder)
+}
+
+/// The bot from the config, exiting if it's a program that won't start
+fn bot_source(config: &Config) -> BotSource {
+    match BotSource::from_settings(config.settings()) {
+        Ok(bot) => bot,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Sets up the boards for whichever mode the config asks for, exiting if a peer or bot can't be reached
+fn live_boards(config: &Config) -> Vec<Board> {
+    let settings = config.settings();
+    match settings.game.mode {
+        GameMode::Marathon => vec![local_board(
+            config,
+            Universe::from_config(rand::random(), config),
+        )],
+        GameMode::VersusBot => {
+            let seed = rand::random();
+            let bot = bot_source(config);
+            vec![
+                local_board(config, Universe::from_config(seed, config)),
+                opponent_board(
+                    config,
+                    Universe::from_config(seed, config),
+                    Box::new(bot),
+                    "bot",
+                ),
+            ]
+        }
+        GameMode::Demo => vec![opponent_board(
+            config,
+            Universe::from_config(rand::random(), config),
+            Box::new(bot_source(config)),
+            "bot",
+        )],
+        GameMode::Netplay => {
+            let address = &settings.network.address;
+            let session = if settings.network.host {
+                println!("waiting for someone to connect to {}", address);
+                TcpListener::bind(address)
+                    .map_err(NetworkError::from)
+                    .and_then(|listener| network::host(&listener, rand::random()))
+            } else {
+                network::connect(address)
+            };
+            match session {
+                Ok(session) => vec![
+                    local_board(config, Universe::from_config(session.seed, config))
+                        .with_peer(session.sender),
+                    opponent_board(
+                        config,
+                        Universe::from_config(session.seed, config),
+                        Box::new(session.source),
+                        "peer",
+                    ),
+                ],
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
 
 fn main() {
-    let config = Config::default();
-    let mut universe = Universe::default();
+    let mut config = Config::load_or_default();
+    crash::install();
+    let mut autosave = Autosave::default();
 
-    init();
+    // `--replay <file>` watches a recorded game instead of playing one
+    let args: Vec<String> = std::env::args().collect();
+    let mut mode = match args.iter().position(|arg| arg == "--replay") {
+        Some(idx) => {
+            let path = match args.get(idx + 1) {
+                Some(path) => path,
+                None => {
+                    eprintln!("usage: {} --replay <file>", args[0]);
+                    std::process::exit(1);
+                }
+            };
+            match Replay::load(Path::new(path)) {
+                Ok(replay) => {
+                    let player = Player::new(replay);
+                    let mut universe = player.universe();
+                    universe.configure(&config);
+                    Mode::Playback(Box::new(player), Box::new(universe))
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        // Only marathon games are saved, there's no resuming a game against someone else
+        None if config.settings().game.mode != GameMode::Marathon => {
+            Mode::Live(live_boards(&config))
+        }
+        None => match save::load(Path::new(SAVE_PATH)) {
+            Ok(saved) => Mode::Resume(Box::new(saved)),
+            // Nothing was left unfinished last time
+            Err(save::SaveError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                Mode::Live(live_boards(&config))
+            }
+            Err(e) => {
+                eprintln!("couldn't resume the last game, {}", e);
+                Mode::Live(live_boards(&config))
+            }
+        },
+    };
 
     let (mut rl, thread) = raylib::init()
         .size(*config.w() as i32, *config.h() as i32)
@@ -13,8 +165,12 @@ fn main() {
         .build();
 
     rl.set_target_fps(*config.fps());
+    if config.settings().window.fullscreen {
+        rl.toggle_fullscreen();
+    }
 
-    let mut audio = RaylibAudio::init_audio_device();
+    let audio = RaylibAudio::init_audio_device();
+    audio.set_master_volume(config.settings().audio.master);
     // match Music::load_music_stream(&thread, "../resources/cool.wav") {
     //     Ok(mut m) => RaylibAudio::play_music_stream(&mut audio, &mut m),
     //     Err(e) => {
@@ -22,12 +178,131 @@ fn main() {
     //     }
     // }
 
-    // Debug, create new tetromino and add it to the universe
+    // The controls menu sits on top of a live game, which waits until it's closed
+    let mut menu: Option<ControlsMenu> = None;
+
     while !rl.window_should_close() {
-        universe.tick(&rl);
+        if let Some(controls) = &mut menu {
+            if let Some(settings) = controls.tick(&mut rl) {
+                match Config::path() {
+                    Some(path) => {
+                        if let Err(e) = Config::save(&settings, &path) {
+                            eprintln!("failed to save controls: {}", e);
+                        }
+                    }
+                    None => eprintln!(
+                        "nowhere to save controls to, they'll only last until the game is closed"
+                    ),
+                }
+                config = Config::from_settings(settings);
+                if let Mode::Live(boards) = &mut mode {
+                    // There's no one at the keyboard to hand the board to in a demo
+                    if config.settings().game.mode != GameMode::Demo {
+                        boards[0].set_source(Box::new(KeyboardSource::from_settings(
+                            config.settings(),
+                        )));
+                    }
+                    for board in boards.iter_mut() {
+                        board.universe_mut().configure(&config);
+                    }
+                }
+                menu = None;
+            }
+        } else if matches!(mode, Mode::Live(_)) && rl.is_key_pressed(MENU_KEY) {
+            menu = Some(ControlsMenu::new(config.settings()));
+        } else {
+            match &mut mode {
+                Mode::Live(boards) => {
+                    let device = RaylibDevice::new(&rl, config.settings().gamepad.deadzone);
+                    let restarted = boards[0].tick(&device);
+                    for board in boards.iter_mut().skip(1) {
+                        board.tick(&device);
+                    }
+                    match config.settings().game.mode {
+                        GameMode::Marathon => {
+                            autosave.tick(boards[0].universe(), Path::new(SAVE_PATH))
+                        }
+                        // The bot starts over whenever the player does, on the same tetrominos
+                        GameMode::VersusBot => {
+                            if let Some(seed) = restarted {
+                                for board in boards.iter_mut().skip(1) {
+                                    board.restart(seed);
+                                }
+                            }
+                        }
+                        GameMode::Demo => {
+                            if !*boards[0].universe().game().running() {
+                                boards[0].restart(rand::random());
+                            }
+                        }
+                        GameMode::Netplay => {}
+                    }
+                }
+                Mode::Playback(player, universe) => player.tick(&rl, universe),
+                Mode::Resume(saved) => {
+                    let resume = if rl.is_key_pressed(KeyboardKey::KEY_Y) {
+                        Some(true)
+                    } else if rl.is_key_pressed(KeyboardKey::KEY_N) {
+                        Some(false)
+                    } else {
+                        None
+                    };
+                    if let Some(resume) = resume {
+                        let mut universe = Universe::from_config(rand::random(), &config);
+                        if resume {
+                            universe.restore(saved);
+                        }
+                        // A save only gets resumed once
+                        save::discard(Path::new(SAVE_PATH));
+                        mode = Mode::Live(vec![local_board(&config, universe)]);
+                    }
+                }
+            }
+        }
 
         let mut d = rl.begin_drawing(&thread);
 
-        universe.render(&mut d, &config);
+        let palette = match &mode {
+            Mode::Live(boards) => {
+                d.clear_background(boards[0].universe().color_palette().grid());
+                for (idx, board) in boards.iter().enumerate() {
+                    board
+                        .universe()
+                        .render_board(&mut d, &config.viewport(idx, boards.len()));
+                }
+                boards[0].universe().color_palette().clone()
+            }
+            Mode::Playback(player, universe) => {
+                universe.render(&mut d, &config);
+                player.render(&mut d, &config, universe);
+                universe.color_palette().clone()
+            }
+            Mode::Resume(saved) => {
+                save::render_prompt(&mut d, &config, saved);
+                saved.color_palette().clone()
+            }
+        };
+        if let Some(controls) = &menu {
+            controls.render(&mut d, &config, &palette);
+        }
+    }
+
+    if let Mode::Live(boards) = &mut mode {
+        // Keep whatever was played of an unfinished game
+        for board in boards.iter_mut() {
+            board.finish();
+        }
+
+        // And put it aside to be continued next time
+        if config.settings().game.mode == GameMode::Marathon {
+            let universe = boards[0].universe();
+            if *universe.game().running() {
+                if let Err(e) = save::save(universe, Path::new(SAVE_PATH)) {
+                    eprintln!("failed to save game: {}", e);
+                }
+            } else {
+                save::discard(Path::new(SAVE_PATH));
+            }
+        }
     }
 }
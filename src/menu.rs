@@ -0,0 +1,262 @@
+// Screen for rebinding the keys of each action and changing how quickly they repeat, saved back to the config file
+use raylib::prelude::*;
+
+use super::config::{Config, Handling, KeySettings, Settings};
+use super::tetris_input::{
+    device::{Input, RaylibDevice},
+    keys::input_name,
+};
+use super::universe::color::ColorPalette;
+use super::Action;
+
+/// Opens and closes the controls menu
+pub const MENU_KEY: KeyboardKey = KeyboardKey::KEY_F1;
+
+const FONT_SIZE: i32 = 20;
+const ROW_HEIGHT: i32 = 28;
+
+/// A line of the menu that can be selected
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Row {
+    Binding(Action),
+    // Index into `Handling::each`
+    Handling(usize),
+    Reset,
+    Done,
+}
+
+pub struct ControlsMenu {
+    // Edited copy of the settings, only applied once the menu is closed
+    settings: Settings,
+    rows: Vec<Row>,
+    selected: usize,
+    // Waiting for a key to bind to the selected action
+    listening: bool,
+    // Shown at the bottom, for whatever went wrong last
+    message: Option<String>,
+}
+
+impl ControlsMenu {
+    pub fn new(settings: &Settings) -> Self {
+        let rows = Action::ALL
+            .iter()
+            .map(|action| Row::Binding(*action))
+            .chain((0..settings.handling.each().len()).map(Row::Handling))
+            .chain([Row::Reset, Row::Done].iter().copied())
+            .collect();
+        ControlsMenu {
+            settings: settings.clone(),
+            rows,
+            selected: 0,
+            listening: false,
+            message: None,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.rows.len();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = (self.selected + self.rows.len() - 1) % self.rows.len();
+    }
+
+    /// Get the row that's currently selected.
+    pub fn selected(&self) -> Row {
+        self.rows[self.selected]
+    }
+
+    /// Adds `input` to the inputs bound to `action`, conflicts with other actions are left for the player to sort out
+    pub fn bind(&mut self, action: Action, input: Input) {
+        let name = match input_name(input) {
+            Some(name) => String::from(name),
+            None => {
+                self.message = Some(String::from("that can't be bound"));
+                return;
+            }
+        };
+        let names = self.settings.keys.get_mut(action);
+        if !names.iter().any(|bound| bound.eq_ignore_ascii_case(&name)) {
+            names.push(name);
+        }
+        self.message = None;
+    }
+
+    /// Unbinds every key from `action`
+    pub fn clear(&mut self, action: Action) {
+        self.settings.keys.get_mut(action).clear();
+    }
+
+    /// Changes the `field`th handling value by `by` frames
+    pub fn adjust(&mut self, field: usize, by: i32) {
+        if let Some((_, value)) = self.settings.handling.each_mut().get_mut(field) {
+            **value = (**value as i32 + by).max(0) as u32;
+        }
+    }
+
+    /// Puts every key and handling value back to its default
+    pub fn reset(&mut self) {
+        self.settings.keys = KeySettings::default();
+        self.settings.handling = Handling::default();
+        self.message = None;
+    }
+
+    /// Names of the keys that are bound more than once
+    pub fn conflicts(&self) -> Vec<String> {
+        self.settings.keys.conflicts()
+    }
+
+    /// The edited settings, as long as they're valid
+    pub fn finish(&mut self) -> Option<Settings> {
+        match self.settings.validate() {
+            Ok(()) => Some(self.settings.clone()),
+            Err(e) => {
+                self.message = Some(e.to_string());
+                None
+            }
+        }
+    }
+
+    /// Get a reference to the menu's settings.
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Handles a frame of input, returning the new settings once the menu is closed
+    pub fn tick(&mut self, rl: &mut RaylibHandle) -> Option<Settings> {
+        if self.listening {
+            if let Some(input) = RaylibDevice::pressed_input(rl) {
+                self.listening = false;
+                if input == Input::Key(KeyboardKey::KEY_BACKSPACE) {
+                    return None;
+                }
+                if let Row::Binding(action) = self.selected() {
+                    self.bind(action, input);
+                }
+            }
+            return None;
+        }
+
+        if rl.is_key_pressed(MENU_KEY) {
+            return self.finish();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.select_next();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.select_previous();
+        }
+
+        let enter = rl.is_key_pressed(KeyboardKey::KEY_ENTER);
+        match self.selected() {
+            Row::Binding(action) => {
+                if enter {
+                    self.listening = true;
+                }
+                if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE)
+                    || rl.is_key_pressed(KeyboardKey::KEY_DELETE)
+                {
+                    self.clear(action);
+                }
+            }
+            Row::Handling(field) => {
+                if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) {
+                    self.adjust(field, 1);
+                }
+                if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
+                    self.adjust(field, -1);
+                }
+            }
+            Row::Reset => {
+                if enter {
+                    self.reset();
+                }
+            }
+            Row::Done => {
+                if enter {
+                    return self.finish();
+                }
+            }
+        }
+        None
+    }
+
+    pub fn render(&self, d: &mut RaylibDrawHandle, config: &Config, color_palette: &ColorPalette) {
+        d.draw_rectangle(
+            0,
+            0,
+            *config.w() as i32,
+            *config.h() as i32,
+            color_palette.grid().fade(0.9),
+        );
+
+        let color = *color_palette.line();
+        let conflicts = self.conflicts();
+        let x = 150;
+        let mut y = 80;
+        d.draw_text("CONTROLS", x, y, 50, color);
+        y += 70;
+
+        for (idx, row) in self.rows.iter().enumerate() {
+            let selected = idx == self.selected;
+            let color = if selected { Color::RAYWHITE } else { color };
+            let label = match row {
+                Row::Binding(action) => String::from(action.name()),
+                Row::Handling(field) => String::from(self.settings.handling.each()[*field].0),
+                Row::Reset => String::from("reset to defaults"),
+                Row::Done => String::from("save and close"),
+            };
+            d.draw_text(
+                &format!("{} {}", if selected { ">" } else { " " }, label),
+                x,
+                y,
+                FONT_SIZE,
+                color,
+            );
+
+            let mut value_x = x + 250;
+            match row {
+                Row::Binding(action) => {
+                    if selected && self.listening {
+                        d.draw_text(
+                            "press a key or gamepad button, backspace to cancel",
+                            value_x,
+                            y,
+                            FONT_SIZE,
+                            color,
+                        );
+                    } else {
+                        // Each key is drawn on its own, so that conflicting ones can stand out
+                        for name in self.settings.keys.get(*action).iter() {
+                            let key_color = if conflicts.contains(&name.to_ascii_uppercase()) {
+                                Color::RED
+                            } else {
+                                color
+                            };
+                            d.draw_text(name, value_x, y, FONT_SIZE, key_color);
+                            value_x += measure_text(name, FONT_SIZE) + 15;
+                        }
+                    }
+                }
+                Row::Handling(field) => {
+                    d.draw_text(
+                        &format!("< {} >", self.settings.handling.each()[*field].1),
+                        value_x,
+                        y,
+                        FONT_SIZE,
+                        color,
+                    );
+                }
+                Row::Reset | Row::Done => {}
+            }
+            y += ROW_HEIGHT;
+        }
+
+        y += ROW_HEIGHT;
+        let help = "up/down: select   enter: bind another key   backspace: unbind   left/right: change   f1: save and close";
+        d.draw_text(help, x, y, FONT_SIZE, color);
+        if let Some(message) = &self.message {
+            d.draw_text(message, x, y + ROW_HEIGHT, FONT_SIZE, Color::RED);
+        }
+    }
+}
@@ -0,0 +1,664 @@
+// Recording every game to a file, and playing those files back through the same input path as live play
+pub mod snapshot;
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::config::{Config, Handling, Kicks180};
+use super::source::ReplaySource;
+use super::universe::{game::GameOverReason, RotationSystem, Universe};
+use super::{Action, FrameInput};
+use snapshot::{Snapshot, SNAPSHOT_INTERVAL};
+
+/// Bumped whenever the layout of the replay file, or how its input plays out, changes
+pub const REPLAY_VERSION: u32 = 14;
+/// Number of frames between each state checksum
+const CHECKSUM_INTERVAL: u32 = 60;
+/// Where finished games get written to
+const REPLAY_DIR: &str = "replays";
+/// Playback speeds that up/down cycle through
+const SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const NORMAL_SPEED: usize = 2;
+/// Frames that `,` and `.` scrub by
+const SCRUB_FRAMES: u32 = 5 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReplayHeader {
+    pub version: u32,
+    pub seed: u64,
+    // How the pieces spawn and rotate, named after the system it came from, kept here once rather than in every
+    // snapshot
+    pub rotation_system: RotationSystem,
+    // The rest of the rules the game was played under
+    pub kicks_180: Kicks180,
+    pub irs: bool,
+    pub ihs: bool,
+    pub partial_lock_out: bool,
+    pub width: u32,
+    pub height: u32,
+    pub buffer: u32,
+    // Delay and rate of the horizontal movement keys, in frames
+    pub das: u32,
+    pub arr: u32,
+    pub player: String,
+    // Seconds since the unix epoch
+    pub date: u64,
+}
+
+/// A run of consecutive frames that all had the same input
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FrameRun {
+    pub len: u32,
+    pub actions: Vec<Action>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub down: Vec<Action>,
+}
+
+impl FrameRun {
+    /// The input of each of the run's frames
+    pub fn input(&self) -> FrameInput {
+        FrameInput {
+            actions: self.actions.clone(),
+            down: self.down.clone(),
+        }
+    }
+}
+
+/// Checksum of the universe after `frame` frames have been simulated
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Checksum {
+    pub frame: u32,
+    pub value: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub frames: Vec<FrameRun>,
+    pub checksums: Vec<Checksum>,
+    // Frames at which each tetromino locked, for stepping a piece at a time
+    pub locks: Vec<u32>,
+    pub snapshots: Vec<Snapshot>,
+    // Set on crash dumps, to the panic that ended the recording
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crash: Option<String>,
+    // How the recorded game was lost, if it was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_over: Option<GameOverReason>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    Version(u32),
+    // There's no snapshot of the game as it started, to play it back from
+    NoStart,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "couldn't access replay file: {}", e),
+            ReplayError::Format(e) => write!(f, "replay file is malformed: {}", e),
+            ReplayError::Version(v) => write!(
+                f,
+                "replay file is version {}, but only version {} can be played",
+                v, REPLAY_VERSION
+            ),
+            ReplayError::NoStart => write!(f, "replay file doesn't have the game as it started"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(e: serde_json::Error) -> Self {
+        ReplayError::Format(e)
+    }
+}
+
+impl Replay {
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Reads a replay, rejecting any version that we don't know how to play and any that doesn't start with a
+    /// snapshot of frame 0
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        let mut replay: Replay = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if replay.header.version != REPLAY_VERSION {
+            return Err(ReplayError::Version(replay.header.version));
+        }
+        if replay.snapshots.first().map(|snapshot| snapshot.frame) != Some(0) {
+            return Err(ReplayError::NoStart);
+        }
+        for snapshot in replay.snapshots.iter_mut() {
+            snapshot
+                .universe
+                .attach_rules(&replay.header.rotation_system);
+        }
+        Ok(replay)
+    }
+
+    /// Appends a frame's input, extending the last run if it's the same
+    pub fn push_frame(&mut self, input: &FrameInput) {
+        match self.frames.last_mut() {
+            Some(run) if run.actions == input.actions && run.down == input.down => run.len += 1,
+            _ => self.frames.push(FrameRun {
+                len: 1,
+                actions: input.actions.clone(),
+                down: input.down.clone(),
+            }),
+        }
+    }
+
+    /// Total number of frames in the replay
+    pub fn len(&self) -> u32 {
+        self.frames.iter().map(|run| run.len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Finds which run `frame` falls in, and how far into that run it is
+    pub(crate) fn locate(&self, frame: u32) -> (usize, u32) {
+        let mut start = 0;
+        for (idx, run) in self.frames.iter().enumerate() {
+            if frame < start + run.len {
+                return (idx, frame - start);
+            }
+            start += run.len;
+        }
+        (self.frames.len(), 0)
+    }
+
+    fn checksum_at(&self, frame: u32) -> Option<u64> {
+        self.checksums
+            .iter()
+            .find(|checksum| checksum.frame == frame)
+            .map(|checksum| checksum.value)
+    }
+}
+
+/// FNV-1a hash of everything that affects how the game plays out
+pub fn checksum(universe: &Universe) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |n: u32| {
+        for byte in n.to_le_bytes().iter() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+
+    let focused = universe.focused_tetromino();
+    for tetromino in universe
+        .stagnant_tetrominos()
+        .iter()
+        .chain(std::iter::once(&focused))
+    {
+        for coord in tetromino.coords() {
+            feed(coord.x as u32);
+            feed(coord.y as u32);
+        }
+    }
+    let game = universe.game();
+    feed(*game.ticks());
+    feed(*game.score());
+    feed(*game.lines_cleared());
+    feed(*game.level());
+    feed(*game.running() as u32);
+    feed(*game.paused() as u32);
+    feed(game.game_over().map_or(0, |reason| reason as u32 + 1));
+    // Nothing held counts as 0, so that it can't be mistaken for holding the first type
+    feed(universe.held().map_or(0, |held| held.index() as u32 + 1));
+    feed(universe.can_hold() as u32);
+    // Some rotations leave the cells where they were, e.g. the O's
+    feed(universe.focused().rotation);
+    for piece in universe.preview().iter() {
+        feed(piece.index() as u32);
+    }
+    let stream = universe.randomizer().peek();
+    feed(stream as u32);
+    feed((stream >> 32) as u32);
+
+    // The board and the rules it's played by
+    let dim = universe.dim();
+    feed(*dim.w());
+    feed(*dim.h());
+    feed(*dim.buffer());
+    feed(universe.kicks_180() as u32);
+    feed(universe.irs() as u32);
+    feed(universe.ihs() as u32);
+    feed(universe.partial_lock_out() as u32);
+    let rules = universe.rotation_system();
+    for byte in rules.name.bytes() {
+        feed(byte as u32);
+    }
+    for piece in rules.pieces.iter() {
+        feed(piece.piece.index() as u32);
+        feed(piece.centre_column as u32);
+        feed(piece.spawn);
+        feed(piece.pivot[0] as u32);
+        feed(piece.pivot[1] as u32);
+        for state in piece.states.iter() {
+            feed(state.len() as u32);
+            for [x, y] in state.iter() {
+                feed(*x as u32);
+                feed(*y as u32);
+            }
+        }
+        for kick in piece.kicks.iter() {
+            feed(kick.from);
+            feed(kick.to);
+            for [x, y] in kick.tests.iter() {
+                feed(*x as u32);
+                feed(*y as u32);
+            }
+        }
+    }
+
+    hash
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Records the input of each frame of a live game
+pub struct Recorder {
+    replay: Replay,
+    frame: u32,
+    pieces: u32,
+    saved: bool,
+    // Input of the frame being simulated, until it's recorded, so that a crash dump can end on it
+    pending: Option<FrameInput>,
+    // Only kept for crash dumps, so it's never written out when the game ends
+    crash_only: bool,
+}
+
+impl Recorder {
+    pub fn new(universe: &Universe, handling: &Handling) -> Self {
+        let player = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| String::from("player"));
+
+        Recorder {
+            replay: Replay {
+                header: ReplayHeader {
+                    version: REPLAY_VERSION,
+                    seed: universe.seed(),
+                    rotation_system: universe.rotation_system().clone(),
+                    kicks_180: universe.kicks_180(),
+                    irs: universe.irs(),
+                    ihs: universe.ihs(),
+                    partial_lock_out: universe.partial_lock_out(),
+                    width: *universe.dim().w(),
+                    height: *universe.dim().h(),
+                    buffer: *universe.dim().buffer(),
+                    das: handling.das,
+                    arr: handling.arr,
+                    player,
+                    date: now(),
+                },
+                frames: Vec::new(),
+                checksums: Vec::new(),
+                locks: Vec::new(),
+                snapshots: vec![Snapshot {
+                    frame: 0,
+                    universe: universe.clone(),
+                }],
+                crash: None,
+                game_over: None,
+            },
+            frame: 0,
+            pieces: *universe.game().pieces(),
+            saved: false,
+            pending: None,
+            crash_only: false,
+        }
+    }
+
+    /// Puts `player` down as who played the recorded games, instead of whoever is logged in
+    pub fn with_player(mut self, player: &str) -> Self {
+        self.replay.header.player = String::from(player);
+        self
+    }
+
+    /// Keeps the recording only for crash dumps, for games that aren't ours to keep as replays
+    pub fn crash_only(mut self) -> Self {
+        self.crash_only = true;
+        self
+    }
+
+    /// Notes the input of the frame that's about to be simulated, in case it never gets as far as `record`
+    pub fn begin_frame(&mut self, input: &FrameInput) {
+        self.pending = Some(input.clone());
+    }
+
+    /// Records the input that was fed into the frame that `universe` just simulated
+    pub fn record(&mut self, input: &FrameInput, universe: &Universe) {
+        self.pending = None;
+        self.replay.push_frame(input);
+
+        self.frame += 1;
+        if self.frame.is_multiple_of(CHECKSUM_INTERVAL) {
+            self.replay.checksums.push(Checksum {
+                frame: self.frame,
+                value: checksum(universe),
+            });
+        }
+        if self.frame.is_multiple_of(SNAPSHOT_INTERVAL) {
+            self.replay.snapshots.push(Snapshot {
+                frame: self.frame,
+                universe: universe.clone(),
+            });
+        }
+        if *universe.game().pieces() != self.pieces {
+            self.pieces = *universe.game().pieces();
+            self.replay.locks.push(self.frame);
+        }
+        self.replay.game_over = *universe.game().game_over();
+    }
+
+    /// A new recording of `universe` by the same player with the same handling, for when the game is restarted
+    pub fn restarted(&self, universe: &Universe) -> Self {
+        let header = &self.replay.header;
+        let mut recorder = Recorder::new(
+            universe,
+            &Handling {
+                das: header.das,
+                arr: header.arr,
+                ..Handling::default()
+            },
+        );
+        recorder.replay.header.player = header.player.clone();
+        recorder.crash_only = self.crash_only;
+        recorder
+    }
+
+    /// Get the number of frames that have been recorded.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Writes the recording to the replay directory, unless it's empty or already written
+    pub fn finish(&mut self) {
+        if self.saved || self.crash_only || self.replay.is_empty() {
+            return;
+        }
+        match self.save() {
+            Ok(path) => println!("saved replay to {}", path.display()),
+            Err(e) => eprintln!("failed to save replay: {}", e),
+        }
+        self.saved = true;
+    }
+
+    fn save(&self) -> Result<PathBuf, ReplayError> {
+        fs::create_dir_all(REPLAY_DIR)?;
+        let header = &self.replay.header;
+        let path = Path::new(REPLAY_DIR).join(format!("{}-{}.json", header.date, header.seed));
+        self.replay.save(&path)?;
+        Ok(path)
+    }
+
+    /// The recording so far, ending on the frame that was being simulated when it panicked with `message`
+    pub fn crash_replay(&self, message: &str) -> Replay {
+        let mut replay = self.replay.clone();
+        if let Some(input) = &self.pending {
+            replay.push_frame(input);
+        }
+        replay.crash = Some(String::from(message));
+        replay
+    }
+
+    /// Get a reference to the recorder's replay.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Get whether the recorder is partway through a frame, which is the case for the board that panicked.
+    pub fn mid_frame(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+/// Feeds a replay back into a universe, frame by frame
+pub struct Player {
+    source: ReplaySource,
+    speed: usize,
+    paused: bool,
+    // Fractional frames owed to the universe at slow/fast speeds
+    budget: f32,
+    // First frame whose checksum didn't match the recording
+    desync: Option<u32>,
+}
+
+impl Player {
+    /// Plays back `replay`, which has to start with a snapshot of frame 0, as recordings and `Replay::load` make
+    /// sure of
+    pub fn new(replay: Replay) -> Self {
+        Player {
+            source: ReplaySource::new(replay),
+            speed: NORMAL_SPEED,
+            paused: false,
+            budget: 0_f32,
+            desync: None,
+        }
+    }
+
+    /// A fresh universe in the state that the recorded game started in
+    pub fn universe(&self) -> Universe {
+        self.replay().snapshots[0].universe.clone()
+    }
+
+    /// Handles the playback controls, then simulates however many frames the current speed calls for
+    pub fn tick(&mut self, rl: &RaylibHandle, universe: &mut Universe) {
+        if rl.is_key_pressed(KeyboardKey::KEY_P) {
+            self.paused = !self.paused;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.speed = self.speed.saturating_sub(1);
+        }
+
+        // Stepping a piece at a time only makes sense while paused
+        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) {
+            self.paused = true;
+            self.step_piece(universe);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
+            self.paused = true;
+            self.step_back_piece(universe);
+        }
+
+        // Scrubbing
+        if rl.is_key_pressed(KeyboardKey::KEY_PERIOD) {
+            self.seek(universe, self.frame() + SCRUB_FRAMES);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_COMMA) {
+            self.seek(universe, self.frame().saturating_sub(SCRUB_FRAMES));
+        }
+        // Number keys jump to that tenth of the replay
+        let tenths = [
+            KeyboardKey::KEY_ZERO,
+            KeyboardKey::KEY_ONE,
+            KeyboardKey::KEY_TWO,
+            KeyboardKey::KEY_THREE,
+            KeyboardKey::KEY_FOUR,
+            KeyboardKey::KEY_FIVE,
+            KeyboardKey::KEY_SIX,
+            KeyboardKey::KEY_SEVEN,
+            KeyboardKey::KEY_EIGHT,
+            KeyboardKey::KEY_NINE,
+        ];
+        for (tenth, key) in tenths.iter().enumerate() {
+            if rl.is_key_pressed(*key) {
+                self.seek(universe, self.replay().len() * tenth as u32 / 10);
+            }
+        }
+
+        if self.paused {
+            return;
+        }
+
+        self.budget += SPEEDS[self.speed];
+        while self.budget >= 1_f32 {
+            self.budget -= 1_f32;
+            if !self.step(universe) {
+                self.budget = 0_f32;
+                break;
+            }
+        }
+    }
+
+    /// Simulates the next recorded frame, returns false once the replay has run out
+    pub fn step(&mut self, universe: &mut Universe) -> bool {
+        let input = match self.source.next_input() {
+            Some(input) => input,
+            None => return false,
+        };
+        universe.push_input(&input);
+        universe.update();
+
+        let frame = self.frame();
+        let replay = self.source.replay_mut();
+        if let Some(expected) = replay.checksum_at(frame) {
+            if self.desync.is_none() && expected != checksum(universe) {
+                self.desync = Some(frame);
+            }
+        }
+        // Remember where we've been, so that seeking back here is cheap
+        if frame.is_multiple_of(SNAPSHOT_INTERVAL) {
+            snapshot::insert(
+                &mut replay.snapshots,
+                Snapshot {
+                    frame,
+                    universe: universe.clone(),
+                },
+            );
+        }
+        true
+    }
+
+    /// Jumps to `frame` by restoring the closest snapshot before it and simulating forward
+    pub fn seek(&mut self, universe: &mut Universe, frame: u32) {
+        let frame = frame.min(self.replay().len());
+
+        // Only restore a snapshot if it actually saves us from simulating
+        if frame < self.frame()
+            || snapshot::nearest(&self.replay().snapshots, frame)
+                .is_some_and(|snapshot| snapshot.frame > self.frame())
+        {
+            let snapshot = snapshot::nearest(&self.replay().snapshots, frame)
+                .expect("the first snapshot is always at frame 0");
+            universe.restore(&snapshot.universe);
+            let start = snapshot.frame;
+            self.source.seek(start);
+        }
+
+        while self.frame() < frame && self.step(universe) {}
+        self.budget = 0_f32;
+    }
+
+    /// Plays until the current tetromino locks
+    pub fn step_piece(&mut self, universe: &mut Universe) {
+        let target = match self
+            .replay()
+            .locks
+            .iter()
+            .find(|lock| **lock > self.frame())
+        {
+            Some(lock) => *lock,
+            None => self.replay().len(),
+        };
+        self.seek(universe, target);
+    }
+
+    /// Goes back to just after the previous tetromino locked
+    pub fn step_back_piece(&mut self, universe: &mut Universe) {
+        let target = match self
+            .replay()
+            .locks
+            .iter()
+            .rev()
+            .find(|lock| **lock < self.frame())
+        {
+            Some(lock) => *lock,
+            None => 0,
+        };
+        self.seek(universe, target);
+    }
+
+    pub fn render(&self, d: &mut RaylibDrawHandle, config: &Config, universe: &Universe) {
+        let color = universe.color_palette().line();
+        d.draw_text(
+            &format!(
+                "REPLAY {}/{} x{}",
+                self.frame(),
+                self.replay().len(),
+                SPEEDS[self.speed]
+            ),
+            150,
+            300,
+            20,
+            color,
+        );
+        if self.paused {
+            d.draw_text("PAUSED", 150, 330, 20, color);
+        }
+        if let Some(message) = &self.replay().crash {
+            d.draw_text(
+                &format!("CRASHED: {}", message),
+                150,
+                *config.h() as i32 - 70,
+                20,
+                color,
+            );
+        }
+        if let Some(frame) = self.desync {
+            d.draw_text(
+                &format!("DESYNC AT FRAME {}", frame),
+                150,
+                *config.h() as i32 - 100,
+                20,
+                color,
+            );
+        }
+    }
+
+    /// Get a reference to the player's replay.
+    pub fn replay(&self) -> &Replay {
+        self.source.replay()
+    }
+
+    /// Get the number of frames that have been played back.
+    pub fn frame(&self) -> u32 {
+        self.source.frame()
+    }
+
+    /// Get the first frame that didn't match its recorded checksum, if any.
+    pub fn desync(&self) -> Option<u32> {
+        self.desync
+    }
+}
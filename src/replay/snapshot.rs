@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize, Serializer};
+
+use super::super::universe::Universe;
+
+/// Seconds of play between each snapshot
+pub const SNAPSHOT_SECONDS: u32 = 5;
+/// Replays are simulated at a fixed 60 frames a second
+pub const SNAPSHOT_INTERVAL: u32 = SNAPSHOT_SECONDS * 60;
+
+/// The full state of the universe after `frame` frames have been simulated,
+/// so that seeking only ever has to simulate forward from the closest one
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub frame: u32,
+    // Written without the rotation system, which the replay's header has the one copy of
+    #[serde(serialize_with = "without_rules")]
+    pub universe: Universe,
+}
+
+fn without_rules<S: Serializer>(universe: &Universe, serializer: S) -> Result<S::Ok, S::Error> {
+    universe.without_rules().serialize(serializer)
+}
+
+/// Inserts a snapshot, keeping the list sorted by frame and free of duplicates
+pub fn insert(snapshots: &mut Vec<Snapshot>, snapshot: Snapshot) {
+    if let Err(idx) = snapshots.binary_search_by_key(&snapshot.frame, |s| s.frame) {
+        snapshots.insert(idx, snapshot);
+    }
+}
+
+/// The latest snapshot taken at or before `frame`
+pub fn nearest(snapshots: &[Snapshot], frame: u32) -> Option<&Snapshot> {
+    snapshots.iter().rev().find(|snapshot| snapshot.frame <= frame)
+}
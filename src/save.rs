@@ -0,0 +1,176 @@
+// Saving an in-progress game when the window is closed, and resuming it on the next start
+use std::{fmt, fs, io, path::Path};
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::config::Config;
+use super::replay::checksum;
+use super::universe::Universe;
+
+/// Where the in-progress game is kept between runs
+pub const SAVE_PATH: &str = "save.json";
+/// Identifies the file as one of our saves
+const SAVE_MAGIC: &str = "tetris-rs-save";
+/// Bumped whenever the layout of the save file changes
+pub const SAVE_VERSION: u32 = 5;
+/// Number of pieces placed between each autosave
+pub const AUTOSAVE_PIECES: u32 = 10;
+
+/// Layout of the save file, stored as JSON:
+///
+/// - `magic`: always `"tetris-rs-save"`
+/// - `version`: layout version, only files matching `SAVE_VERSION` are loaded
+/// - `checksum`: checksum of `universe` as computed by `replay::checksum`, to catch files that were cut short or edited.
+///   Before it's compared, `Universe::validate` checks that the pieces in the file are all in its rotation system
+/// - `universe`: the board, active and held pieces, `Game` (score, level, lines, ticks) and the randomizer's seed, state and next queue
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    magic: String,
+    version: u32,
+    checksum: u64,
+    universe: Universe,
+}
+
+/// Just enough of the file to tell whether the rest of it is worth parsing
+#[derive(Deserialize)]
+struct SaveHeader {
+    magic: String,
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    NotASave,
+    Version(u32),
+    Corrupt,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "couldn't access save file: {}", e),
+            SaveError::Format(e) => write!(f, "save file is malformed: {}", e),
+            SaveError::NotASave => write!(f, "file isn't a saved game"),
+            SaveError::Version(v) => write!(
+                f,
+                "save file is version {}, but only version {} can be loaded",
+                v, SAVE_VERSION
+            ),
+            SaveError::Corrupt => write!(
+                f,
+                "save file doesn't match its checksum or describes pieces its rules don't have"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Format(e)
+    }
+}
+
+pub fn save(universe: &Universe, path: &Path) -> Result<(), SaveError> {
+    let file = SaveFile {
+        magic: String::from(SAVE_MAGIC),
+        version: SAVE_VERSION,
+        checksum: checksum(universe),
+        universe: universe.clone(),
+    };
+    fs::write(path, serde_json::to_string(&file)?)?;
+    Ok(())
+}
+
+/// Reads a saved game, checking that it's ours, that it's a version we understand and that it's intact
+pub fn load(path: &Path) -> Result<Universe, SaveError> {
+    let text = fs::read_to_string(path)?;
+
+    let header: SaveHeader = serde_json::from_str(&text)?;
+    if header.magic != SAVE_MAGIC {
+        return Err(SaveError::NotASave);
+    }
+    if header.version != SAVE_VERSION {
+        return Err(SaveError::Version(header.version));
+    }
+
+    let file: SaveFile = serde_json::from_str(&text)?;
+    // Checksumming looks every piece up in the rules, so they have to hang together first
+    if file.universe.validate().is_err() || checksum(&file.universe) != file.checksum {
+        return Err(SaveError::Corrupt);
+    }
+    Ok(file.universe)
+}
+
+/// Removes the save file, if there is one
+pub fn discard(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            eprintln!("failed to remove save file: {}", e);
+        }
+    }
+}
+
+/// Saves the game every so many pieces, so that a crash loses at most that many
+pub struct Autosave {
+    every: u32,
+    last_pieces: u32,
+}
+
+impl Autosave {
+    pub fn new(every: u32) -> Self {
+        Autosave {
+            every,
+            last_pieces: 0,
+        }
+    }
+
+    pub fn tick(&mut self, universe: &Universe, path: &Path) {
+        let pieces = *universe.game().pieces();
+        if pieces == self.last_pieces {
+            return;
+        }
+        self.last_pieces = pieces;
+
+        if *universe.game().running() && self.every != 0 && pieces.is_multiple_of(self.every) {
+            if let Err(e) = save(universe, path) {
+                eprintln!("failed to autosave: {}", e);
+            }
+        }
+    }
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Autosave::new(AUTOSAVE_PIECES)
+    }
+}
+
+/// Draws the saved game with the "Continue?" prompt over it
+pub fn render_prompt(d: &mut RaylibDrawHandle, config: &Config, saved: &Universe) {
+    saved.render(d, config);
+    d.draw_text(
+        "CONTINUE?",
+        150,
+        (*config.h() as f64 / 2_f64) as i32,
+        50,
+        saved.color_palette().line(),
+    );
+    d.draw_text(
+        "Press \"y\" to resume, \"n\" for a new game",
+        150,
+        (*config.h() as f64 / 2_f64) as i32 + 60,
+        20,
+        saved.color_palette().line(),
+    );
+}
@@ -0,0 +1,249 @@
+// Playing lots of games with a bot and no window, and collecting statistics on them, for seeing how changes to
+// scoring, the randomizer or a bot play out over many games before they're merged
+use std::{
+    fs,
+    io::{self, Write},
+    ops::Range,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BotSettings, Config};
+use crate::source::{bot_from_settings, play_piece, tbp::TbpError, Bot};
+use crate::universe::{game::GameOverReason, Universe};
+
+/// How one game went
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameStats {
+    pub seed: u64,
+    pub lines: u32,
+    pub score: u32,
+    pub pieces: u32,
+    // Highest the stack got, in rows
+    pub max_height: u32,
+    // Clears of four lines or more
+    pub tetrises: u32,
+    // Share of the lines cleared that were cleared by tetrises
+    pub tetris_rate: f32,
+    // Why the game ended, or nothing if it reached the piece limit
+    pub top_out: Option<GameOverReason>,
+}
+
+/// Every game put together
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub games: u32,
+    pub mean_lines: f32,
+    pub mean_score: f32,
+    pub mean_pieces: f32,
+    pub mean_max_height: f32,
+    pub max_height: u32,
+    pub tetris_rate: f32,
+    // Games that ended before the piece limit, and why
+    pub top_outs: u32,
+    pub block_outs: u32,
+    pub lock_outs: u32,
+    pub partial_lock_outs: u32,
+}
+
+impl Summary {
+    pub fn of(games: &[GameStats]) -> Self {
+        let count = games.len().max(1) as f32;
+        // Summed as u64, as a few thousand high-scoring games are enough to overflow a u32
+        let mean = |stat: fn(&GameStats) -> u32| {
+            games.iter().map(|game| stat(game) as u64).sum::<u64>() as f32 / count
+        };
+        let reasons = |reason: GameOverReason| {
+            games
+                .iter()
+                .filter(|game| game.top_out == Some(reason))
+                .count() as u32
+        };
+        let lines: u64 = games.iter().map(|game| game.lines as u64).sum();
+        let tetrises: f32 = games
+            .iter()
+            .map(|game| game.tetris_rate * game.lines as f32)
+            .sum();
+        Summary {
+            games: games.len() as u32,
+            mean_lines: mean(|game| game.lines),
+            mean_score: mean(|game| game.score),
+            mean_pieces: mean(|game| game.pieces),
+            mean_max_height: mean(|game| game.max_height),
+            max_height: games.iter().map(|game| game.max_height).max().unwrap_or(0),
+            tetris_rate: if lines == 0 {
+                0.0
+            } else {
+                tetrises / lines as f32
+            },
+            top_outs: games.iter().filter(|game| game.top_out.is_some()).count() as u32,
+            block_outs: reasons(GameOverReason::BlockOut),
+            lock_outs: reasons(GameOverReason::LockOut),
+            partial_lock_outs: reasons(GameOverReason::PartialLockOut),
+        }
+    }
+}
+
+/// Something that can be written as a row of a CSV file
+pub trait CsvRow {
+    /// Names of the columns, comma separated
+    fn header() -> &'static str;
+    /// Values of the columns, comma separated
+    fn row(&self) -> String;
+}
+
+impl CsvRow for GameStats {
+    fn header() -> &'static str {
+        "seed,lines,score,pieces,max_height,tetrises,tetris_rate,top_out"
+    }
+
+    fn row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{}",
+            self.seed,
+            self.lines,
+            self.score,
+            self.pieces,
+            self.max_height,
+            self.tetrises,
+            self.tetris_rate,
+            self.top_out.map_or("", |reason| reason.name())
+        )
+    }
+}
+
+impl CsvRow for Summary {
+    fn header() -> &'static str {
+        "games,mean_lines,mean_score,mean_pieces,mean_max_height,max_height,tetris_rate,top_outs,block_outs,\
+         lock_outs,partial_lock_outs"
+    }
+
+    fn row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            self.games,
+            self.mean_lines,
+            self.mean_score,
+            self.mean_pieces,
+            self.mean_max_height,
+            self.max_height,
+            self.tetris_rate,
+            self.top_outs,
+            self.block_outs,
+            self.lock_outs,
+            self.partial_lock_outs
+        )
+    }
+}
+
+/// Writes `rows` out as CSV, with a header
+pub fn write_csv<W: Write, T: CsvRow>(writer: &mut W, rows: &[T]) -> io::Result<()> {
+    writeln!(writer, "{}", T::header())?;
+    for row in rows.iter() {
+        writeln!(writer, "{}", row.row())?;
+    }
+    Ok(())
+}
+
+/// Writes `games.csv`, `games.json`, `summary.csv` and `summary.json` into `dir`, making it if it isn't there
+pub fn write_stats(dir: &Path, games: &[GameStats], summary: &Summary) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    write_csv(&mut fs::File::create(dir.join("games.csv"))?, games)?;
+    write_csv(
+        &mut fs::File::create(dir.join("summary.csv"))?,
+        std::slice::from_ref(summary),
+    )?;
+    fs::write(dir.join("games.json"), serde_json::to_string_pretty(games)?)?;
+    fs::write(
+        dir.join("summary.json"),
+        serde_json::to_string_pretty(summary)?,
+    )?;
+    Ok(())
+}
+
+/// Plays `universe` with `bot` until it tops out or has placed `pieces` tetrominos, keeping track of how it went
+pub fn play(universe: &mut Universe, bot: &mut dyn Bot, pieces: u32) -> GameStats {
+    let mut max_height = 0;
+    let mut tetrises = 0;
+    let mut tetris_lines = 0;
+    while *universe.game().running() && *universe.game().pieces() < pieces {
+        let lines = *universe.game().lines_cleared();
+        play_piece(universe, bot);
+        let cleared = universe.game().lines_cleared() - lines;
+        if cleared >= 4 {
+            tetrises += 1;
+            tetris_lines += cleared;
+        }
+        let dim = universe.dim();
+        let height = universe
+            .stagnant_tetrominos()
+            .iter()
+            .flat_map(|tetromino| tetromino.coords().iter())
+            .filter_map(|coord| dim.cell(coord))
+            .map(|(_, y)| y + 1)
+            .max()
+            .unwrap_or(0);
+        max_height = max_height.max(height);
+    }
+
+    let game = universe.game();
+    GameStats {
+        seed: universe.seed(),
+        lines: *game.lines_cleared(),
+        score: *game.score(),
+        pieces: *game.pieces(),
+        max_height,
+        tetrises,
+        tetris_rate: if *game.lines_cleared() == 0 {
+            0.0
+        } else {
+            tetris_lines as f32 / *game.lines_cleared() as f32
+        },
+        top_out: *game.game_over(),
+    }
+}
+
+/// `f` of every item, worked out over every core. Results come back in the same order as the items
+pub fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let mut items: Vec<&T> = items.iter().collect();
+    parallel_map_mut(&mut items, |item| f(item))
+}
+
+/// Same as `parallel_map`, for an `f` that changes the items as it goes
+pub fn parallel_map_mut<T: Send, R: Send>(
+    items: &mut [T],
+    f: impl Fn(&mut T) -> R + Sync,
+) -> Vec<R> {
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk = items.len().div_ceil(threads).max(1);
+    let f = &f;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks_mut(chunk)
+            .map(|items| scope.spawn(move || items.iter_mut().map(f).collect::<Vec<R>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("a thread of parallel_map panicked"))
+            .collect()
+    })
+}
+
+/// Plays a game on every seed in `seeds` by the rules of `config`, each with its own `bot`, spread over every core.
+/// Stats come back in the same order as the seeds
+pub fn run(
+    config: &Config,
+    bot: &BotSettings,
+    seeds: Range<u64>,
+    pieces: u32,
+) -> Result<Vec<GameStats>, TbpError> {
+    let seeds: Vec<u64> = seeds.collect();
+    parallel_map(&seeds, |seed| {
+        let mut bot = bot_from_settings(bot, *seed)?;
+        let mut universe = Universe::from_config(*seed, config);
+        Ok(play(&mut universe, bot.as_mut(), pieces))
+    })
+    .into_iter()
+    .collect()
+}
@@ -0,0 +1,150 @@
+// Computer players, and the source that paces their moves out like someone pressing keys
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use super::{tbp::TbpError, Frame, HeuristicBot, InputSource, TbpBot};
+use crate::config::{BotKind, BotSettings, Settings};
+use crate::tetris_input::device::Device;
+use crate::universe::Universe;
+use crate::{Action, FrameInput};
+
+pub trait Bot {
+    /// Decides what to do with the focused tetromino of `universe`, called once for every new tetromino
+    fn plan(&mut self, universe: &Universe) -> Vec<Action>;
+}
+
+/// Drops every tetromino at a random rotation and column, mostly useful as something to play against
+pub struct RandomBot {
+    rng: Pcg32,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        RandomBot {
+            rng: Pcg32::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Bot for RandomBot {
+    fn plan(&mut self, _universe: &Universe) -> Vec<Action> {
+        let mut plan = vec![Action::RotateCw; self.rng.gen_range(0..4)];
+        let shift: i32 = self.rng.gen_range(-5..=5);
+        let step = if shift < 0 {
+            Action::MoveLeft
+        } else {
+            Action::MoveRight
+        };
+        plan.extend(std::iter::repeat_n(step, shift.unsigned_abs() as usize));
+        plan.push(Action::HardDrop);
+        plan
+    }
+}
+
+/// The bot that `settings` describes, using `seed` for anything random it does
+pub fn bot_from_settings(settings: &BotSettings, seed: u64) -> Result<Box<dyn Bot>, TbpError> {
+    Ok(match settings.kind {
+        BotKind::Heuristic => Box::new(HeuristicBot::new(settings.weights.clone())),
+        BotKind::Random => Box::new(RandomBot::new(seed)),
+        BotKind::Tbp => Box::new(TbpBot::launch(&settings.command, &settings.args)?),
+    })
+}
+
+/// Plays a whole plan of `bot` in a single frame of `universe`, which places a tetromino. Returns the frame's input,
+/// for recording it
+pub fn play_piece(universe: &mut Universe, bot: &mut dyn Bot) -> FrameInput {
+    let mut actions = bot.plan(universe);
+    // Every frame has to place a tetromino, or playing headless would never end
+    if !actions.contains(&Action::HardDrop) {
+        actions.push(Action::HardDrop);
+    }
+    let input = FrameInput {
+        actions,
+        down: Vec::new(),
+    };
+    universe.push_input(&input);
+    universe.update();
+    input
+}
+
+/// Plays `universe` with `bot` as fast as it goes, a whole plan every frame, until it tops out or has placed
+/// `pieces` tetrominos. For benchmarking bots and rule changes without a window
+pub fn play_headless(universe: &mut Universe, bot: &mut dyn Bot, pieces: u32) {
+    while *universe.game().running() && *universe.game().pieces() < pieces {
+        play_piece(universe, bot);
+    }
+}
+
+/// Plays whatever a bot plans, one action every `delay` frames
+pub struct BotSource {
+    bot: Box<dyn Bot>,
+    plan: VecDeque<Action>,
+    // Seed and piece count that the current plan was made for, a new plan is made whenever they change
+    planned_for: Option<(u64, u32)>,
+    delay: u32,
+    // Frames that each tetromino should take, spread out over its plan, instead of a fixed delay
+    piece_frames: Option<u32>,
+    wait: u32,
+}
+
+impl BotSource {
+    pub fn new(bot: Box<dyn Bot>, delay: u32) -> Self {
+        BotSource {
+            bot,
+            plan: VecDeque::new(),
+            planned_for: None,
+            delay,
+            piece_frames: None,
+            wait: 0,
+        }
+    }
+
+    /// Paces the bot to place `pps` tetrominos a second at `fps` frames a second, however many moves each takes
+    pub fn with_pps(mut self, pps: f32, fps: u32) -> Self {
+        self.piece_frames = Some((fps as f32 / pps).round() as u32);
+        self
+    }
+
+    /// The bot and speed from the config, failing if it's a program that can't be started
+    pub fn from_settings(settings: &Settings) -> Result<Self, TbpError> {
+        let bot = bot_from_settings(&settings.bot, rand::random())?;
+        Ok(BotSource::new(bot, 0).with_pps(settings.bot.pps, settings.window.fps))
+    }
+}
+
+impl InputSource for BotSource {
+    fn next_frame(&mut self, _device: &dyn Device, universe: &Universe) -> Frame {
+        if !universe.game().running() {
+            return Frame::Input(FrameInput::default());
+        }
+
+        let piece = (universe.seed(), *universe.game().pieces());
+        if self.planned_for != Some(piece) {
+            self.plan = self.bot.plan(universe).into();
+            self.planned_for = Some(piece);
+            if let Some(frames) = self.piece_frames {
+                // Each move takes a frame of its own on top of the wait
+                self.delay = (frames / self.plan.len().max(1) as u32).saturating_sub(1);
+            }
+            self.wait = self.delay;
+        }
+
+        if self.wait > 0 {
+            self.wait -= 1;
+            return Frame::Input(FrameInput::default());
+        }
+        // Plans don't account for gravity, so soft drops that it already did would lock the tetromino early
+        while self.plan.front() == Some(&Action::SoftDrop) && universe.grounded() {
+            self.plan.pop_front();
+        }
+        match self.plan.pop_front() {
+            Some(action) => {
+                self.wait = self.delay;
+                Frame::Input(FrameInput::from(vec![action]))
+            }
+            None => Frame::Input(FrameInput::default()),
+        }
+    }
+}
@@ -0,0 +1,285 @@
+// A bot that tries every placement it can reach, and picks whichever leaves the best looking board
+use super::Bot;
+use crate::config::BotWeights;
+use crate::tetromino::{coord::Coord, tetromino_type::TetrominoType};
+use crate::universe::{rotations::system::place, Dimensions, RotationSystem, Universe};
+use crate::Action;
+
+/// The stack as one bitmask per row, with bit `x` set when column `x` is filled
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    w: u32,
+    rows: Vec<u64>,
+}
+
+/// What the board looks like, as counted by `Field::features`. See `BotWeights` for what each one means
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Features {
+    pub height: u32,
+    pub holes: u32,
+    pub bumpiness: u32,
+    pub wells: u32,
+    pub row_transitions: u32,
+    pub column_transitions: u32,
+    pub t_slots: u32,
+}
+
+impl Field {
+    /// An empty board of `dim`, hidden rows included
+    pub fn new(dim: &Dimensions) -> Self {
+        Field {
+            w: *dim.w(),
+            rows: vec![0; dim.total_h() as usize],
+        }
+    }
+
+    /// The stack of `universe`
+    pub fn from_universe(universe: &Universe) -> Self {
+        let mut field = Field::new(universe.dim());
+        for tetromino in universe.stagnant_tetrominos().iter() {
+            field.fill(tetromino.coords());
+        }
+        field
+    }
+
+    /// Bits of a row with every column filled
+    fn full(&self) -> u64 {
+        (1 << self.w) - 1
+    }
+
+    /// Whether the cell at `x`, `y` is taken, anything off the board counts as taken
+    pub fn filled(&self, x: i32, y: i32) -> bool {
+        if x < 0 || x >= self.w as i32 || y < 0 || y >= self.rows.len() as i32 {
+            return true;
+        }
+        self.rows[y as usize] & (1 << x) != 0
+    }
+
+    /// Whether every one of `cells` is free
+    pub fn fits(&self, cells: &[Coord]) -> bool {
+        cells.iter().all(|coord| !self.filled(coord.x, coord.y))
+    }
+
+    fn fill(&mut self, cells: &[Coord]) {
+        for coord in cells.iter() {
+            if !self.filled(coord.x, coord.y) {
+                self.rows[coord.y as usize] |= 1 << coord.x;
+            }
+        }
+    }
+
+    /// Fills `cells` and clears any rows that completes, returning how many lines that cleared
+    pub fn place(&mut self, cells: &[Coord]) -> u32 {
+        self.fill(cells);
+        let full = self.full();
+        let before = self.rows.len();
+        self.rows.retain(|row| *row != full);
+        let lines = before - self.rows.len();
+        self.rows.resize(before, 0);
+        lines as u32
+    }
+
+    /// Height of every column, counting from the floor to its highest filled cell
+    pub fn heights(&self) -> Vec<u32> {
+        (0..self.w)
+            .map(|x| {
+                self.rows
+                    .iter()
+                    .rposition(|row| row & (1 << x) != 0)
+                    .map_or(0, |y| y as u32 + 1)
+            })
+            .collect()
+    }
+
+    pub fn features(&self) -> Features {
+        let heights = self.heights();
+        let top = heights.iter().copied().max().unwrap_or(0) as i32;
+        let w = self.w as i32;
+        let mut features = Features {
+            height: heights.iter().sum(),
+            bumpiness: heights
+                .windows(2)
+                .map(|pair| (pair[0] as i32 - pair[1] as i32).unsigned_abs())
+                .sum(),
+            ..Features::default()
+        };
+
+        for x in 0..w {
+            let mut depth = 0;
+            for y in (0..top).rev() {
+                if self.filled(x, y) {
+                    depth = 0;
+                    continue;
+                }
+                if y < heights[x as usize] as i32 {
+                    features.holes += 1;
+                }
+                // Deeper wells count for more, as they take longer to fill back in
+                if self.filled(x - 1, y) && self.filled(x + 1, y) {
+                    depth += 1;
+                    features.wells += depth;
+                } else {
+                    depth = 0;
+                }
+            }
+
+            // The floor counts as filled, and so does everything above the column
+            let mut last = true;
+            for y in 0..=top {
+                let filled = y < top && self.filled(x, y);
+                if filled != last {
+                    features.column_transitions += 1;
+                }
+                last = filled;
+            }
+        }
+
+        for y in 0..top {
+            // Walls on either side count as filled
+            let mut last = true;
+            for x in 0..=w {
+                let filled = self.filled(x, y);
+                if filled != last {
+                    features.row_transitions += 1;
+                }
+                last = filled;
+            }
+
+            // A row with one hole, under three open cells with something hanging over one side of them,
+            // is where a T spins in and clears
+            for x in 1..w - 1 {
+                let overhangs = [self.filled(x - 1, y + 2), self.filled(x + 1, y + 2)];
+                if self.rows[y as usize] | (1 << x) == self.full()
+                    && !self.filled(x, y)
+                    && (-1..=1).all(|dx| !self.filled(x + dx, y + 1))
+                    && !self.filled(x, y + 2)
+                    && overhangs[0] != overhangs[1]
+                {
+                    features.t_slots += 1;
+                }
+            }
+        }
+        features
+    }
+
+    /// How good the board looks after a tetromino landed at `landing_height` and cleared `lines`
+    pub fn score(&self, weights: &BotWeights, lines: u32, landing_height: f32) -> f32 {
+        let features = self.features();
+        weights.landing_height * landing_height
+            + weights.lines * lines as f32
+            + weights.height * features.height as f32
+            + weights.holes * features.holes as f32
+            + weights.bumpiness * features.bumpiness as f32
+            + weights.wells * features.wells as f32
+            + weights.row_transitions * features.row_transitions as f32
+            + weights.column_transitions * features.column_transitions as f32
+            + weights.t_slots * features.t_slots as f32
+    }
+}
+
+/// Weighs up every placement of the focused tetromino and of whatever hold would bring in, looking one
+/// tetromino further ahead in the next queue
+pub struct HeuristicBot {
+    weights: BotWeights,
+}
+
+impl HeuristicBot {
+    pub fn new(weights: BotWeights) -> Self {
+        HeuristicBot { weights }
+    }
+
+    /// Score of locking `cells` into `field`, or `None` if that would lose the game
+    fn evaluate(&self, field: &Field, cells: &[Coord], dim: &Dimensions) -> Option<(Field, f32)> {
+        if cells.iter().all(|coord| coord.y >= *dim.h() as i32) {
+            return None;
+        }
+        let mut after = field.clone();
+        let lines = after.place(cells);
+        let landing = cells.iter().map(|coord| coord.y as f32).sum::<f32>() / cells.len() as f32;
+        let score = after.score(&self.weights, lines, landing);
+        Some((after, score))
+    }
+
+    /// Best score out of every straight drop of `piece` into `field`, without bothering with tucks or spins
+    fn best_drop(
+        &self,
+        field: &Field,
+        piece: TetrominoType,
+        rotation_system: &RotationSystem,
+        dim: &Dimensions,
+    ) -> Option<f32> {
+        let fits = |state: &[[i32; 2]], [x, y]: [i32; 2]| {
+            state.iter().all(|[dx, dy]| !field.filled(x + dx, y + dy))
+        };
+        let top = field.heights().into_iter().max().unwrap_or(0) as i32;
+        let mut best: Option<f32> = None;
+        for state in rotation_system.piece(piece).states.iter() {
+            let left = state.iter().map(|[x, _]| *x).min().unwrap_or(0);
+            let right = state.iter().map(|[x, _]| *x).max().unwrap_or(0);
+            let bottom = state.iter().map(|[_, y]| *y).min().unwrap_or(0);
+            for x in -left..*dim.w() as i32 - right {
+                // Everything above the highest column is open, so that's as good a place as any to drop from
+                let mut origin = [x, top - bottom];
+                if !fits(state, origin) {
+                    continue;
+                }
+                while fits(state, [origin[0], origin[1] - 1]) {
+                    origin[1] -= 1;
+                }
+                let cells = place(state, origin);
+                if let Some((_, score)) = self.evaluate(field, &cells, dim) {
+                    best = Some(best.map_or(score, |best| best.max(score)));
+                }
+            }
+        }
+        best
+    }
+}
+
+impl Bot for HeuristicBot {
+    fn plan(&mut self, universe: &Universe) -> Vec<Action> {
+        let rotation_system = universe.rotation_system();
+        let dim = universe.dim();
+        let field = Field::from_universe(universe);
+        let focused = *universe.focused();
+        let preview = universe.preview();
+
+        // Each option is what to press first, where the tetromino to place starts and what comes after it
+        let mut options = vec![(None, focused, preview.front().copied())];
+        if universe.can_hold() {
+            let (swap, next) = match universe.held() {
+                Some(held) => (Some(*held), preview.front()),
+                None => (preview.front().copied(), preview.get(1)),
+            };
+            if let Some(swap) = swap.filter(|swap| *swap != focused.piece) {
+                options.push((
+                    Some(Action::Hold),
+                    rotation_system.spawn_placement(swap, dim),
+                    next.copied(),
+                ));
+            }
+        }
+
+        let mut best: Option<(f32, Vec<Action>)> = None;
+        for (first, start, next) in options.iter() {
+            for m in universe.moves(start).iter() {
+                let cells = rotation_system.cells(&m.placement);
+                let (after, mut score) = match self.evaluate(&field, &cells, dim) {
+                    Some(evaluated) => evaluated,
+                    None => continue,
+                };
+                if let Some(next) = next {
+                    score += self
+                        .best_drop(&after, *next, rotation_system, dim)
+                        .unwrap_or(f32::NEG_INFINITY);
+                }
+                if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                    let inputs = first.iter().chain(m.inputs.iter()).copied().collect();
+                    best = Some((score, inputs));
+                }
+            }
+        }
+        // Every placement loses, so it may as well be now
+        best.map_or_else(|| vec![Action::HardDrop], |(_, inputs)| inputs)
+    }
+}
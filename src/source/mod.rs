@@ -0,0 +1,128 @@
+// Everything that can drive a universe: the local keyboard and gamepad, a replay, a bot (built in or a program of
+// its own), a peer over the network or a script
+pub mod bot;
+pub mod heuristic;
+pub mod network;
+pub mod replay;
+pub mod tbp;
+
+use std::collections::VecDeque;
+
+use super::config::Settings;
+use super::tetris_input::{device::Device, Bindings, TetrominoControls};
+use super::universe::Universe;
+use super::{Action, FrameInput};
+
+pub use bot::{bot_from_settings, play_headless, play_piece, Bot, BotSource, RandomBot};
+pub use heuristic::HeuristicBot;
+pub use network::{NetworkSender, NetworkSource, Session};
+pub use replay::ReplaySource;
+pub use tbp::TbpBot;
+
+/// What a source has for the next frame
+#[derive(Clone, Debug, PartialEq)]
+pub enum Frame {
+    /// The frame is ready to be simulated with this input, which may well be nothing at all
+    Input(FrameInput),
+    /// The frame isn't known yet, e.g. a peer that's lagging behind, so the universe should wait
+    Waiting,
+    /// The universe should start over on this seed, e.g. because a peer restarted their game
+    Restart(u64),
+    /// The source has nothing more to give
+    Finished,
+}
+
+pub trait InputSource {
+    /// The actions for the next frame of `universe`. Only local sources read `device`, the rest ignore it
+    fn next_frame(&mut self, device: &dyn Device, universe: &Universe) -> Frame;
+
+    /// Number of frames that are ready to go on top of the next one, so that boards can catch up
+    fn backlog(&self) -> usize {
+        0
+    }
+
+    /// Whether the player on the other end can restart the game, which picks a new seed
+    fn can_restart(&self) -> bool {
+        false
+    }
+}
+
+/// The keyboard and gamepad of whoever is sitting at this computer
+pub struct KeyboardSource {
+    controls: TetrominoControls,
+}
+
+impl KeyboardSource {
+    pub fn new(controls: TetrominoControls) -> Self {
+        KeyboardSource { controls }
+    }
+
+    /// Binds the keys, buttons and repeat rates from the config
+    pub fn from_settings(settings: &Settings) -> Self {
+        KeyboardSource::new(TetrominoControls::new(
+            &Bindings::from_settings(&settings.keys),
+            &settings.handling,
+            &settings.gamepad,
+        ))
+    }
+
+    /// Get a reference to the keyboard source's controls.
+    pub fn controls(&self) -> &TetrominoControls {
+        &self.controls
+    }
+}
+
+impl InputSource for KeyboardSource {
+    fn next_frame(&mut self, device: &dyn Device, _universe: &Universe) -> Frame {
+        self.controls.poll(device);
+        Frame::Input(FrameInput {
+            actions: self.controls.get_queue(),
+            down: self.controls.down(),
+        })
+    }
+
+    fn can_restart(&self) -> bool {
+        true
+    }
+}
+
+/// Plays a fixed list of frames, for tests and demos
+pub struct ScriptedSource {
+    frames: VecDeque<FrameInput>,
+}
+
+impl ScriptedSource {
+    pub fn new(frames: Vec<Vec<Action>>) -> Self {
+        ScriptedSource::from_inputs(frames.into_iter().map(FrameInput::from).collect())
+    }
+
+    /// A script that also says what's held down each frame
+    pub fn from_inputs(frames: Vec<FrameInput>) -> Self {
+        ScriptedSource {
+            frames: frames.into(),
+        }
+    }
+
+    /// A script that fires `actions` once every `every` frames, `times` times over
+    pub fn every(every: usize, actions: &[Action], times: usize) -> Self {
+        let frames = (0..every * times)
+            .map(|frame| {
+                if frame % every == 0 {
+                    actions.to_vec()
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect();
+        ScriptedSource::new(frames)
+    }
+}
+
+impl InputSource for ScriptedSource {
+    fn next_frame(&mut self, _device: &dyn Device, _universe: &Universe) -> Frame {
+        match self.frames.pop_front() {
+            Some(input) => Frame::Input(input),
+            None => Frame::Finished,
+        }
+    }
+}
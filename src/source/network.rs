@@ -0,0 +1,233 @@
+// Playing against someone on another computer. Each side sends the actions of every frame it plays as a line of JSON,
+// and shows the other side's board by playing those frames back
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Frame, InputSource};
+use crate::tetris_input::device::Device;
+use crate::universe::Universe;
+use crate::{Action, FrameInput};
+
+/// Bumped whenever the messages change
+pub const NETWORK_VERSION: u32 = 3;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// Sent by both sides when connecting, the host's seed is the one that's played
+    Hello { version: u32, seed: u64 },
+    /// The input of one frame
+    Frame {
+        actions: Vec<Action>,
+        #[serde(default)]
+        down: Vec<Action>,
+    },
+    /// The sender started a new game on `seed`
+    Restart { seed: u64 },
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    Version(u32),
+    Handshake,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Io(e) => write!(f, "connection failed: {}", e),
+            NetworkError::Format(e) => write!(f, "peer sent something we don't understand: {}", e),
+            NetworkError::Version(v) => write!(
+                f,
+                "peer speaks version {}, but we only speak version {}",
+                v, NETWORK_VERSION
+            ),
+            NetworkError::Handshake => write!(f, "peer didn't say hello"),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+impl From<io::Error> for NetworkError {
+    fn from(e: io::Error) -> Self {
+        NetworkError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for NetworkError {
+    fn from(e: serde_json::Error) -> Self {
+        NetworkError::Format(e)
+    }
+}
+
+/// Both ends of a connection to a peer, along with the seed that both games start on
+pub struct Session {
+    pub seed: u64,
+    pub source: NetworkSource,
+    pub sender: NetworkSender,
+}
+
+/// Waits for a peer to connect to `listener`, and starts both games on `seed`
+pub fn host(listener: &TcpListener, seed: u64) -> Result<Session, NetworkError> {
+    let (stream, _) = listener.accept()?;
+    let mut sender = NetworkSender::new(stream.try_clone()?);
+    sender.send(&Message::Hello {
+        version: NETWORK_VERSION,
+        seed,
+    })?;
+    let mut reader = BufReader::new(stream);
+    read_hello(&mut reader)?;
+    Session::start(seed, reader, sender)
+}
+
+/// Connects to a peer that's hosting at `address`, playing on whichever seed they picked
+pub fn connect<A: ToSocketAddrs>(address: A) -> Result<Session, NetworkError> {
+    let stream = TcpStream::connect(address)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let seed = read_hello(&mut reader)?;
+    let mut sender = NetworkSender::new(stream);
+    sender.send(&Message::Hello {
+        version: NETWORK_VERSION,
+        seed,
+    })?;
+    Session::start(seed, reader, sender)
+}
+
+fn read_hello(reader: &mut BufReader<TcpStream>) -> Result<u64, NetworkError> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    match serde_json::from_str(&line)? {
+        Message::Hello { version, seed } if version == NETWORK_VERSION => Ok(seed),
+        Message::Hello { version, .. } => Err(NetworkError::Version(version)),
+        _ => Err(NetworkError::Handshake),
+    }
+}
+
+impl Session {
+    fn start(
+        seed: u64,
+        reader: BufReader<TcpStream>,
+        sender: NetworkSender,
+    ) -> Result<Session, NetworkError> {
+        // From here on a frame is never waited for, the board just falls behind until it arrives
+        reader.get_ref().set_nonblocking(true)?;
+        reader.get_ref().set_nodelay(true)?;
+        Ok(Session {
+            seed,
+            source: NetworkSource::new(reader),
+            sender,
+        })
+    }
+}
+
+/// The peer's board, played back from the frames they send
+pub struct NetworkSource {
+    reader: BufReader<TcpStream>,
+    // Partly received line, reads can stop anywhere
+    line: String,
+    frames: VecDeque<Frame>,
+    closed: bool,
+}
+
+impl NetworkSource {
+    fn new(reader: BufReader<TcpStream>) -> Self {
+        NetworkSource {
+            reader,
+            line: String::new(),
+            frames: VecDeque::new(),
+            closed: false,
+        }
+    }
+
+    /// Reads every message that has arrived so far
+    fn receive(&mut self) {
+        while !self.closed {
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => self.closed = true,
+                Ok(_) if self.line.ends_with('\n') => {
+                    match serde_json::from_str(&self.line) {
+                        Ok(Message::Frame { actions, down }) => {
+                            self.frames.push_back(Frame::Input(FrameInput { actions, down }))
+                        }
+                        Ok(Message::Restart { seed }) => self.frames.push_back(Frame::Restart(seed)),
+                        Ok(Message::Hello { .. }) => {}
+                        Err(e) => {
+                            eprintln!("dropping peer, {}", NetworkError::from(e));
+                            self.closed = true;
+                        }
+                    }
+                    self.line.clear();
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    eprintln!("lost connection to peer, {}", e);
+                    self.closed = true;
+                }
+            }
+        }
+    }
+}
+
+impl InputSource for NetworkSource {
+    fn next_frame(&mut self, _device: &dyn Device, _universe: &Universe) -> Frame {
+        self.receive();
+        match self.frames.pop_front() {
+            Some(frame) => frame,
+            None if self.closed => Frame::Finished,
+            None => Frame::Waiting,
+        }
+    }
+
+    fn backlog(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+/// Sends our own frames to the peer
+pub struct NetworkSender {
+    stream: TcpStream,
+    // Bytes that couldn't be written without blocking yet
+    pending: Vec<u8>,
+}
+
+impl NetworkSender {
+    fn new(stream: TcpStream) -> Self {
+        NetworkSender {
+            stream,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), NetworkError> {
+        serde_json::to_writer(&mut self.pending, message)?;
+        self.pending.push(b'\n');
+        self.flush()
+    }
+
+    /// Writes as much as can be written without blocking
+    fn flush(&mut self) -> Result<(), NetworkError> {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero).into()),
+                Ok(n) => {
+                    self.pending.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+// Feeding a recorded game back in, frame by frame
+use super::{Frame, InputSource};
+use crate::replay::Replay;
+use crate::tetris_input::device::Device;
+use crate::universe::Universe;
+use crate::FrameInput;
+
+pub struct ReplaySource {
+    replay: Replay,
+    // Position within the run-length encoded frames
+    run: usize,
+    offset: u32,
+    frame: u32,
+}
+
+impl ReplaySource {
+    pub fn new(replay: Replay) -> Self {
+        ReplaySource {
+            replay,
+            run: 0,
+            offset: 0,
+            frame: 0,
+        }
+    }
+
+    /// Carries on from `frame`, for when the universe has been put back to a snapshot taken there
+    pub fn seek(&mut self, frame: u32) {
+        let (run, offset) = self.replay.locate(frame);
+        self.run = run;
+        self.offset = offset;
+        self.frame = frame;
+    }
+
+    /// Get a reference to the replay source's replay.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Get a mutable reference to the replay source's replay.
+    pub fn replay_mut(&mut self) -> &mut Replay {
+        &mut self.replay
+    }
+
+    /// Get the number of frames that have been handed out so far.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// The input of the next recorded frame, `None` once the replay has run out
+    pub fn next_input(&mut self) -> Option<FrameInput> {
+        let run = self.replay.frames.get(self.run)?;
+        let input = run.input();
+
+        self.offset += 1;
+        if self.offset >= run.len {
+            self.run += 1;
+            self.offset = 0;
+        }
+        self.frame += 1;
+        Some(input)
+    }
+}
+
+impl InputSource for ReplaySource {
+    fn next_frame(&mut self, _device: &dyn Device, _universe: &Universe) -> Frame {
+        match self.next_input() {
+            Some(input) => Frame::Input(input),
+            None => Frame::Finished,
+        }
+    }
+}
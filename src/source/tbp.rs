@@ -0,0 +1,485 @@
+// Bots that run as programs of their own, speaking the Tetris Bot Protocol: one JSON message a line over the bot's
+// stdin and stdout. See <https://github.com/tetris-bot-protocol/tbp-spec> for the protocol itself
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{heuristic::Field, Bot};
+use crate::tetromino::{coord::Coord, tetromino_type::TetrominoType};
+use crate::universe::{moves::Move, Dimensions, Universe};
+use crate::Action;
+
+/// Columns and rows of the board that the protocol describes, whatever the board being played on is
+pub const TBP_WIDTH: usize = 10;
+pub const TBP_HEIGHT: usize = 40;
+
+/// Cells of each tetromino facing north, relative to its centre, in the order that tetrominos are numbered
+const NORTH: [[[i32; 2]; 4]; 7] = [
+    [[-1, 0], [0, 0], [1, 0], [2, 0]],  // I
+    [[-1, 0], [0, 0], [1, 0], [-1, 1]], // J
+    [[-1, 0], [0, 0], [1, 0], [1, 1]],  // L
+    [[0, 0], [1, 0], [0, 1], [1, 1]],   // O
+    [[-1, 0], [0, 0], [0, 1], [1, 1]],  // S
+    [[-1, 0], [0, 0], [1, 0], [0, 1]],  // T
+    [[-1, 1], [0, 1], [0, 0], [1, 0]],  // Z
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 4] = [
+        Orientation::North,
+        Orientation::East,
+        Orientation::South,
+        Orientation::West,
+    ];
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+/// Where a tetromino is, by the cell it turns around
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    #[serde(rename = "type")]
+    pub piece: TetrominoType,
+    pub orientation: Orientation,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Location {
+    /// Cells that the tetromino covers, or `None` if it isn't one of the seven that the protocol knows
+    pub fn cells(&self) -> Option<Vec<Coord>> {
+        let north = NORTH.get(self.piece.index() as usize)?;
+        Some(
+            north
+                .iter()
+                .map(|[x, y]| match self.orientation {
+                    Orientation::North => [*x, *y],
+                    Orientation::East => [*y, -x],
+                    Orientation::South => [-x, -y],
+                    Orientation::West => [-y, *x],
+                })
+                .map(|[x, y]| Coord::new(self.x + x, self.y + y))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TbpMove {
+    pub location: Location,
+    pub spin: Spin,
+}
+
+/// Sent to the bot
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    /// Asks whether the bot can play by the rules, which are always the standard ones for now
+    Rules {},
+    /// A new game, with the board's rows from the bottom up. Cells hold the letter of the tetromino that filled them
+    Start {
+        hold: Option<TetrominoType>,
+        queue: Vec<TetrominoType>,
+        combo: u32,
+        back_to_back: bool,
+        board: Vec<Vec<Option<char>>>,
+    },
+    /// Asks for the moves the bot would make with the current tetromino
+    Suggest,
+    /// The move that was actually made, which the bot carries on from
+    Play {
+        #[serde(rename = "move")]
+        mv: TbpMove,
+    },
+    /// A tetromino that just showed up at the end of the queue
+    NewPiece { piece: TetrominoType },
+    /// The game is over, another might start after it
+    Stop,
+    /// The bot should exit
+    Quit,
+}
+
+/// Sent by the bot
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    /// The first thing a bot says
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        #[serde(default)]
+        features: Vec<String>,
+    },
+    /// The bot can play by the rules it was sent
+    Ready,
+    /// The bot can't play by the rules it was sent
+    Error { reason: String },
+    /// Moves the bot would make, best first
+    Suggestion { moves: Vec<TbpMove> },
+}
+
+#[derive(Debug)]
+pub enum TbpError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    // The bot turned the game down, saying why
+    Refused(String),
+    // The bot said something other than what it should have at that point
+    Unexpected(&'static str),
+    // The board or pieces are ones that the protocol can't describe
+    Unsupported(String),
+}
+
+impl fmt::Display for TbpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TbpError::Io(e) => write!(f, "couldn't talk to the bot: {}", e),
+            TbpError::Format(e) => write!(f, "bot sent something we don't understand: {}", e),
+            TbpError::Refused(reason) => write!(f, "bot won't play: {}", reason),
+            TbpError::Unexpected(expected) => write!(f, "bot should have sent {}", expected),
+            TbpError::Unsupported(what) => write!(f, "bots can't be told about {}", what),
+        }
+    }
+}
+
+impl std::error::Error for TbpError {}
+
+impl From<io::Error> for TbpError {
+    fn from(e: io::Error) -> Self {
+        TbpError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for TbpError {
+    fn from(e: serde_json::Error) -> Self {
+        TbpError::Format(e)
+    }
+}
+
+/// The game as a bot sees it, which it keeps up to date from the moves it's told were played
+#[derive(Clone, Debug, PartialEq)]
+pub struct TbpGame {
+    pub field: Field,
+    pub hold: Option<TetrominoType>,
+    // Current tetromino first, then the next queue
+    pub queue: Vec<TetrominoType>,
+}
+
+impl TbpGame {
+    /// The game that a start message describes, on a board of the protocol's size
+    pub fn start(
+        hold: Option<TetrominoType>,
+        queue: Vec<TetrominoType>,
+        board: &[Vec<Option<char>>],
+    ) -> Self {
+        let mut field = Field::new(&Dimensions::with_buffer(
+            TBP_WIDTH as u32,
+            (TBP_HEIGHT / 2) as u32,
+            (TBP_HEIGHT / 2) as u32,
+        ));
+        let cells: Vec<Coord> = board
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, cell)| cell.is_some())
+                    .map(move |(x, _)| Coord::new(x as i32, y as i32))
+            })
+            .collect();
+        field.place(&cells);
+        TbpGame { field, hold, queue }
+    }
+
+    /// Locks `mv` into the field, holding first if it isn't the current tetromino, returning the lines it cleared
+    pub fn play(&mut self, mv: &TbpMove) -> u32 {
+        if !self.queue.is_empty() {
+            let current = self.queue.remove(0);
+            // Holding with nothing held brings the next tetromino in as well
+            if current != mv.location.piece
+                && self.hold.replace(current).is_none()
+                && !self.queue.is_empty()
+            {
+                self.queue.remove(0);
+            }
+        }
+        self.field.place(&mv.location.cells().unwrap_or_default())
+    }
+}
+
+/// Reads the message on the next line
+pub fn receive<R: BufRead, M: for<'de> Deserialize<'de>>(reader: &mut R) -> Result<M, TbpError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(TbpError::Io(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Writes `message` out as a line of its own
+pub fn send<W: Write, M: Serialize>(writer: &mut W, message: &M) -> Result<(), TbpError> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// A bot on the other end of a pair of pipes, usually a program that the game launched
+pub struct TbpBot {
+    reader: Box<dyn BufRead>,
+    writer: Box<dyn Write>,
+    child: Option<Child>,
+    name: String,
+    // Seed that the bot's game was started on, and where it thinks that game is at
+    game: Option<(u64, TbpGame)>,
+    // Whether the bot has a game going that has to be stopped before starting another
+    started: bool,
+    starts: u32,
+    // Once the bot goes wrong it's left alone, and tetrominos are just dropped
+    failed: bool,
+}
+
+impl TbpBot {
+    /// Runs `command` with `args`, and gets it ready to play
+    pub fn launch(command: &str, args: &[String]) -> Result<Self, TbpError> {
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let reader = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let writer = child.stdin.take().expect("stdin is piped");
+        match TbpBot::new(reader, writer) {
+            Ok(mut bot) => {
+                bot.child = Some(child);
+                Ok(bot)
+            }
+            // Nothing else will stop a bot that never got ready
+            Err(error) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(error)
+            }
+        }
+    }
+
+    /// Talks to a bot that reads from `writer` and writes to `reader`, waiting until it says that it's ready
+    pub fn new(
+        reader: impl BufRead + 'static,
+        writer: impl Write + 'static,
+    ) -> Result<Self, TbpError> {
+        let mut bot = TbpBot {
+            reader: Box::new(reader),
+            writer: Box::new(writer),
+            child: None,
+            name: String::new(),
+            game: None,
+            started: false,
+            starts: 0,
+            failed: false,
+        };
+        match bot.receive()? {
+            BotMessage::Info { name, .. } => bot.name = name,
+            _ => return Err(TbpError::Unexpected("its info")),
+        }
+        bot.send(&FrontendMessage::Rules {})?;
+        match bot.receive()? {
+            BotMessage::Ready => Ok(bot),
+            BotMessage::Error { reason } => Err(TbpError::Refused(reason)),
+            _ => Err(TbpError::Unexpected("whether it's ready")),
+        }
+    }
+
+    /// Get the name that the bot gave itself.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the number of games the bot has been started on, including every time it had to be caught up.
+    pub fn starts(&self) -> u32 {
+        self.starts
+    }
+
+    fn send(&mut self, message: &FrontendMessage) -> Result<(), TbpError> {
+        send(&mut self.writer, message)
+    }
+
+    fn receive(&mut self) -> Result<BotMessage, TbpError> {
+        receive(&mut self.reader)
+    }
+
+    /// Tells the bot about whatever happened since its last move, which is just new tetrominos in the queue as
+    /// long as the game went the way it expected. Anything else starts it over on the game as it is now
+    fn catch_up(&mut self, universe: &Universe) -> Result<(), TbpError> {
+        let queue: Vec<TetrominoType> = std::iter::once(universe.focused().piece)
+            .chain(universe.preview().iter().copied())
+            .collect();
+        let hold = *universe.held();
+        let field = Field::from_universe(universe);
+
+        if let Some((seed, game)) = &mut self.game {
+            if *seed == universe.seed()
+                && game.field == field
+                && game.hold == hold
+                && queue.starts_with(&game.queue)
+            {
+                let new = queue[game.queue.len()..].to_vec();
+                game.queue = queue;
+                for piece in new {
+                    self.send(&FrontendMessage::NewPiece { piece })?;
+                }
+                return Ok(());
+            }
+        }
+
+        if self.started {
+            self.send(&FrontendMessage::Stop)?;
+            self.started = false;
+        }
+        let dim = universe.dim();
+        if *dim.w() as usize != TBP_WIDTH {
+            return Err(TbpError::Unsupported(format!(
+                "boards {} wide, only {}",
+                dim.w(),
+                TBP_WIDTH
+            )));
+        }
+        let mut board = vec![vec![None; TBP_WIDTH]; TBP_HEIGHT];
+        for tetromino in universe.stagnant_tetrominos().iter() {
+            let letter = tetromino.tetromino_type().name().chars().next();
+            for coord in tetromino.coords().iter() {
+                if let Some((x, y)) = dim.cell(coord) {
+                    if let Some(row) = board.get_mut(y as usize) {
+                        row[x as usize] = letter;
+                    }
+                }
+            }
+        }
+        let pieces = queue.iter().chain(hold.iter());
+        if let Some(piece) = pieces
+            .clone()
+            .find(|piece| piece.index() as usize >= NORTH.len())
+        {
+            return Err(TbpError::Unsupported(format!("piece {}", piece.name())));
+        }
+
+        self.send(&FrontendMessage::Start {
+            hold,
+            queue: queue.clone(),
+            combo: *universe.game().combo(),
+            back_to_back: *universe.game().back_to_back(),
+            board,
+        })?;
+        self.started = true;
+        self.starts += 1;
+        self.game = Some((universe.seed(), TbpGame { field, hold, queue }));
+        Ok(())
+    }
+
+    /// Inputs that put the tetromino where `mv` says, holding first if it's for the tetromino that hold brings in
+    fn inputs(universe: &Universe, mv: &TbpMove) -> Option<Vec<Action>> {
+        let rotation_system = universe.rotation_system();
+        let sorted = |mut cells: Vec<Coord>| {
+            cells.sort_by_key(|coord| (coord.x, coord.y));
+            cells
+        };
+        let target = sorted(mv.location.cells()?);
+
+        let focused = *universe.focused();
+        let (first, start) = if mv.location.piece == focused.piece {
+            (None, focused)
+        } else {
+            let swap = (*universe.held()).or_else(|| universe.preview().front().copied());
+            if !universe.can_hold() || swap != Some(mv.location.piece) {
+                return None;
+            }
+            (
+                Some(Action::Hold),
+                rotation_system.spawn_placement(mv.location.piece, universe.dim()),
+            )
+        };
+
+        // The bot's idea of what counts as a spin may not be the same as ours, so it's only a tiebreak
+        let spin = mv.spin != Spin::None;
+        let moves = universe.moves(&start);
+        let chosen: &Move = moves
+            .iter()
+            .filter(|m| sorted(rotation_system.cells(&m.placement)) == target)
+            .max_by_key(|m| m.spin == spin)?;
+        Some(
+            first
+                .into_iter()
+                .chain(chosen.inputs.iter().copied())
+                .collect(),
+        )
+    }
+
+    fn try_plan(&mut self, universe: &Universe) -> Result<Vec<Action>, TbpError> {
+        self.catch_up(universe)?;
+        self.send(&FrontendMessage::Suggest)?;
+        let moves = match self.receive()? {
+            BotMessage::Suggestion { moves } => moves,
+            _ => return Err(TbpError::Unexpected("a suggestion")),
+        };
+
+        for mv in moves.iter() {
+            if let Some(inputs) = TbpBot::inputs(universe, mv) {
+                self.send(&FrontendMessage::Play { mv: *mv })?;
+                if let Some((_, game)) = &mut self.game {
+                    game.play(mv);
+                }
+                return Ok(inputs);
+            }
+        }
+        // None of the suggestions can be played here, so the bot gets started over on whatever happens instead
+        self.game = None;
+        Ok(vec![Action::HardDrop])
+    }
+}
+
+impl Bot for TbpBot {
+    fn plan(&mut self, universe: &Universe) -> Vec<Action> {
+        if !self.failed {
+            match self.try_plan(universe) {
+                Ok(plan) => return plan,
+                Err(e) => {
+                    eprintln!("{} stopped playing, {}", self.name, e);
+                    self.failed = true;
+                }
+            }
+        }
+        vec![Action::HardDrop]
+    }
+}
+
+impl Drop for TbpBot {
+    fn drop(&mut self) {
+        let _ = self.send(&FrontendMessage::Quit);
+        // A bot that doesn't quit when it's told to isn't waited on
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
@@ -1,5 +1,43 @@
 #[cfg(test)]
 mod test {
+    use crate::{
+        tetromino::{
+            coord::Coord, randomizer::Randomizer, tetromino_type::TetrominoType, Tetromino,
+        },
+        universe::{color::ColorPalette, game::Game, Dimensions, Universe},
+    };
+
+    /// A 10x20 universe with `focused` falling, over single cells wherever `blocked` says
+    fn fixture(focused: Tetromino, blocked: &[(i32, i32)]) -> Universe {
+        let stagnant = blocked
+            .iter()
+            .map(|(x, y)| Tetromino::new(vec![Coord::new(*x, *y)], TetrominoType::O))
+            .collect();
+        Universe::new(
+            Dimensions::new(10, 20),
+            focused,
+            stagnant,
+            ColorPalette::default(),
+            Game::default(),
+            Randomizer::new(1),
+        )
+    }
+
+    /// A T pointing up with its center at `center`, over single cells wherever `blocked` says
+    fn t_universe(center: (i32, i32), blocked: &[(i32, i32)]) -> Universe {
+        let t = Tetromino::spawn_tetromino(
+            vec![
+                Coord::new(1, 0),
+                Coord::new(0, 0),
+                Coord::new(1, 1),
+                Coord::new(2, 0),
+            ],
+            Coord::new(center.0, center.1),
+            TetrominoType::T,
+        );
+        fixture(t, blocked)
+    }
+
     mod movement {
         use crate::{
             tetromino::{
@@ -22,16 +60,17 @@ mod test {
             );
             tetromino.move_by(Tetromino::get_dxdy(Direction::Down));
 
+            // The first reference coord lands on the spawn point, the rest keep their offsets from it, in order
             let right_real_coords = vec![
+                Coord { x: 5, y: 21 },
                 Coord { x: 4, y: 21 },
                 Coord { x: 5, y: 22 },
-                Coord { x: 5, y: 21 },
                 Coord { x: 6, y: 21 },
             ];
 
             dbg!(&right_real_coords, tetromino.coords());
 
-            for idx in (0..4).into_iter() {
+            for idx in 0..4 {
                 assert_eq!(right_real_coords.get(idx), tetromino.coords().get(idx))
             }
         }
@@ -47,20 +86,21 @@ mod test {
                 Coord::new(5, 22),
                 TetrominoType::T,
             );
-            tetromino.move_by(Tetromino::get_dxdy(Direction::Left));
-            tetromino.move_by(Tetromino::get_dxdy(Direction::Left));
-            tetromino.move_by(Tetromino::get_dxdy(Direction::Left));
+            // Three moves down, which is what the coords below have always expected
+            tetromino.move_by(Tetromino::get_dxdy(Direction::Down));
+            tetromino.move_by(Tetromino::get_dxdy(Direction::Down));
+            tetromino.move_by(Tetromino::get_dxdy(Direction::Down));
 
             let right_real_coords = vec![
-                Coord { x: 4, y: 19 },
-                Coord { x: 5, y: 20 },
                 Coord { x: 5, y: 19 },
+                Coord { x: 6, y: 20 },
                 Coord { x: 6, y: 19 },
+                Coord { x: 7, y: 19 },
             ];
 
             dbg!(&right_real_coords, tetromino.coords());
 
-            for idx in (0..4).into_iter() {
+            for idx in 0..4 {
                 assert_eq!(right_real_coords.get(idx), tetromino.coords().get(idx))
             }
         }
@@ -76,8 +116,16 @@ mod test {
     }
 
     mod spawn {
-        use crate::tetromino::{
-            coord::Coord, direction::Direction, tetromino_type::TetrominoType, Tetromino,
+        use crate::{
+            tetromino::{
+                coord::Coord, direction::Direction, randomizer::Randomizer,
+                tetromino_type::TetrominoType, Tetromino,
+            },
+            universe::{
+                color::ColorPalette, game::Game, rotations::system::BUILTIN_SYSTEMS, Dimensions,
+                RotationSystem, Universe,
+            },
+            Action,
         };
 
         #[test]
@@ -93,14 +141,14 @@ mod test {
                 TetrominoType::T,
             );
             let right_real_coords = vec![
-                Coord { x: 4, y: 22 },
-                Coord { x: 5, y: 23 },
                 Coord { x: 5, y: 22 },
+                Coord { x: 6, y: 23 },
                 Coord { x: 6, y: 22 },
+                Coord { x: 7, y: 22 },
             ];
             dbg!(&right_real_coords, tetromino.coords());
 
-            for idx in (0..4).into_iter() {
+            for idx in 0..4 {
                 assert_eq!(right_real_coords.get(idx), tetromino.coords().get(idx))
             }
         }
@@ -116,7 +164,10 @@ mod test {
                 Coord::new(5, 10),
                 TetrominoType::T,
             );
-            assert!(tetromino.within_boundary(Tetromino::get_dxdy(Direction::Down), 10, 20));
+            assert!(tetromino.within_boundary(
+                Tetromino::get_dxdy(Direction::Down),
+                &Dimensions::new(10, 20)
+            ));
         }
 
         #[test]
@@ -131,7 +182,2365 @@ mod test {
                 Coord::new(5, 0),
                 TetrominoType::T,
             );
-            assert!(tetromino.within_boundary(Tetromino::get_dxdy(Direction::Down), 10, 20));
+            // Already on the floor, so moving down leaves the board
+            assert!(!tetromino.within_boundary(
+                Tetromino::get_dxdy(Direction::Down),
+                &Dimensions::new(10, 20)
+            ));
+        }
+
+        #[test]
+        fn test_boundary_buffer() {
+            // The T's nub sits in the top row of a 10x20 board with 6 hidden rows
+            let tetromino = Tetromino::spawn_tetromino(
+                vec![
+                    Coord::new(0, 0),
+                    Coord::new(1, 1),
+                    Coord::new(1, 0),
+                    Coord::new(2, 0),
+                ],
+                Coord::new(5, 24),
+                TetrominoType::T,
+            );
+            let dim = Dimensions::with_buffer(10, 20, 6);
+            assert!(tetromino.within_boundary([0, 0], &dim));
+            assert!(!tetromino.within_boundary(Tetromino::get_dxdy(Direction::Up), &dim));
+        }
+
+        #[test]
+        fn test_every_edge_of_the_board() {
+            let dim = Dimensions::with_buffer(10, 20, 6);
+            let (w, h) = (*dim.w() as i32, dim.total_h() as i32);
+
+            // Corners are on the board, and a step past any edge isn't
+            for (x, y) in [(0, 0), (w - 1, 0), (0, h - 1), (w - 1, h - 1)].iter() {
+                assert_eq!(dim.cell(&Coord::new(*x, *y)), Some((*x as u32, *y as u32)));
+            }
+            for (x, y) in [(-1, 0), (w, 0), (0, -1), (0, h), (-1, -1), (w, h)].iter() {
+                assert_eq!(dim.cell(&Coord::new(*x, *y)), None);
+            }
+
+            // A single cell against each edge can't go through it, but can come back off it
+            let edges = [
+                ((0, 5), Direction::Left),
+                ((w - 1, 5), Direction::Right),
+                ((5, 0), Direction::Down),
+                ((5, h - 1), Direction::Up),
+            ];
+            for ((x, y), out) in edges {
+                let [dx, dy] = Tetromino::get_dxdy(out);
+                let cell = Tetromino::new(vec![Coord::new(x, y)], TetrominoType::O);
+                assert!(cell.within_boundary([0, 0], &dim));
+                assert!(!cell.within_boundary([dx, dy], &dim));
+                assert!(cell.within_boundary([-dx, -dy], &dim));
+            }
+
+            // Moving past what a coord can hold is refused rather than wrapping around
+            let mut far = Tetromino::new(vec![Coord::new(i32::MAX, 0)], TetrominoType::O);
+            assert_eq!(Coord::new(i32::MIN, 0).offset([-1, 0]), None);
+            assert!(!far.move_by(Tetromino::get_dxdy(Direction::Right)));
+            assert_eq!(far.coords()[0], Coord::new(i32::MAX, 0));
+            assert!(!far.within_boundary(Tetromino::get_dxdy(Direction::Right), &dim));
+        }
+
+        #[test]
+        fn test_moves_stop_at_the_walls() {
+            for w in [4, 10, 40].iter() {
+                let dim = Dimensions::new(*w, 20);
+                let mut randomizer = Randomizer::new(9);
+                let mut universe = Universe::new(
+                    dim.clone(),
+                    RotationSystem::srs().spawn(randomizer.next_type(), &dim),
+                    vec![],
+                    ColorPalette::default(),
+                    Game::default(),
+                    randomizer,
+                );
+                let walls = [(Action::MoveLeft, 0), (Action::MoveRight, *w as i32 - 1)];
+                for (action, wall) in walls.iter() {
+                    for _ in 0..*w + 5 {
+                        universe.push_action(*action);
+                    }
+                    universe.update();
+                    let focused = universe.focused_tetromino();
+                    let xs: Vec<i32> = focused.coords().iter().map(|coord| coord.x).collect();
+                    assert!(xs.contains(wall));
+                    assert!(xs.iter().all(|x| (0..*w as i32).contains(x)));
+                }
+            }
+        }
+
+        #[test]
+        fn test_spawn_row_follows_height() {
+            for h in [20, 24, 40].iter() {
+                let dim = Dimensions::new(10, *h);
+                let tetromino = RotationSystem::srs().spawn(TetrominoType::T, &dim);
+                let bottom = tetromino.coords().iter().map(|coord| coord.y).min();
+                assert_eq!(bottom, Some(dim.spawn_row() as i32));
+                assert!(tetromino.within_boundary([0, 0], &dim));
+            }
+        }
+
+        #[test]
+        fn test_spawn_centred_on_any_board() {
+            for name in BUILTIN_SYSTEMS.iter() {
+                let system = RotationSystem::builtin(name).unwrap();
+                for (w, h) in [(4, 10), (9, 20), (10, 20), (11, 30), (40, 60)].iter() {
+                    if system.widest() > *w {
+                        continue;
+                    }
+                    let dim = Dimensions::new(*w, *h);
+                    for tetromino_type in system.types().iter() {
+                        let tetromino = system.spawn(*tetromino_type, &dim);
+                        let xs = tetromino.coords().iter().map(|coord| coord.x);
+                        let (left, right) = (xs.clone().min().unwrap(), xs.max().unwrap());
+                        // Centred, with any odd column left over on the right
+                        assert_eq!(left, (*w as i32 - (right - left + 1)) / 2);
+                        assert!(tetromino.within_boundary([0, 0], &dim));
+                        let bottom = tetromino.coords().iter().map(|coord| coord.y).min();
+                        assert_eq!(bottom, Some(dim.spawn_row() as i32));
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn test_play_on_small_and_wide_boards() {
+            for (w, h) in [(4, 10), (40, 60)].iter() {
+                let dim = Dimensions::new(*w, *h);
+                let mut randomizer = Randomizer::new(3);
+                let mut universe = Universe::new(
+                    dim.clone(),
+                    RotationSystem::srs().spawn(randomizer.next_type(), &dim),
+                    vec![],
+                    ColorPalette::default(),
+                    Game::default(),
+                    randomizer,
+                );
+                for frame in 0..2000 {
+                    if frame % 7 == 0 {
+                        universe.push_action(Action::HardDrop);
+                    }
+                    universe.update();
+                }
+                assert!(*universe.game().pieces() > 0);
+                assert!(!universe.game().running());
+            }
+        }
+    }
+
+    mod rotation {
+        use super::{fixture, t_universe};
+        use crate::{
+            config::Kicks180,
+            tetromino::{coord::Coord, tetromino_type::TetrominoType},
+            universe::{
+                game::Game,
+                rotations::{
+                    placement::Placement,
+                    rotation_direction::RotationDirection,
+                    system::{BUILTIN_SYSTEMS, MAX_CELLS},
+                },
+                Dimensions, RotationSystem, Universe,
+            },
+            Action, FrameInput,
+        };
+
+        /// Hard drops the first tetromino of `seed` with `down` held, so that the next one spawns with it
+        fn spawn_holding(seed: u64, irs: bool, ihs: bool, down: Vec<Action>) -> Universe {
+            let mut universe = Universe::from_seed(seed);
+            universe.set_irs(irs);
+            universe.set_ihs(ihs);
+            universe.push_input(&FrameInput {
+                actions: vec![Action::HardDrop],
+                down,
+            });
+            universe.update();
+            universe
+        }
+
+        #[test]
+        fn test_irs_spawns_rotated() {
+            let universe = spawn_holding(4, true, false, vec![Action::RotateCw]);
+            assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 1);
+            let universe = spawn_holding(4, true, false, vec![Action::Rotate180]);
+            assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 2);
+
+            let universe = spawn_holding(4, false, false, vec![Action::RotateCw]);
+            assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 0);
+        }
+
+        #[test]
+        fn test_ihs_spawns_into_hold() {
+            let next = *Universe::from_seed(9).preview().front().unwrap();
+            let universe = spawn_holding(9, false, true, vec![Action::Hold]);
+            assert_eq!(*universe.held(), Some(next));
+            assert_ne!(*universe.focused_tetromino().tetromino_type(), next);
+
+            let universe = spawn_holding(9, false, false, vec![Action::Hold]);
+            assert_eq!(*universe.held(), None);
+            assert_eq!(*universe.focused_tetromino().tetromino_type(), next);
+        }
+
+        /// A T pointing up with its center at (4, 5), and single cells wherever `blocked` says
+        fn t_with_kicks(blocked: &[(i32, i32)], kicks_180: Kicks180) -> Universe {
+            let mut universe = t_universe((4, 5), blocked);
+            universe.set_kicks_180(kicks_180);
+            universe
+        }
+
+        fn rotate_180(universe: &mut Universe) {
+            universe.push_action(Action::Rotate180);
+            universe.update();
+        }
+
+        #[test]
+        fn test_180_in_place() {
+            let mut universe = t_with_kicks(&[], Kicks180::SrsPlus);
+            rotate_180(&mut universe);
+            let t = universe.focused_tetromino();
+            assert_eq!(*t.rotation_state().rn(), 2);
+            assert_eq!(
+                t.coords(),
+                &vec![
+                    Coord::new(4, 5),
+                    Coord::new(5, 5),
+                    Coord::new(4, 4),
+                    Coord::new(3, 5)
+                ]
+            );
+
+            rotate_180(&mut universe);
+            assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 0);
+            assert_eq!(universe.focused_tetromino().coords()[2], Coord::new(4, 6));
+        }
+
+        #[test]
+        fn test_180_kick_tables() {
+            // The cell under the center is taken, so the T can't point down where it is
+            let mut universe = t_with_kicks(&[(4, 4)], Kicks180::None);
+            rotate_180(&mut universe);
+            assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 0);
+            assert_eq!(universe.focused_tetromino().coords()[0], Coord::new(4, 5));
+
+            // SRS+ tries going up first
+            let mut universe = t_with_kicks(&[(4, 4)], Kicks180::SrsPlus);
+            rotate_180(&mut universe);
+            assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 2);
+            assert_eq!(universe.focused_tetromino().coords()[0], Coord::new(4, 6));
+
+            // Nullpomino tries going right first
+            let mut universe = t_with_kicks(&[(4, 4)], Kicks180::Nullpomino);
+            rotate_180(&mut universe);
+            assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 2);
+            assert_eq!(universe.focused_tetromino().coords()[0], Coord::new(5, 5));
+        }
+
+        #[test]
+        fn test_builtin_systems() {
+            // SRS is what tetrominos always spawned as
+            let srs = RotationSystem::srs();
+            let spawns = [
+                (TetrominoType::I, [(4, 21), (3, 21), (5, 21), (6, 21)]),
+                (TetrominoType::O, [(4, 21), (5, 21), (4, 22), (5, 22)]),
+                (TetrominoType::Z, [(4, 21), (5, 21), (3, 22), (4, 22)]),
+            ];
+            for (tetromino_type, cells) in spawns.iter() {
+                let cells: Vec<Coord> = cells.iter().map(|(x, y)| Coord::new(*x, *y)).collect();
+                assert_eq!(
+                    srs.spawn(*tetromino_type, &Dimensions::default()).coords(),
+                    &cells
+                );
+            }
+
+            // Every system is valid, and reads back the same from a file
+            let dir = std::env::temp_dir().join("tetris-rs-rotation");
+            std::fs::create_dir_all(&dir).unwrap();
+            for name in BUILTIN_SYSTEMS.iter() {
+                let system = RotationSystem::builtin(name).unwrap();
+                assert_eq!(system.validate(), Ok(()));
+                let path = dir.join(format!("{}.toml", name));
+                std::fs::write(&path, toml::to_string(&system).unwrap()).unwrap();
+                assert_eq!(RotationSystem::load(&path).unwrap(), system);
+            }
+            std::fs::remove_dir_all(&dir).unwrap();
+
+            // Each piece is described only once
+            let mut broken = RotationSystem::nrs();
+            broken.pieces.push(broken.pieces[0].clone());
+            assert!(broken.validate().is_err());
+            let mut broken = RotationSystem::nrs();
+            broken.pieces[2].states[1].pop();
+            assert!(broken.validate().is_err());
+        }
+
+        #[test]
+        fn test_piece_set_from_shapes() {
+            let dir = std::env::temp_dir().join("tetris-rs-piece-set");
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("set.toml");
+            std::fs::write(
+                &path,
+                r#"
+name = "dominoes and a plus"
+
+[[pieces]]
+piece = 7
+pivot = [0, 0]
+shape = [[0, 0], [1, 0]]
+color = "FF8800"
+spawn = 1
+
+[[pieces]]
+piece = 8
+pivot = [1, 1]
+shape = [[1, 1], [0, 1], [2, 1], [1, 0], [1, 2]]
+"#,
+            )
+            .unwrap();
+            let system = RotationSystem::load(&path).unwrap();
+            std::fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(
+                system.types(),
+                vec![TetrominoType::new(7), TetrominoType::new(8)]
+            );
+            let domino = system.piece(TetrominoType::new(7));
+            assert_eq!(
+                domino.states,
+                vec![
+                    vec![[0, 0], [1, 0]],
+                    vec![[0, 0], [0, -1]],
+                    vec![[0, 0], [-1, 0]],
+                    vec![[0, 0], [0, 1]],
+                ]
+            );
+            assert_eq!(system.colors().len(), 1);
+
+            // The domino spawns standing up, as its spawn orientation says
+            let dim = Dimensions::new(10, 20);
+            let spawned = system.spawn(TetrominoType::new(7), &dim);
+            assert_eq!(*spawned.rotation_state().rn(), 1);
+            assert_eq!(
+                spawned.coords(),
+                &vec![
+                    Coord::new(4, dim.spawn_row() as i32 + 1),
+                    Coord::new(4, dim.spawn_row() as i32)
+                ]
+            );
+        }
+
+        #[test]
+        fn test_clears_past_a_tetris_score_as_one() {
+            // Pentominos can clear 5 lines, and larger pieces more
+            for lines in 4..=MAX_CELLS as u32 {
+                let mut game = Game::default();
+                game.update(lines);
+                assert_eq!(*game.score(), 1200);
+                assert_eq!(*game.lines_cleared(), lines);
+            }
+        }
+
+        #[test]
+        fn test_play_other_piece_sets() {
+            for name in ["pentominoes", "trominoes", "mixed"].iter() {
+                let system = RotationSystem::builtin(name).unwrap();
+                let mut universe = Universe::from_seed(12);
+                universe.set_rotation_system(system.clone());
+                assert_eq!(*universe.held(), None);
+                for frame in 0..3000 {
+                    let action = [
+                        Action::RotateCw,
+                        Action::MoveLeft,
+                        Action::Rotate180,
+                        Action::Hold,
+                        Action::MoveRight,
+                        Action::HardDrop,
+                    ][frame % 6];
+                    universe.push_action(action);
+                    universe.update();
+                    let focused = universe.focused_tetromino();
+                    assert!(system.types().contains(focused.tetromino_type()));
+                    assert_eq!(
+                        focused.coords().len(),
+                        system.piece(*focused.tetromino_type()).states[0].len()
+                    );
+                }
+                assert!(*universe.game().pieces() > 0);
+
+                // Starting over keeps to the same pieces
+                universe.restart(5);
+                assert!(universe
+                    .preview()
+                    .iter()
+                    .all(|piece| system.types().contains(piece)));
+            }
+        }
+
+        /// An ARS J at the spawn point, with single cells wherever `blocked` says
+        fn ars_j(blocked: &[(i32, i32)]) -> Universe {
+            let ars = RotationSystem::ars();
+            let mut universe =
+                fixture(ars.spawn(TetrominoType::J, &Dimensions::default()), blocked);
+            universe.set_rotation_system(ars);
+            universe
+        }
+
+        #[test]
+        fn test_ars_centre_column_rule() {
+            // Spawns as ###/..# along rows 22 and 21, with the box's corner at (3, 21)
+            let universe = ars_j(&[]);
+            assert_eq!(
+                universe.focused_tetromino().coords(),
+                &vec![
+                    Coord::new(3, 22),
+                    Coord::new(4, 22),
+                    Coord::new(5, 22),
+                    Coord::new(5, 21)
+                ]
+            );
+
+            // Blocked on the left, so it kicks right
+            let mut universe = ars_j(&[(3, 21)]);
+            universe.push_action(Action::RotateCw);
+            universe.update();
+            assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 1);
+            assert!(universe
+                .focused_tetromino()
+                .coords()
+                .contains(&Coord::new(5, 23)));
+
+            // Blocked in the centre column first, so it doesn't kick at all
+            let mut universe = ars_j(&[(3, 21), (4, 23)]);
+            universe.push_action(Action::RotateCw);
+            universe.update();
+            assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 0);
+        }
+
+        #[test]
+        fn test_failed_rotation_keeps_state() {
+            // Every cell around the T is taken, so no rotation fits anywhere. The last column is left
+            // open so that no lines get cleared
+            let own = [(4, 5), (3, 5), (5, 5), (4, 6)];
+            let blocked: Vec<(i32, i32)> = (0..9)
+                .flat_map(|x| (0..12).map(move |y| (x, y)))
+                .filter(|cell| !own.contains(cell))
+                .collect();
+            for action in [Action::RotateCw, Action::RotateCcw, Action::Rotate180].iter() {
+                let mut universe = t_with_kicks(&blocked, Kicks180::SrsPlus);
+                let before = universe.focused_tetromino().coords().clone();
+                universe.push_action(*action);
+                universe.update();
+                assert_eq!(universe.focused_tetromino().coords(), &before);
+                assert_eq!(*universe.focused_tetromino().rotation_state().rn(), 0);
+            }
+        }
+
+        #[test]
+        fn test_rotate_is_pure() {
+            let srs = RotationSystem::srs();
+            let t = srs.spawn_placement(TetrominoType::T, &Dimensions::default());
+
+            // Cells come straight from the table of orientations
+            assert_eq!(
+                srs.cells(&t),
+                srs.spawn(TetrominoType::T, &Dimensions::default())
+                    .coords()
+                    .clone()
+            );
+            assert_eq!(srs.placement_of(&srs.tetromino(&t)), t);
+
+            let rotated = srs
+                .rotate(&t, RotationDirection::Clockwise, Kicks180::None, |_| true)
+                .unwrap();
+            assert_eq!(rotated, Placement::new(TetrominoType::T, 1, t.origin));
+            assert_eq!(
+                srs.rotate(
+                    &rotated,
+                    RotationDirection::CounterClockwise,
+                    Kicks180::None,
+                    |_| true
+                ),
+                Some(t)
+            );
+
+            // Nothing fits, so there's no rotation at all
+            assert_eq!(
+                srs.rotate(&t, RotationDirection::Half, Kicks180::SrsPlus, |_| false),
+                None
+            );
+
+            // Blocked where it is, so it takes the second test, one to the left
+            let first = srs.cells(&t.turned(1, [0, 0]));
+            let kicked = srs
+                .rotate(&t, RotationDirection::Clockwise, Kicks180::None, |cells| {
+                    cells != first.as_slice()
+                })
+                .unwrap();
+            assert_eq!(kicked, t.turned(1, [-1, 0]));
+        }
+
+        #[test]
+        fn test_hard_drop_after_move() {
+            // Moving and hard dropping in the same frame lands in the column that was moved to
+            let mut universe = t_with_kicks(&[], Kicks180::None);
+            universe.update();
+            let before = *universe.focused();
+            universe.push_action(Action::MoveLeft);
+            universe.push_action(Action::HardDrop);
+            universe.update();
+            let landed = universe.stagnant_tetrominos().last().unwrap();
+            assert_eq!(
+                *landed.coords(),
+                universe.rotation_system().cells(&Placement::new(
+                    before.piece,
+                    before.rotation,
+                    [before.origin[0] - 1, 0]
+                ))
+            );
+        }
+    }
+
+    mod top_out {
+        use super::t_universe;
+        use crate::{
+            universe::{game::GameOverReason, Universe},
+            Action,
+        };
+
+        fn hard_drop(universe: &mut Universe) {
+            universe.push_action(Action::HardDrop);
+            universe.update();
+        }
+
+        #[test]
+        fn test_block_out() {
+            // Every spawn point is taken, leaving the last column open so that the rows don't clear
+            let blocked: Vec<(i32, i32)> = (0..9).flat_map(|x| vec![(x, 21), (x, 22)]).collect();
+            let mut universe = t_universe((4, 5), &blocked);
+            hard_drop(&mut universe);
+            assert!(!universe.game().running());
+            assert_eq!(*universe.game().game_over(), Some(GameOverReason::BlockOut));
+        }
+
+        #[test]
+        fn test_lock_out() {
+            let mut universe = t_universe((4, 21), &[(3, 20), (4, 20), (5, 20)]);
+            hard_drop(&mut universe);
+            assert!(!universe.game().running());
+            assert_eq!(*universe.game().game_over(), Some(GameOverReason::LockOut));
+        }
+
+        #[test]
+        fn test_partial_lock_out() {
+            // The T's nub ends up just above the visible board
+            let blocked = [(3, 18), (4, 18), (5, 18)];
+
+            let mut universe = t_universe((4, 19), &blocked);
+            hard_drop(&mut universe);
+            assert!(universe.game().running());
+            assert_eq!(*universe.game().game_over(), None);
+
+            let mut universe = t_universe((4, 19), &blocked);
+            universe.set_partial_lock_out(true);
+            hard_drop(&mut universe);
+            assert!(!universe.game().running());
+            assert_eq!(
+                *universe.game().game_over(),
+                Some(GameOverReason::PartialLockOut)
+            );
+        }
+
+        #[test]
+        fn test_clearing_lines_saves_partial_lock_out() {
+            // Filling the rest of row 19 clears it, bringing the nub back into view
+            let mut blocked: Vec<(i32, i32)> = vec![(3, 18), (4, 18), (5, 18)];
+            blocked.extend((0..10).filter(|x| !(3..6).contains(x)).map(|x| (x, 19)));
+            let mut universe = t_universe((4, 19), &blocked);
+            universe.set_partial_lock_out(true);
+            hard_drop(&mut universe);
+            assert!(universe.game().running());
+            assert_eq!(*universe.game().lines_cleared(), 1);
+        }
+    }
+
+    mod moves {
+        use super::fixture;
+        use crate::{
+            tetromino::{coord::Coord, tetromino_type::TetrominoType},
+            universe::{moves::Move, Dimensions, RotationSystem, Universe},
+            Action,
+        };
+
+        /// A universe with `piece` at its spawn point, over single cells wherever `filled` says
+        fn universe_with(piece: TetrominoType, filled: &[(i32, i32)]) -> Universe {
+            fixture(
+                RotationSystem::srs().spawn(piece, &Dimensions::new(10, 20)),
+                filled,
+            )
+        }
+
+        fn sorted(mut cells: Vec<Coord>) -> Vec<Coord> {
+            cells.sort_by_key(|coord| (coord.x, coord.y));
+            cells
+        }
+
+        /// Plays everything but the hard drop of `m` in one frame, and checks that the piece would land where it said
+        fn assert_reaches(universe: &Universe, m: &Move) {
+            let mut universe = universe.clone();
+            for action in m.inputs[..m.inputs.len() - 1].iter() {
+                universe.push_action(*action);
+            }
+            universe.update();
+            universe.full_fall_focused();
+            assert_eq!(
+                sorted(universe.ghost().coords().clone()),
+                sorted(universe.rotation_system().cells(&m.placement))
+            );
+        }
+
+        #[test]
+        fn test_every_drop_on_an_empty_board() {
+            let universe = universe_with(TetrominoType::T, &[]);
+            let moves = universe.moves(universe.focused());
+            // 8 columns lying flat either way up, 9 standing on either side
+            assert_eq!(moves.len(), 8 + 9 + 8 + 9);
+            for m in moves.iter() {
+                assert!(!m.spin);
+                assert_eq!(m.inputs.last(), Some(&Action::HardDrop));
+                assert!(!m.inputs.contains(&Action::SoftDrop));
+                assert_reaches(&universe, m);
+            }
+        }
+
+        #[test]
+        fn test_tucks_under_overhangs() {
+            // A roof over the bottom left corner, that an O can only get under by sliding in from the right
+            let roof: Vec<(i32, i32)> = (0..4).map(|x| (x, 2)).collect();
+            let universe = universe_with(TetrominoType::O, &roof);
+            let moves = universe.moves(universe.focused());
+            let tuck = moves
+                .iter()
+                .find(|m| {
+                    sorted(universe.rotation_system().cells(&m.placement))
+                        == vec![
+                            Coord::new(0, 0),
+                            Coord::new(0, 1),
+                            Coord::new(1, 0),
+                            Coord::new(1, 1),
+                        ]
+                })
+                .unwrap();
+            let slide = tuck
+                .inputs
+                .iter()
+                .position(|action| *action == Action::MoveLeft)
+                .unwrap();
+            assert!(tuck.inputs[..slide].contains(&Action::SoftDrop));
+            for m in moves.iter() {
+                assert_reaches(&universe, m);
+            }
+        }
+
+        #[test]
+        fn test_t_spin_double() {
+            // Rows 0 and 1 leave a T shaped hole under a cell that hangs over its left side
+            let mut filled: Vec<(i32, i32)> = (0..10).filter(|x| *x != 4).map(|x| (x, 0)).collect();
+            filled.extend((0..10).filter(|x| !(3..=5).contains(x)).map(|x| (x, 1)));
+            filled.push((3, 2));
+            let universe = universe_with(TetrominoType::T, &filled);
+            let moves = universe.moves(universe.focused());
+            let slot = vec![
+                Coord::new(3, 1),
+                Coord::new(4, 0),
+                Coord::new(4, 1),
+                Coord::new(5, 1),
+            ];
+            let spin = moves
+                .iter()
+                .find(|m| sorted(universe.rotation_system().cells(&m.placement)) == slot)
+                .unwrap();
+            assert!(spin.spin);
+
+            // Spins are only ever rotated into place
+            for m in moves.iter().filter(|m| m.spin) {
+                assert!(matches!(
+                    m.inputs[m.inputs.len() - 2],
+                    Action::RotateCw | Action::RotateCcw | Action::Rotate180
+                ));
+                assert_reaches(&universe, m);
+            }
+        }
+
+        #[test]
+        fn test_no_moves_when_blocked_out() {
+            let filled: Vec<(i32, i32)> = (0..10).map(|x| (x, 21)).collect();
+            let universe = universe_with(TetrominoType::T, &filled);
+            assert!(universe.moves(universe.focused()).is_empty());
+        }
+    }
+
+    mod replay {
+        use crate::{
+            config::{Config, Handling, Kicks180, Settings},
+            replay::{checksum, Player, Recorder, Replay, ReplayError},
+            tetromino::tetromino_type::TetrominoType,
+            universe::Universe,
+            Action, FrameInput,
+        };
+
+        /// Plays `frames` frames of a scripted game, recording it as it goes
+        fn record_scripted(seed: u64, frames: usize) -> (Universe, Recorder) {
+            let script = [
+                Action::MoveLeft,
+                Action::RotateCw,
+                Action::HardDrop,
+                Action::MoveRight,
+                Action::RotateCcw,
+                Action::Hold,
+                Action::SoftDrop,
+                Action::HardDrop,
+            ];
+            let mut universe = Universe::from_seed(seed);
+            let mut recorder = Recorder::new(&universe, &Handling::default());
+            for frame in 0..frames {
+                if frame % 10 == 0 {
+                    universe.push_action(script[(frame / 10) % script.len()]);
+                }
+                let input = FrameInput::from(universe.pending_actions());
+                universe.update();
+                recorder.record(&input, &universe);
+            }
+            (universe, recorder)
+        }
+
+        #[test]
+        fn test_playback_matches_recording() {
+            let (universe, recorder) = record_scripted(7, 600);
+
+            let mut player = Player::new(recorder.replay().clone());
+            let mut played = player.universe();
+            while player.step(&mut played) {}
+
+            assert_eq!(player.frame(), 600);
+            assert_eq!(player.desync(), None);
+            assert_eq!(checksum(&played), checksum(&universe));
+        }
+
+        #[test]
+        fn test_checksum_covers_hidden_state() {
+            // The O's cells stay put when it rotates, so only its rotation tells the two apart
+            let universe = (0..)
+                .map(Universe::from_seed)
+                .find(|universe| universe.focused().piece == TetrominoType::O)
+                .unwrap();
+            let mut rotated = universe.clone();
+            rotated.push_action(Action::RotateCw);
+            rotated.update();
+            let mut still = universe.clone();
+            still.update();
+            let cells = |universe: &Universe| {
+                let mut cells = universe.focused_tetromino().coords().clone();
+                cells.sort_by_key(|coord| (coord.x, coord.y));
+                cells
+            };
+            assert_eq!(cells(&rotated), cells(&still));
+            assert_ne!(checksum(&rotated), checksum(&still));
+
+            // Same tetromino on the board, different ones to come
+            let first = universe.focused().piece;
+            let other = (universe.seed() + 1..)
+                .map(Universe::from_seed)
+                .find(|other| other.focused().piece == first)
+                .unwrap();
+            assert_eq!(
+                other.focused_tetromino().coords(),
+                universe.focused_tetromino().coords()
+            );
+            assert_ne!(checksum(&other), checksum(&universe));
+        }
+
+        #[test]
+        fn test_frames_are_run_length_encoded() {
+            let (_, recorder) = record_scripted(7, 100);
+            let replay = recorder.replay();
+
+            assert_eq!(replay.len(), 100);
+            // One run for each key press, and one for the gap after it
+            assert_eq!(replay.frames.len(), 20);
+        }
+
+        #[test]
+        fn test_replay_rejects_other_versions() {
+            let (_, recorder) = record_scripted(3, 10);
+            let mut replay = recorder.replay().clone();
+            replay.header.version += 1;
+
+            let path = std::env::temp_dir().join("tetris-rs-replay-version.json");
+            replay.save(&path).unwrap();
+            let loaded = Replay::load(&path);
+            std::fs::remove_file(&path).unwrap();
+
+            assert!(matches!(loaded, Err(ReplayError::Version(_))));
+        }
+
+        #[test]
+        fn test_replay_rejects_missing_start() {
+            let (_, recorder) = record_scripted(3, 700);
+            let mut replay = recorder.replay().clone();
+            replay.snapshots.remove(0);
+
+            let path = std::env::temp_dir().join("tetris-rs-replay-start.json");
+            replay.save(&path).unwrap();
+            let loaded = Replay::load(&path);
+            std::fs::remove_file(&path).unwrap();
+
+            assert!(matches!(loaded, Err(ReplayError::NoStart)));
+        }
+
+        #[test]
+        fn test_header_names_the_rules() {
+            let mut settings = Settings::default();
+            settings.rotation.system = String::from("ars");
+            settings.rotation.kicks_180 = Kicks180::None;
+            settings.game.irs = true;
+            settings.game.ihs = false;
+            settings.game.partial_lock_out = true;
+            settings.board.width = 12;
+            let universe = Universe::from_config(3, &Config::from_settings(settings));
+            let recorder = Recorder::new(&universe, &Handling::default());
+
+            let header = &recorder.replay().header;
+            assert_eq!(header.rotation_system.name, "ars");
+            assert_eq!(header.kicks_180, Kicks180::None);
+            assert!(header.irs);
+            assert!(!header.ihs);
+            assert!(header.partial_lock_out);
+            assert_eq!((header.width, header.height, header.buffer), (12, 20, 20));
+        }
+
+        #[test]
+        fn test_replay_keeps_one_copy_of_the_rules() {
+            let (universe, recorder) = record_scripted(5, 1000);
+            let path = std::env::temp_dir().join("tetris-rs-replay-rules.json");
+            recorder.replay().save(&path).unwrap();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let loaded = Replay::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            // Snapshots only name the rotation system, the header has its tables
+            let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+            let snapshots = value["snapshots"].as_array().unwrap();
+            assert_eq!(snapshots.len(), 4);
+            for snapshot in snapshots.iter() {
+                let rules = &snapshot["universe"]["rotation_system"];
+                assert_eq!(rules["name"], "srs");
+                assert_eq!(rules["pieces"].as_array().unwrap().len(), 0);
+            }
+            assert_eq!(
+                value["header"]["rotation_system"]["pieces"]
+                    .as_array()
+                    .unwrap()
+                    .len(),
+                7
+            );
+
+            // Which are put back on loading, so that it plays back from any of them
+            let mut player = Player::new(loaded);
+            let mut played = player.universe();
+            player.seek(&mut played, 950);
+            while player.step(&mut played) {}
+            assert_eq!(player.desync(), None);
+            assert_eq!(checksum(&played), checksum(&universe));
+        }
+
+        #[test]
+        fn test_seek_matches_playing_through() {
+            let (_, recorder) = record_scripted(11, 1000);
+            let mut player = Player::new(recorder.replay().clone());
+            let mut universe = player.universe();
+
+            player.seek(&mut universe, 450);
+            let expected = checksum(&universe);
+
+            // Back to it from the end, and forward to it from the start
+            player.seek(&mut universe, 1000);
+            player.seek(&mut universe, 450);
+            assert_eq!(player.frame(), 450);
+            assert_eq!(checksum(&universe), expected);
+            player.seek(&mut universe, 0);
+            player.seek(&mut universe, 450);
+            assert_eq!(checksum(&universe), expected);
+        }
+
+        #[test]
+        fn test_step_piece() {
+            let (_, recorder) = record_scripted(11, 1000);
+            let locks = recorder.replay().locks.clone();
+            let mut player = Player::new(recorder.replay().clone());
+            let mut universe = player.universe();
+
+            player.step_piece(&mut universe);
+            player.step_piece(&mut universe);
+            assert_eq!(player.frame(), locks[1]);
+            assert_eq!(*universe.game().pieces(), 2);
+
+            player.step_back_piece(&mut universe);
+            assert_eq!(player.frame(), locks[0]);
+            assert_eq!(*universe.game().pieces(), 1);
+        }
+
+        #[test]
+        fn test_snapshot_round_trip() {
+            let (mut universe, _) = record_scripted(5, 500);
+            let mut restored: Universe =
+                serde_json::from_str(&serde_json::to_string(&universe).unwrap()).unwrap();
+            assert_eq!(checksum(&restored), checksum(&universe));
+
+            // The tetromino stream has to carry on the same way too
+            for _ in 0..5 {
+                universe.push_action(Action::HardDrop);
+                restored.push_action(Action::HardDrop);
+                universe.update();
+                restored.update();
+            }
+            assert_eq!(checksum(&restored), checksum(&universe));
+        }
+    }
+
+    mod save {
+        use crate::{
+            config::Kicks180,
+            replay::checksum,
+            save::{self, SaveError},
+            universe::Universe,
+            Action,
+        };
+
+        fn played(seed: u64) -> Universe {
+            let mut universe = Universe::from_seed(seed);
+            for frame in 0..300 {
+                if frame % 40 == 0 {
+                    universe.push_action(Action::HardDrop);
+                }
+                universe.update();
+            }
+            universe
+        }
+
+        #[test]
+        fn test_save_and_load() {
+            let universe = played(21);
+            let path = std::env::temp_dir().join("tetris-rs-save-round-trip.json");
+            save::save(&universe, &path).unwrap();
+            let loaded = save::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(checksum(&loaded), checksum(&universe));
+            assert_eq!(loaded.seed(), universe.seed());
+        }
+
+        #[test]
+        fn test_load_rejects_bad_files() {
+            let path = std::env::temp_dir().join("tetris-rs-save-bad.json");
+            save::save(&played(4), &path).unwrap();
+            let text = std::fs::read_to_string(&path).unwrap();
+
+            // Cut short
+            std::fs::write(&path, &text[..text.len() / 2]).unwrap();
+            assert!(matches!(save::load(&path), Err(SaveError::Format(_))));
+
+            // From some other version
+            let mut value: serde_json::Value = serde_json::from_str(&text).unwrap();
+            value["version"] = serde_json::json!(save::SAVE_VERSION + 1);
+            std::fs::write(&path, value.to_string()).unwrap();
+            assert!(matches!(save::load(&path), Err(SaveError::Version(_))));
+
+            // Edited by hand
+            let mut value: serde_json::Value = serde_json::from_str(&text).unwrap();
+            value["universe"]["game"]["score"] = serde_json::json!(999_999);
+            std::fs::write(&path, value.to_string()).unwrap();
+            assert!(matches!(save::load(&path), Err(SaveError::Corrupt)));
+
+            // Not ours at all
+            std::fs::write(&path, r#"{"magic": "something else", "version": 1}"#).unwrap();
+            assert!(matches!(save::load(&path), Err(SaveError::NotASave)));
+
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn test_load_rejects_pieces_outside_the_rules() {
+            let path = std::env::temp_dir().join("tetris-rs-save-rules.json");
+            save::save(&played(4), &path).unwrap();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let edits: [(&[&str], serde_json::Value); 5] = [
+                (&["focused", "piece"], serde_json::json!(42)),
+                (&["focused", "rotation"], serde_json::json!(4)),
+                (&["held"], serde_json::json!(42)),
+                (&["randomizer", "pieces"], serde_json::json!([])),
+                (
+                    &["rotation_system", "pieces", "0", "states"],
+                    serde_json::json!([[[0, 0]]]),
+                ),
+            ];
+
+            // Each of these would panic looking the piece up, if the checksum got to it first
+            for (keys, edit) in edits.iter() {
+                let mut value: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let field = keys.iter().fold(&mut value["universe"], |value, key| {
+                    match key.parse::<usize>() {
+                        Ok(idx) => &mut value[idx],
+                        Err(_) => &mut value[*key],
+                    }
+                });
+                *field = edit.clone();
+                std::fs::write(&path, value.to_string()).unwrap();
+                assert!(
+                    matches!(save::load(&path), Err(SaveError::Corrupt)),
+                    "{:?} wasn't rejected",
+                    keys
+                );
+            }
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn test_load_rejects_boards_that_cant_be_played() {
+            let path = std::env::temp_dir().join("tetris-rs-save-dim.json");
+            save::save(&played(6), &path).unwrap();
+            let text = std::fs::read_to_string(&path).unwrap();
+
+            // A board of no columns or rows would divide by zero drawing it, even with a checksum that matches
+            for (key, size) in [("w", 0), ("h", 0), ("buffer", 0), ("w", 1000)].iter() {
+                for rechecksum in [false, true].iter() {
+                    let mut value: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    value["universe"]["dim"][*key] = serde_json::json!(size);
+                    if *rechecksum {
+                        let universe: Universe =
+                            serde_json::from_value(value["universe"].clone()).unwrap();
+                        value["checksum"] = serde_json::json!(checksum(&universe));
+                    }
+                    std::fs::write(&path, value.to_string()).unwrap();
+                    assert!(
+                        matches!(save::load(&path), Err(SaveError::Corrupt)),
+                        "{} of {} wasn't rejected",
+                        key,
+                        size
+                    );
+                }
+            }
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn test_checksum_covers_the_rules() {
+            let universe = played(6);
+            let mut changed = universe.clone();
+            changed.set_irs(true);
+            assert_ne!(checksum(&changed), checksum(&universe));
+            let mut changed = universe.clone();
+            changed.set_kicks_180(Kicks180::None);
+            assert_ne!(checksum(&changed), checksum(&universe));
+            let mut changed = universe.clone();
+            let mut rules = changed.rotation_system().clone();
+            rules.pieces[0].kicks[0].tests[0] = [9, 9];
+            changed.attach_rules(&rules);
+            assert_ne!(checksum(&changed), checksum(&universe));
+        }
+    }
+
+    mod crash {
+        use std::sync::{Arc, Mutex};
+
+        use crate::{
+            config::Handling,
+            crash,
+            replay::{checksum, Player, Recorder, Replay},
+            universe::Universe,
+            Action, FrameInput,
+        };
+
+        #[test]
+        fn test_dump_reproduces_crashing_frame() {
+            let mut universe = Universe::from_seed(99);
+            let recorder = Arc::new(Mutex::new(Recorder::new(&universe, &Handling::default())));
+            crash::track(&recorder);
+            // An opponent's board that gets through its frame, so it isn't the one that crashed
+            let mut opponent = Universe::from_seed(99);
+            let opponent_recorder = Arc::new(Mutex::new(
+                Recorder::new(&opponent, &Handling::default()).crash_only(),
+            ));
+            crash::track(&opponent_recorder);
+            let input = FrameInput::from(vec![Action::HardDrop]);
+            opponent.push_input(&input);
+            opponent.update();
+            crash::lock(&opponent_recorder).record(&input, &opponent);
+
+            let mut before_crash = universe.clone();
+            for frame in 0..250 {
+                if frame % 25 == 0 {
+                    universe.push_action(Action::HardDrop);
+                }
+                let input = FrameInput::from(universe.pending_actions());
+                crash::lock(&recorder).begin_frame(&input);
+                before_crash = universe.clone();
+                universe.update();
+                // The last frame panics partway through, so it never gets recorded
+                if frame < 249 {
+                    crash::lock(&recorder).record(&input, &universe);
+                }
+            }
+
+            let dir = std::env::temp_dir().join("tetris-rs-crash-dump");
+            let paths = crash::dump(&dir, "boom").unwrap();
+            assert_eq!(paths.len(), 1);
+            assert!(paths[0].to_string_lossy().ends_with("-board0.json"));
+            let replay = Replay::load(&paths[0]).unwrap();
+
+            // Without a board partway through a frame, there's no telling which one it was, so both are written
+            crash::track(&recorder);
+            crash::track(&opponent_recorder);
+            crash::lock(&recorder).record(&FrameInput::default(), &universe);
+            assert_eq!(crash::dump(&dir, "boom").unwrap().len(), 2);
+            std::fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(replay.crash.as_deref(), Some("boom"));
+            assert_eq!(replay.len(), 250);
+
+            // Jumping to the crashing frame gives the state it started from
+            let mut player = Player::new(replay);
+            let mut played = player.universe();
+            player.seek(&mut played, 249);
+            assert_eq!(checksum(&played), checksum(&before_crash));
+            // And playing it gives the state it ended in
+            player.step(&mut played);
+            assert_eq!(checksum(&played), checksum(&universe));
+        }
+    }
+
+    mod config {
+        use crate::config::{Config, ConfigError, GameMode, KeySettings, Settings, DEFAULT_CONFIG};
+
+        fn parse(text: &str) -> Result<Settings, ConfigError> {
+            let settings: Settings = toml::from_str(text)?;
+            settings.validate()?;
+            Ok(settings)
+        }
+
+        #[test]
+        fn test_default_file_matches_defaults() {
+            assert_eq!(parse(DEFAULT_CONFIG).unwrap(), Settings::default());
+        }
+
+        #[test]
+        fn test_missing_values_fall_back() {
+            let settings = parse("[board]\nwidth = 12\n\n[keys]\nleft = \"a\"\n").unwrap();
+            assert_eq!(settings.board.width, 12);
+            assert_eq!(settings.board.height, 20);
+            assert_eq!(settings.keys.left, vec!["a"]);
+            assert_eq!(settings.keys.right, KeySettings::default().right);
+            assert_eq!(settings.game.mode, GameMode::Marathon);
+
+            let settings = parse("[game]\nmode = \"versus_bot\"\n").unwrap();
+            assert_eq!(settings.game.mode, GameMode::VersusBot);
+        }
+
+        #[test]
+        fn test_invalid_values_are_reported() {
+            let invalid = [
+                "[window]\nfps = 0\n",
+                "[board]\nwidth = 3\n",
+                "[board]\nheight = 100\n",
+                "[handling]\narr = 0\n",
+                "[gamepad]\ndeadzone = 1.0\n",
+                "[colors]\nt = \"red\"\n",
+                "[audio]\nmusic = 1.5\n",
+                "[keys]\nhard_drop = \"NOT_A_KEY\"\n",
+                "[keys]\nrotate_cw = \"z\"\n",
+                "[keys]\nhold = [\"V\", \"SPACE\"]\n",
+                "[network]\naddress = \"\"\n",
+                "[rotation]\nsystem = \"no/such/system.toml\"\n",
+                "[bot]\nkind = \"tbp\"\ncommand = \"bot\"\n\n[board]\nwidth = 12\n",
+                "[bot]\nkind = \"tbp\"\ncommand = \"bot\"\n\n[board]\nheight = 30\n",
+                "[bot]\nkind = \"tbp\"\ncommand = \"bot\"\n\n[rotation]\nsystem = \"ars\"\n",
+                "[bot]\nkind = \"tbp\"\ncommand = \"bot\"\n\n[rotation]\nsystem = \"pentominoes\"\n",
+            ];
+            for text in invalid.iter() {
+                match parse(text) {
+                    Err(ConfigError::Invalid(message)) => assert!(!message.is_empty()),
+                    _ => panic!("expected {:?} to be rejected", text),
+                }
+            }
+
+            // A tbp bot plays the standard 10x20 board with 20 hidden rows, by SRS
+            assert!(parse("[bot]\nkind = \"tbp\"\ncommand = \"bot\"\n").is_ok());
+
+            assert!(matches!(
+                parse("[game]\nmode = \"zen\"\n"),
+                Err(ConfigError::Parse(_))
+            ));
+            assert!(matches!(parse("[window"), Err(ConfigError::Parse(_))));
+        }
+
+        #[test]
+        fn test_load_from_file() {
+            let path = std::env::temp_dir()
+                .join("tetris-rs-config")
+                .join("config.toml");
+            Config::write_default(&path).unwrap();
+            let config = Config::load(&path).unwrap();
+            std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+            assert_eq!(config.settings(), &Settings::default());
+            assert_eq!(*config.w(), 1600);
+        }
+    }
+    mod input {
+        use raylib::consts::{GamepadButton, KeyboardKey};
+
+        use super::t_universe;
+        use crate::{
+            config::Settings,
+            tetris_input::{
+                device::{Device, Input, Stick},
+                Bindings, TetrominoControls,
+            },
+            universe::{game::GameOverReason, Universe},
+            Action,
+        };
+
+        /// Stands in for a keyboard or gamepad, with whichever inputs the test says are held down
+        #[derive(Default)]
+        struct FakeDevice {
+            down: Vec<Input>,
+        }
+
+        impl Device for FakeDevice {
+            fn is_down(&self, input: Input) -> bool {
+                self.down.contains(&input)
+            }
+        }
+
+        /// Frames out of `frames` on which holding `input` fires an action
+        fn fire_frames(input: Input, frames: u32) -> Vec<u32> {
+            let mut controls = TetrominoControls::default();
+            let device = FakeDevice { down: vec![input] };
+            (0..frames)
+                .filter(|_| {
+                    controls.poll(&device);
+                    !controls.get_queue().is_empty()
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_several_inputs_per_action() {
+            let settings: Settings = toml::from_str(
+                "[keys]\nleft = [\"a\", \"LEFT\", \"pad_left\", \"STICK_LEFT\"]\nhold = \"c\"\nrotate_cw = \"UP\"\n",
+            )
+            .unwrap();
+            settings.validate().unwrap();
+            let bindings = Bindings::from_settings(&settings.keys);
+
+            assert_eq!(
+                bindings.inputs_for(Action::MoveLeft),
+                &[
+                    Input::Key(KeyboardKey::KEY_A),
+                    Input::Key(KeyboardKey::KEY_LEFT),
+                    Input::Button(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT),
+                    Input::Stick(Stick::Left),
+                ]
+            );
+            assert_eq!(
+                bindings.action_for(Input::Key(KeyboardKey::KEY_C)),
+                Some(Action::Hold)
+            );
+            assert_eq!(
+                bindings.action_for(Input::Key(KeyboardKey::KEY_UP)),
+                Some(Action::RotateCw)
+            );
+            assert_eq!(bindings.action_for(Input::Key(KeyboardKey::KEY_Q)), None);
+        }
+
+        #[test]
+        fn test_gamepad_repeats_like_keyboard() {
+            let key = fire_frames(Input::Key(KeyboardKey::KEY_LEFT), 40);
+            // Fires straight away, waits out DAS, then repeats
+            assert_eq!(key[0], 0);
+            assert!(key[1] > 8);
+            assert!(key.len() > 4);
+
+            let button = Input::Button(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT);
+            assert_eq!(fire_frames(button, 40), key);
+            assert_eq!(fire_frames(Input::Stick(Stick::Left), 40), key);
+
+            // Hold doesn't repeat at all
+            assert_eq!(fire_frames(Input::Key(KeyboardKey::KEY_LEFT_SHIFT), 40), vec![0]);
+        }
+
+        #[test]
+        fn test_release_and_press_again() {
+            let mut controls = TetrominoControls::default();
+            let mut device = FakeDevice::default();
+            let pad_a = Input::Button(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN);
+
+            device.down.push(pad_a);
+            controls.poll(&device);
+            assert_eq!(controls.get_queue(), vec![Action::RotateCw]);
+            controls.poll(&device);
+            assert!(controls.get_queue().is_empty());
+
+            device.down.clear();
+            controls.poll(&device);
+            assert!(controls.get_queue().is_empty());
+
+            device.down.push(pad_a);
+            controls.poll(&device);
+            assert_eq!(controls.get_queue(), vec![Action::RotateCw]);
+        }
+
+        #[test]
+        fn test_stick_deadzone() {
+            assert!(!Stick::Left.pushed(-0.4, 0.0, 0.5));
+            assert!(Stick::Left.pushed(-0.6, 0.0, 0.5));
+            assert!(!Stick::Right.pushed(-0.6, 0.0, 0.5));
+            // Up is negative
+            assert!(Stick::Up.pushed(0.0, -0.9, 0.5));
+            assert!(!Stick::Down.pushed(0.0, -0.9, 0.5));
+        }
+
+        #[test]
+        fn test_hold_once_per_piece() {
+            let mut universe = Universe::from_seed(3);
+            let first = *universe.focused_tetromino().tetromino_type();
+            let next = universe.preview()[0];
+
+            universe.push_action(Action::Hold);
+            universe.update();
+            assert_eq!(*universe.held(), Some(first));
+            assert_eq!(*universe.focused_tetromino().tetromino_type(), next);
+
+            // Can't hold again until the piece locks
+            universe.push_action(Action::Hold);
+            universe.update();
+            assert_eq!(*universe.held(), Some(first));
+            assert_eq!(*universe.focused_tetromino().tetromino_type(), next);
+
+            universe.push_action(Action::HardDrop);
+            universe.update();
+            universe.push_action(Action::Hold);
+            universe.update();
+            assert_eq!(*universe.focused_tetromino().tetromino_type(), first);
+        }
+
+        #[test]
+        fn test_actions_after_a_lock_are_dropped() {
+            let mut universe = Universe::from_seed(3);
+            let mut dropped = universe.clone();
+            universe.push_action(Action::HardDrop);
+            universe.push_action(Action::MoveLeft);
+            universe.push_action(Action::Hold);
+            universe.update();
+            dropped.push_action(Action::HardDrop);
+            dropped.update();
+
+            assert_eq!(*universe.game().pieces(), 1);
+            assert_eq!(universe.focused(), dropped.focused());
+            assert_eq!(*universe.held(), None);
+        }
+
+        #[test]
+        fn test_actions_after_game_over_are_dropped() {
+            let mut universe = t_universe((4, 21), &[(3, 20), (4, 20), (5, 20)]);
+            let focused = *universe.focused();
+            universe.push_action(Action::HardDrop);
+            universe.push_action(Action::Hold);
+            universe.update();
+
+            assert_eq!(*universe.game().game_over(), Some(GameOverReason::LockOut));
+            assert_eq!(*universe.held(), None);
+            assert_eq!(universe.focused().piece, focused.piece);
+        }
+
+        #[test]
+        fn test_pause_stops_the_game() {
+            let mut universe = Universe::from_seed(8);
+            universe.update();
+            universe.push_action(Action::Pause);
+            universe.update();
+            let ticks = *universe.game().ticks();
+
+            for _ in 0..100 {
+                universe.push_action(Action::MoveLeft);
+                universe.update();
+            }
+            assert_eq!(*universe.game().ticks(), ticks);
+
+            universe.push_action(Action::Pause);
+            universe.update();
+            assert!(!*universe.game().paused());
+            assert_eq!(*universe.game().ticks(), ticks + 1);
+        }
+    }
+    mod menu {
+        use raylib::consts::KeyboardKey;
+
+        use crate::{
+            config::{Config, Settings, DEFAULT_CONFIG},
+            menu::ControlsMenu,
+            tetris_input::device::Input,
+            Action,
+        };
+
+        #[test]
+        fn test_rebinding_and_conflicts() {
+            let mut menu = ControlsMenu::new(&Settings::default());
+            menu.bind(Action::Hold, Input::Key(KeyboardKey::KEY_V));
+            assert_eq!(menu.settings().keys.hold.last().unwrap(), "V");
+            assert!(menu.conflicts().is_empty());
+
+            // Z is already rotating counter clockwise
+            menu.bind(Action::Hold, Input::Key(KeyboardKey::KEY_Z));
+            assert_eq!(menu.conflicts(), vec!["Z"]);
+            assert!(menu.finish().is_none());
+
+            menu.clear(Action::RotateCcw);
+            menu.bind(Action::RotateCcw, Input::Key(KeyboardKey::KEY_A));
+            let settings = menu.finish().unwrap();
+            assert_eq!(settings.keys.rotate_ccw, vec!["A"]);
+        }
+
+        #[test]
+        fn test_reset_and_save() {
+            let mut menu = ControlsMenu::new(&Settings::default());
+            menu.adjust(0, 3);
+            menu.adjust(1, -10);
+            assert_eq!(menu.settings().handling.das, 11);
+            assert_eq!(menu.settings().handling.arr, 0);
+            // ARR can't be 0
+            assert!(menu.finish().is_none());
+
+            menu.bind(Action::MoveLeft, Input::Key(KeyboardKey::KEY_J));
+            menu.reset();
+            assert_eq!(menu.settings(), &Settings::default());
+
+            // What gets written out reads back the same
+            menu.bind(Action::MoveLeft, Input::Key(KeyboardKey::KEY_J));
+            menu.adjust(4, 2);
+            let settings = menu.finish().unwrap();
+            let path = std::env::temp_dir()
+                .join("tetris-rs-controls")
+                .join("config.toml");
+            Config::save(&settings, &path).unwrap();
+            let loaded = Config::load(&path).unwrap();
+            std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+            assert_eq!(loaded.settings(), &settings);
+        }
+
+        #[test]
+        fn test_save_keeps_the_rest_of_the_file() {
+            let path = std::env::temp_dir()
+                .join("tetris-rs-controls-edited")
+                .join("config.toml");
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let edited = DEFAULT_CONFIG
+                .replace("width = 10", "width = 12 # wider")
+                .replace(
+                    "left = [\"LEFT\", \"PAD_LEFT\", \"STICK_LEFT\"]",
+                    "left = [\n    \"LEFT\",\n    \"PAD_LEFT\",\n]",
+                )
+                .replace("arr = 4\n", "");
+            std::fs::write(&path, &edited).unwrap();
+
+            let mut settings = Config::load(&path).unwrap().settings().clone();
+            settings.keys.left = vec![String::from("J")];
+            settings.handling.arr = 2;
+            settings.handling.das = 6;
+            Config::save(&settings, &path).unwrap();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let loaded = Config::load(&path).unwrap();
+            std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+            assert_eq!(loaded.settings(), &settings);
+            assert_eq!(loaded.settings().board.width, 12);
+            // Comments and hand edits are all still there, the values the menu changed are swapped in place
+            assert!(text.contains("width = 12 # wider"));
+            assert!(text.contains("# Frames that left/right have to be held"));
+            assert!(text.contains("das = 6\n"));
+            assert!(text.contains("left = [\"J\"]\nright"));
+            assert!(!text.contains("PAD_LEFT\","));
+            assert_eq!(text.matches("[keys]").count(), 1);
+            assert_eq!(text.matches("arr = ").count(), 1);
+        }
+    }
+
+    mod gym {
+        use crate::{
+            gym::{Env, GymAction, Rewards, VecEnv},
+            source::heuristic::Field,
+            universe::{game::Game, rotations::placement::Placement},
+            Action,
+        };
+
+        #[test]
+        fn test_reset() {
+            let mut env = Env::new(Rewards::default());
+            env.step(GymAction::Input(Action::HardDrop));
+            let observation = env.reset(8);
+            assert_eq!(observation.board.len(), 40);
+            assert!(observation.board.iter().flatten().all(|filled| !filled));
+            assert_eq!(observation.queue.len(), 5);
+            assert_eq!(observation.hold, None);
+            assert!(observation.can_hold);
+            assert_eq!(observation.combo, 0);
+            assert_eq!(*env.universe().game().pieces(), 0);
+            // Same seed, same game
+            assert_eq!(Env::new(Rewards::default()).reset(8), observation);
+        }
+
+        #[test]
+        fn test_combo_and_back_to_back() {
+            let mut game = Game::default();
+            game.update(4);
+            assert_eq!((*game.combo(), *game.back_to_back()), (1, true));
+            game.update(4);
+            assert_eq!((*game.combo(), *game.back_to_back()), (2, true));
+            // Not clearing anything breaks the combo but not the back to back
+            game.update(0);
+            assert_eq!((*game.combo(), *game.back_to_back()), (0, true));
+            game.update(2);
+            assert_eq!((*game.combo(), *game.back_to_back()), (1, false));
+        }
+
+        #[test]
+        fn test_place() {
+            let rewards = Rewards {
+                holes: -1.0,
+                invalid: -5.0,
+                ..Rewards::default()
+            };
+            let mut env = Env::new(rewards);
+            env.reset(2);
+
+            // Whichever placement leaves holes gets charged for them
+            let field = Field::from_universe(env.universe());
+            let rotation_system = env.universe().rotation_system().clone();
+            let placements = env.placements();
+            let holey = placements
+                .iter()
+                .find(|placement| {
+                    let mut after = field.clone();
+                    after.place(&rotation_system.cells(placement));
+                    after.features().holes > 0
+                })
+                .copied()
+                .unwrap();
+            let held = holey.piece != env.universe().focused().piece;
+            let (observation, reward, done, info) = env.step(GymAction::Place(holey));
+            assert!(!done && !info.invalid);
+            assert_eq!(info.pieces, 1);
+            assert_eq!(observation.hold.is_some(), held);
+            let mut cells: Vec<_> = observation
+                .board
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|(_, filled)| **filled)
+                        .map(move |(x, _)| (x as i32, y as i32))
+                })
+                .collect();
+            let mut expected: Vec<_> = rotation_system
+                .cells(&holey)
+                .iter()
+                .map(|coord| (coord.x, coord.y))
+                .collect();
+            cells.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(cells, expected);
+            let mut after = field.clone();
+            after.place(&rotation_system.cells(&holey));
+            assert_eq!(reward, -(after.features().holes as f32));
+
+            // Off the board is nowhere to be, so the tetromino just drops
+            let nowhere = Placement::new(observation.piece.piece, 0, [-20, 0]);
+            let (_, reward, _, info) = env.step(GymAction::Place(nowhere));
+            assert!(info.invalid);
+            assert_eq!(info.pieces, 2);
+            assert!(reward <= -5.0);
+        }
+
+        #[test]
+        fn test_game_over_and_survival() {
+            let rewards = Rewards {
+                lines: 0.0,
+                survival: 0.5,
+                game_over: -10.0,
+                ..Rewards::default()
+            };
+            let mut env = Env::new(rewards);
+            env.reset(3);
+            let mut steps = 0;
+            loop {
+                let (_, reward, done, info) = env.step(GymAction::Input(Action::HardDrop));
+                steps += 1;
+                if done {
+                    assert_eq!(reward, -10.0);
+                    assert!(info.game_over.is_some());
+                    break;
+                }
+                assert_eq!(reward, 0.5);
+            }
+            assert!(steps > 10);
+            // Nothing more happens once it's over
+            let (_, reward, done, _) = env.step(GymAction::Input(Action::HardDrop));
+            assert!(done);
+            assert_eq!(reward, 0.0);
+        }
+
+        #[test]
+        fn test_vec_env() {
+            let mut envs = VecEnv::new(Env::new(Rewards::default()), 6);
+            let observations = envs.reset(10);
+            assert_eq!(observations.len(), 6);
+            assert_eq!(observations[1], Env::new(Rewards::default()).reset(11));
+
+            let drops = vec![GymAction::Input(Action::HardDrop); 6];
+            let mut finished = 0;
+            for _ in 0..200 {
+                for (observation, _, done, _) in envs.step(&drops) {
+                    if done {
+                        finished += 1;
+                        // Games start over as soon as they end
+                        assert!(observation.board.iter().flatten().all(|filled| !filled));
+                    }
+                }
+            }
+            assert!(finished >= 6);
+            assert!(envs
+                .envs()
+                .iter()
+                .all(|env| *env.universe().game().running()));
+        }
+    }
+
+    mod heuristic {
+        use crate::{
+            config::BotWeights,
+            source::{heuristic::Field, play_headless, Bot, HeuristicBot},
+            tetromino::coord::Coord,
+            universe::{Dimensions, Universe},
+            Action,
+        };
+
+        /// A 10 wide board with `rows` filled in from the bottom, `#` for filled and `.` for empty
+        fn field(rows: &[&str]) -> Field {
+            let mut field = Field::new(&Dimensions::new(10, 20));
+            let cells: Vec<Coord> = rows
+                .iter()
+                .rev()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .filter(|(_, c)| *c == '#')
+                        .map(move |(x, _)| Coord::new(x as i32, y as i32))
+                })
+                .collect();
+            field.place(&cells);
+            field
+        }
+
+        #[test]
+        fn test_features() {
+            let field = field(&[
+                "#...#.....", //
+                "##.#....##",
+                "####.#####",
+            ]);
+            assert_eq!(field.heights(), vec![3, 2, 1, 2, 3, 1, 1, 1, 2, 2]);
+            let features = field.features();
+            assert_eq!(features.height, 18);
+            assert_eq!(features.holes, 2);
+            assert_eq!(features.bumpiness, 7);
+            // The open cell between the 2nd and 4th columns, and the bottom of the covered hole
+            assert_eq!(features.wells, 2);
+            assert_eq!(features.row_transitions, 2 + 4 + 4);
+            assert_eq!(features.column_transitions, 9 + 3);
+
+            // Clearing takes the full row out and moves everything above it down
+            let mut cleared = field.clone();
+            assert_eq!(cleared.place(&[Coord::new(4, 0)]), 1);
+            assert_eq!(cleared.heights(), vec![2, 1, 0, 1, 2, 0, 0, 0, 1, 1]);
+        }
+
+        #[test]
+        fn test_t_slots() {
+            let ready = field(&[
+                "###.......", //
+                "##...#####",
+                "###.######",
+            ]);
+            assert_eq!(ready.features().t_slots, 1);
+
+            // Without the overhang there's nothing for a T to spin under
+            let open = field(&[
+                "##........", //
+                "##...#####",
+                "###.######",
+            ]);
+            assert_eq!(open.features().t_slots, 0);
+        }
+
+        #[test]
+        fn test_heuristic_bot_survives() {
+            let mut universe = Universe::from_seed(11);
+            let mut bot = HeuristicBot::new(BotWeights::default());
+            play_headless(&mut universe, &mut bot, 120);
+            assert!(*universe.game().running());
+            assert_eq!(*universe.game().pieces(), 120);
+            // Most of what goes in comes back out as lines
+            assert!(*universe.game().lines_cleared() >= 40);
+        }
+
+        #[test]
+        fn test_heuristic_bot_holds() {
+            let mut universe = Universe::from_seed(4);
+            let mut bot = HeuristicBot::new(BotWeights::default());
+            let mut held = 0;
+            while *universe.game().pieces() < 50 {
+                let plan = bot.plan(&universe);
+                let holds = plan
+                    .iter()
+                    .filter(|action| **action == Action::Hold)
+                    .count();
+                assert!(holds <= 1);
+                held += holds;
+                for action in plan {
+                    universe.push_action(action);
+                }
+                universe.update();
+            }
+            assert!(held > 0);
+        }
+    }
+
+    mod sim {
+        use crate::{
+            config::{BotKind, BotSettings, Config},
+            sim::{self, CsvRow, GameStats, Summary},
+            source::RandomBot,
+            universe::{game::GameOverReason, Universe},
+        };
+
+        fn stats(
+            seed: u64,
+            lines: u32,
+            tetris_rate: f32,
+            top_out: Option<GameOverReason>,
+        ) -> GameStats {
+            GameStats {
+                seed,
+                lines,
+                score: lines * 100,
+                pieces: 10 * seed as u32,
+                max_height: 4 + seed as u32,
+                tetrises: 0,
+                tetris_rate,
+                top_out,
+            }
+        }
+
+        #[test]
+        fn test_summary() {
+            let games = [
+                stats(1, 10, 0.4, None),
+                stats(2, 30, 0.0, Some(GameOverReason::BlockOut)),
+                stats(3, 0, 0.0, Some(GameOverReason::LockOut)),
+            ];
+            let summary = Summary::of(&games);
+            assert_eq!(summary.games, 3);
+            assert_eq!(summary.mean_lines, 40.0 / 3.0);
+            assert_eq!(summary.mean_pieces, 20.0);
+            assert_eq!(summary.max_height, 7);
+            // 4 of the 40 lines came from tetrises
+            assert!((summary.tetris_rate - 0.1).abs() < 1e-6);
+            assert_eq!(summary.top_outs, 2);
+            assert_eq!((summary.block_outs, summary.lock_outs), (1, 1));
+            assert_eq!(Summary::of(&[]), Summary::default());
+        }
+
+        #[test]
+        fn test_summary_doesnt_overflow() {
+            let mut game = stats(1, 0, 0.0, None);
+            game.lines = u32::MAX;
+            game.score = u32::MAX;
+            let summary = Summary::of(&[game.clone(), game]);
+            assert_eq!(summary.mean_lines, u32::MAX as f32);
+            assert_eq!(summary.mean_score, u32::MAX as f32);
+        }
+
+        #[test]
+        fn test_csv() {
+            let mut out = Vec::new();
+            sim::write_csv(
+                &mut out,
+                &[
+                    stats(1, 10, 0.4, None),
+                    stats(2, 0, 0.0, Some(GameOverReason::BlockOut)),
+                ],
+            )
+            .unwrap();
+            let out = String::from_utf8(out).unwrap();
+            let lines: Vec<&str> = out.lines().collect();
+            assert_eq!(lines[0], GameStats::header());
+            assert_eq!(lines[1], "1,10,1000,10,5,0,0.4,");
+            assert_eq!(lines[2], "2,0,0,20,6,0,0,block out");
+            // Every row has a value for every column
+            let columns = Summary::header().split(',').count();
+            assert_eq!(Summary::default().row().split(',').count(), columns);
+        }
+
+        #[test]
+        fn test_play() {
+            let mut universe = Universe::from_seed(6);
+            let game = sim::play(&mut universe, &mut RandomBot::new(6), 500);
+            // Random drops don't last long
+            assert_eq!(game.top_out, *universe.game().game_over());
+            assert!(game.top_out.is_some());
+            assert!(game.pieces < 500);
+            assert!(game.max_height >= *universe.dim().h());
+            assert_eq!(game.seed, 6);
+        }
+
+        #[test]
+        fn test_run() {
+            let bot = BotSettings {
+                kind: BotKind::Random,
+                ..BotSettings::default()
+            };
+            let config = Config::default();
+            let games = sim::run(&config, &bot, 20..28, 200).unwrap();
+            let seeds: Vec<u64> = games.iter().map(|game| game.seed).collect();
+            assert_eq!(seeds, (20..28).collect::<Vec<_>>());
+            // Games spread over threads play out just the same as one after the other
+            let mut universe = Universe::from_config(23, &config);
+            assert_eq!(
+                games[3],
+                sim::play(&mut universe, &mut RandomBot::new(23), 200)
+            );
+
+            let missing = BotSettings {
+                kind: BotKind::Tbp,
+                command: String::from("./there-is-no-such-bot"),
+                ..BotSettings::default()
+            };
+            assert!(sim::run(&config, &missing, 0..2, 10).is_err());
+        }
+    }
+
+    mod tournament {
+        use std::collections::HashSet;
+
+        use crate::{
+            config::{BotKind, Config},
+            replay::{Player, Replay},
+            tournament::{expected, Entrant, Outcome, Tournament, START_ELO},
+        };
+
+        fn entrants(bots: &[&str]) -> Vec<Entrant> {
+            bots.iter()
+                .map(|bot| Entrant::parse(bot, &Config::default()).unwrap())
+                .collect()
+        }
+
+        #[test]
+        fn test_expected() {
+            assert_eq!(expected(START_ELO, START_ELO), 0.5);
+            assert!((expected(1900.0, 1500.0) - 10.0 / 11.0).abs() < 1e-5);
+            assert!((expected(1600.0, 1450.0) + expected(1450.0, 1600.0) - 1.0).abs() < 1e-5);
+        }
+
+        #[test]
+        fn test_entrant_parse() {
+            let config = Config::default();
+            let parse = |arg| Entrant::parse(arg, &config);
+            assert_eq!(parse("h=heuristic").unwrap().bot.kind, BotKind::Heuristic);
+            assert_eq!(parse("r=random").unwrap().bot.kind, BotKind::Random);
+            let tbp = parse("cold clear=tbp:cold-clear --threads 2").unwrap();
+            assert_eq!(tbp.name, "cold clear");
+            assert_eq!(tbp.bot.kind, BotKind::Tbp);
+            assert_eq!(tbp.bot.command, "cold-clear");
+            assert_eq!(tbp.bot.args, vec!["--threads", "2"]);
+
+            assert!(parse("heuristic").is_err());
+            assert!(parse("=heuristic").is_err());
+            assert!(parse("a=minimax").is_err());
+            assert!(parse("t=tbp:").is_err());
+            assert!(parse("h=heuristic:/no/such/config.toml").is_err());
+        }
+
+        #[test]
+        fn test_round_robin() {
+            let dir = std::env::temp_dir().join(format!("tournament-{}", std::process::id()));
+            let mut tournament = Tournament::new(
+                &Config::default(),
+                entrants(&["heuristic=heuristic", "random=random"]),
+                vec![3, 4],
+                80,
+            )
+            .with_replays(&dir);
+            tournament.round_robin().unwrap();
+
+            // Both seeds get played from either side
+            let games = tournament.games();
+            assert_eq!(games.len(), 4);
+            let sides: HashSet<(u64, &str)> = games
+                .iter()
+                .map(|game| (game.seed, game.left.name.as_str()))
+                .collect();
+            assert_eq!(sides.len(), 4);
+            for game in games.iter() {
+                let heuristic_won = if game.left.name == "heuristic" {
+                    Outcome::Left
+                } else {
+                    Outcome::Right
+                };
+                assert_eq!(game.outcome, heuristic_won);
+            }
+
+            let standings = tournament.standings();
+            assert_eq!(standings[0].name, "heuristic");
+            assert_eq!((standings[0].wins, standings[0].points), (4, 4.0));
+            assert_eq!((standings[1].losses, standings[1].points), (4, 0.0));
+            assert!(standings[0].elo > START_ELO);
+            assert!((standings[0].elo + standings[1].elo - 2.0 * START_ELO).abs() < 1e-3);
+            let table = tournament.to_string();
+            assert!(table.lines().nth(1).unwrap().contains("heuristic"));
+
+            // Replays of each board play back to the same result
+            let game = &games[0];
+            let name = format!(
+                "r1-{}-{}-vs-{}-right.json",
+                game.seed, game.left.name, game.right.name
+            );
+            let replay = Replay::load(&dir.join(name)).unwrap();
+            assert_eq!(replay.header.player, game.right.name);
+            let mut player = Player::new(replay);
+            let mut universe = player.universe();
+            while player.step(&mut universe) {}
+            assert_eq!(player.desync(), None);
+            assert_eq!(*universe.game().lines_cleared(), game.right.lines);
+            assert_eq!(*universe.game().pieces(), game.right.pieces);
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn test_swiss() {
+            let mut tournament = Tournament::new(
+                &Config::default(),
+                entrants(&["a=random", "b=random", "c=random", "d=random", "e=random"]),
+                vec![1],
+                10,
+            );
+            tournament.swiss(3).unwrap();
+
+            // 2 pairings a round, with 2 games each, and nobody meets twice or sits out twice
+            let games = tournament.games();
+            assert_eq!(games.len(), 12);
+            let pairings: HashSet<(&str, &str)> = games
+                .iter()
+                .map(|game| {
+                    let (left, right) = (game.left.name.as_str(), game.right.name.as_str());
+                    (left.min(right), left.max(right))
+                })
+                .collect();
+            assert_eq!(pairings.len(), 6);
+            let standings = tournament.standings();
+            assert_eq!(
+                standings.iter().map(|standing| standing.byes).sum::<u32>(),
+                3
+            );
+            assert!(standings.iter().all(|standing| standing.byes <= 1));
+            // A bye is worth as much as a pairing's games
+            let points: f32 = standings.iter().map(|standing| standing.points).sum();
+            assert_eq!(points, 12.0 + 3.0 * 2.0);
+        }
+    }
+
+    mod tune {
+        use crate::{
+            config::{BotWeights, Settings},
+            tune::{Trial, Tuner},
+        };
+
+        /// A tuner starting from `start`, for fitnesses that don't play any games
+        fn tuner(
+            start: &BotWeights,
+            sigma: f32,
+            population: usize,
+            elite: usize,
+            seed: u64,
+        ) -> Tuner {
+            let mut settings = Settings::default();
+            settings.bot.weights = start.clone();
+            let trial = Trial {
+                seeds: Vec::new(),
+                pieces: 0,
+                settings,
+            };
+            Tuner::new(trial, sigma, population, elite, seed)
+        }
+
+        /// Closer to `target` is better, with the best possible being 0
+        fn distance(weights: &BotWeights, target: &BotWeights) -> f32 {
+            -weights
+                .each()
+                .iter()
+                .zip(target.each().iter())
+                .map(|((_, a), (_, b))| (a - b).powi(2))
+                .sum::<f32>()
+        }
+
+        fn target() -> BotWeights {
+            let mut target = BotWeights::default();
+            for (idx, (_, weight)) in target.each_mut().iter_mut().enumerate() {
+                **weight = idx as f32 - 4.0;
+            }
+            target
+        }
+
+        #[test]
+        fn test_converges() {
+            let target = target();
+            let start = BotWeights::default();
+            let mut tuner = tuner(&start, 2.0, 24, 6, 1);
+            for _ in 0..60 {
+                tuner.step(|weights| distance(weights, &target));
+            }
+            assert_eq!(tuner.generation(), 60);
+            let best = tuner.best().unwrap();
+            assert!(best.fitness > -0.1, "only got to {}", best.fitness);
+            assert!(distance(tuner.mean(), &target) > distance(&start, &target));
+            // The last generation comes best first
+            let last = tuner.last();
+            assert_eq!(last.len(), 24);
+            assert!(last
+                .windows(2)
+                .all(|pair| pair[0].fitness >= pair[1].fitness));
+        }
+
+        #[test]
+        fn test_checkpoint() {
+            let target = target();
+            let fitness = |weights: &BotWeights| distance(weights, &target);
+            let mut tuner = tuner(&BotWeights::default(), 1.0, 8, 2, 5);
+            tuner.step(fitness);
+            tuner.step(fitness);
+
+            let path = std::env::temp_dir().join(format!("tune-{}.json", std::process::id()));
+            tuner.save(&path).unwrap();
+            let mut resumed = Tuner::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(resumed, tuner);
+
+            // Carrying on from a checkpoint goes exactly the way that not stopping would have
+            tuner.step(fitness);
+            resumed.step(fitness);
+            assert_eq!(resumed, tuner);
+        }
+
+        #[test]
+        fn test_load_rejects_checkpoints_that_cant_carry_on() {
+            let path = std::env::temp_dir().join(format!("tune-bad-{}.json", std::process::id()));
+            tuner(&BotWeights::default(), 1.0, 8, 2, 5)
+                .save(&path)
+                .unwrap();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let edits = [
+                ("/trial/settings/board/width", serde_json::json!(0)),
+                (
+                    "/trial/settings/rotation/system",
+                    serde_json::json!("/no/such/system.toml"),
+                ),
+                ("/elite", serde_json::json!(9)),
+                ("/sigma", serde_json::json!([1.0])),
+            ];
+            for (pointer, edit) in edits.iter() {
+                let mut value: serde_json::Value = serde_json::from_str(&text).unwrap();
+                *value.pointer_mut(pointer).unwrap() = edit.clone();
+                std::fs::write(&path, value.to_string()).unwrap();
+                assert!(Tuner::load(&path).is_err(), "{} wasn't rejected", pointer);
+            }
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    mod tbp {
+        use std::{
+            io::{self, BufReader, PipeReader, PipeWriter},
+            thread,
+        };
+
+        use crate::{
+            source::{
+                play_headless,
+                tbp::{
+                    self, BotMessage, FrontendMessage, Location, Orientation, Spin, TbpGame,
+                    TbpMove,
+                },
+                TbpBot,
+            },
+            tetromino::{coord::Coord, tetromino_type::TetrominoType},
+            universe::Universe,
+        };
+
+        #[test]
+        fn test_messages() {
+            let json = |message: &FrontendMessage| serde_json::to_string(message).unwrap();
+            assert_eq!(json(&FrontendMessage::Rules {}), r#"{"type":"rules"}"#);
+            assert_eq!(json(&FrontendMessage::Suggest), r#"{"type":"suggest"}"#);
+            assert_eq!(
+                json(&FrontendMessage::NewPiece {
+                    piece: TetrominoType::S
+                }),
+                r#"{"type":"new_piece","piece":"S"}"#
+            );
+
+            let suggestion = r#"{"type":"suggestion","moves":[{"location":{"type":"T","orientation":"east","x":0,"y":1},"spin":"full"}]}"#;
+            let mv = TbpMove {
+                location: Location {
+                    piece: TetrominoType::T,
+                    orientation: Orientation::East,
+                    x: 0,
+                    y: 1,
+                },
+                spin: Spin::Full,
+            };
+            assert_eq!(
+                serde_json::from_str::<BotMessage>(suggestion).unwrap(),
+                BotMessage::Suggestion { moves: vec![mv] }
+            );
+            assert_eq!(
+                json(&FrontendMessage::Play { mv }),
+                r#"{"type":"play","move":{"location":{"type":"T","orientation":"east","x":0,"y":1},"spin":"full"}}"#
+            );
+        }
+
+        #[test]
+        fn test_location_cells() {
+            let cells = |piece, orientation, x, y| {
+                let mut cells = Location {
+                    piece,
+                    orientation,
+                    x,
+                    y,
+                }
+                .cells()
+                .unwrap();
+                cells.sort_by_key(|coord| (coord.x, coord.y));
+                cells
+            };
+            let coords = |coords: &[(i32, i32)]| -> Vec<Coord> {
+                coords.iter().map(|(x, y)| Coord::new(*x, *y)).collect()
+            };
+            assert_eq!(
+                cells(TetrominoType::T, Orientation::North, 4, 0),
+                coords(&[(3, 0), (4, 0), (4, 1), (5, 0)])
+            );
+            // Pointing east means the bump is on the right
+            assert_eq!(
+                cells(TetrominoType::T, Orientation::East, 0, 1),
+                coords(&[(0, 0), (0, 1), (0, 2), (1, 1)])
+            );
+            assert_eq!(
+                cells(TetrominoType::I, Orientation::West, 0, 1),
+                coords(&[(0, 0), (0, 1), (0, 2), (0, 3)])
+            );
+            assert!(Location {
+                piece: TetrominoType::new(7),
+                orientation: Orientation::North,
+                x: 0,
+                y: 0,
+            }
+            .cells()
+            .is_none());
+        }
+
+        /// A bot that always holds, dropping whatever comes out of hold flat into the lowest spot it fits
+        fn holding_bot(reader: PipeReader, mut writer: PipeWriter) {
+            let mut reader = BufReader::new(reader);
+            let info = BotMessage::Info {
+                name: String::from("holder"),
+                version: String::from("1"),
+                author: String::new(),
+                features: Vec::new(),
+            };
+            tbp::send(&mut writer, &info).unwrap();
+            let mut game: Option<TbpGame> = None;
+            while let Ok(message) = tbp::receive(&mut reader) {
+                match message {
+                    FrontendMessage::Rules {} => {
+                        tbp::send(&mut writer, &BotMessage::Ready).unwrap()
+                    }
+                    FrontendMessage::Start {
+                        hold, queue, board, ..
+                    } => game = Some(TbpGame::start(hold, queue, &board)),
+                    FrontendMessage::Suggest => {
+                        let game = game.as_ref().unwrap();
+                        let piece = game.hold.unwrap_or(game.queue[1]);
+                        let moves = (1..9)
+                            .filter_map(|x| {
+                                (0..30).find_map(|y| {
+                                    let location = Location {
+                                        piece,
+                                        orientation: Orientation::North,
+                                        x,
+                                        y,
+                                    };
+                                    Some(location).filter(|location| {
+                                        game.field.fits(&location.cells().unwrap())
+                                    })
+                                })
+                            })
+                            .min_by_key(|location| location.y)
+                            .map(|location| TbpMove {
+                                location,
+                                spin: Spin::None,
+                            })
+                            .into_iter()
+                            .collect();
+                        tbp::send(&mut writer, &BotMessage::Suggestion { moves }).unwrap();
+                    }
+                    FrontendMessage::Play { mv } => {
+                        game.as_mut().unwrap().play(&mv);
+                    }
+                    FrontendMessage::NewPiece { piece } => game.as_mut().unwrap().queue.push(piece),
+                    FrontendMessage::Stop => game = None,
+                    FrontendMessage::Quit => break,
+                }
+            }
+        }
+
+        #[test]
+        fn test_bot_holds_and_stays_in_sync() {
+            let (from_game, to_bot) = io::pipe().unwrap();
+            let (from_bot, to_game) = io::pipe().unwrap();
+            let handle = thread::spawn(move || holding_bot(from_game, to_game));
+
+            let mut bot = TbpBot::new(BufReader::new(from_bot), to_bot).unwrap();
+            assert_eq!(bot.name(), "holder");
+            let mut universe = Universe::from_seed(5);
+            play_headless(&mut universe, &mut bot, 30);
+            assert!(*universe.game().running());
+            assert_eq!(*universe.game().pieces(), 30);
+            assert!(universe.held().is_some());
+            // Every hold and new tetromino was followed along, so the bot never had to be started over
+            assert_eq!(bot.starts(), 1);
+
+            drop(bot);
+            handle.join().unwrap();
+        }
+    }
+
+    mod source {
+        use std::{net::TcpListener, thread};
+
+        use crate::{
+            board::Board,
+            config::Handling,
+            device::{Device, Input},
+            replay::{checksum, Recorder},
+            source::{
+                network, BotSource, Frame, InputSource, RandomBot, ReplaySource, ScriptedSource,
+            },
+            universe::Universe,
+            Action,
+        };
+
+        /// Nothing held down, for sources that don't read the keyboard anyway
+        struct NoInput;
+
+        impl Device for NoInput {
+            fn is_down(&self, _input: Input) -> bool {
+                false
+            }
+        }
+
+        /// A board playing a short scripted game on `seed`
+        fn scripted_board(seed: u64) -> Board {
+            let script = ScriptedSource::every(
+                20,
+                &[Action::MoveLeft, Action::RotateCw, Action::HardDrop],
+                10,
+            );
+            Board::new(Universe::from_seed(seed), Box::new(script))
+        }
+
+        #[test]
+        fn test_recorded_game_plays_back_through_board() {
+            let mut universe = Universe::from_seed(8);
+            let mut recorder = Recorder::new(&universe, &Handling::default());
+            let mut script = ScriptedSource::every(15, &[Action::MoveRight, Action::HardDrop], 12);
+            while let Frame::Input(input) = script.next_frame(&NoInput, &universe) {
+                universe.push_input(&input);
+                universe.update();
+                recorder.record(&input, &universe);
+            }
+            assert!(*universe.game().pieces() > 0);
+
+            let mut board = Board::new(
+                Universe::from_seed(8),
+                Box::new(ReplaySource::new(recorder.replay().clone())),
+            );
+            while !board.finished() {
+                board.tick(&NoInput);
+            }
+            assert_eq!(checksum(board.universe()), checksum(&universe));
+        }
+
+        #[test]
+        fn test_bot_places_tetrominos() {
+            let mut board = Board::new(
+                Universe::from_seed(3),
+                Box::new(BotSource::new(Box::new(RandomBot::new(3)), 2)),
+            );
+            for _ in 0..600 {
+                board.tick(&NoInput);
+            }
+            assert!(*board.universe().game().pieces() >= 10);
+        }
+
+        #[test]
+        fn test_network_peers_see_the_same_game() {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            let client = thread::spawn(move || network::connect(address).unwrap());
+            let host = network::host(&listener, 21).unwrap();
+            let client = client.join().unwrap();
+            assert_eq!(client.seed, 21);
+
+            // The host plays, the client watches
+            // Our end of the connection stays open for as long as either half of it is around
+            drop(host.source);
+            let mut local = scripted_board(host.seed).with_peer(host.sender);
+            let mut remote = Board::new(Universe::from_seed(client.seed), Box::new(client.source));
+            while !local.finished() {
+                local.tick(&NoInput);
+            }
+            drop(local);
+            while !remote.finished() {
+                remote.tick(&NoInput);
+            }
+
+            let mut expected = scripted_board(21);
+            while !expected.finished() {
+                expected.tick(&NoInput);
+            }
+            assert_eq!(checksum(remote.universe()), checksum(expected.universe()));
         }
     }
 }
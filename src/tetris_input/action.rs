@@ -0,0 +1,80 @@
+// Everything that a player can ask the game to do, independent of which input asked for it
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    Hold,
+    Pause,
+    Restart,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::RotateCw,
+        Action::RotateCcw,
+        Action::Rotate180,
+        Action::Hold,
+        Action::Pause,
+        Action::Restart,
+    ];
+
+    /// Name of the action in the `[keys]` section of the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "left",
+            Action::MoveRight => "right",
+            Action::SoftDrop => "soft_drop",
+            Action::HardDrop => "hard_drop",
+            Action::RotateCw => "rotate_cw",
+            Action::RotateCcw => "rotate_ccw",
+            Action::Rotate180 => "rotate_180",
+            Action::Hold => "hold",
+            Action::Pause => "pause",
+            Action::Restart => "restart",
+        }
+    }
+
+    /// Whether holding down an input bound to the action keeps firing it
+    pub fn repeats(&self) -> bool {
+        !matches!(self, Action::Hold | Action::Pause | Action::Restart)
+    }
+
+    /// Whether holding it down while a tetromino spawns does anything, for IRS and IHS
+    pub fn acts_on_spawn(&self) -> bool {
+        matches!(
+            self,
+            Action::RotateCw | Action::RotateCcw | Action::Rotate180 | Action::Hold
+        )
+    }
+}
+
+/// Everything the universe is told about a single frame
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct FrameInput {
+    // Fired this frame, either pressed or repeating
+    pub actions: Vec<Action>,
+    // Held down this frame, only the ones that act on spawn
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub down: Vec<Action>,
+}
+
+impl From<Vec<Action>> for FrameInput {
+    fn from(actions: Vec<Action>) -> Self {
+        FrameInput {
+            actions,
+            down: Vec::new(),
+        }
+    }
+}
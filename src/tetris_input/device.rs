@@ -0,0 +1,90 @@
+// Where inputs are read from, so that the keyboard, gamepads and fakes in tests all go through the same repeat logic
+use raylib::prelude::*;
+
+/// Direction that the left analog stick is pushed in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stick {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Stick {
+    /// Whether the stick at `x`, `y` is pushed this way past the deadzone, up being negative as in raylib
+    pub fn pushed(&self, x: f32, y: f32, deadzone: f32) -> bool {
+        match self {
+            Stick::Left => -x > deadzone,
+            Stick::Right => x > deadzone,
+            Stick::Up => -y > deadzone,
+            Stick::Down => y > deadzone,
+        }
+    }
+}
+
+/// Anything that can be bound to an action
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    Key(KeyboardKey),
+    // Includes the D-pad, which raylib counts as the left face buttons
+    Button(GamepadButton),
+    Stick(Stick),
+}
+
+pub trait Device {
+    /// Whether `input` is being held down this frame
+    fn is_down(&self, input: Input) -> bool;
+}
+
+/// The keyboard and the first gamepad, as seen through raylib
+pub struct RaylibDevice<'a> {
+    rl: &'a RaylibHandle,
+    gamepad: GamepadNumber,
+    // How far the stick has to be pushed, from 0 to 1, before it counts
+    deadzone: f32,
+}
+
+impl<'a> RaylibDevice<'a> {
+    pub fn new(rl: &'a RaylibHandle, deadzone: f32) -> Self {
+        RaylibDevice {
+            rl,
+            gamepad: GamepadNumber::GAMEPAD_PLAYER1,
+            deadzone,
+        }
+    }
+
+    /// Whichever key or gamepad button was pressed this frame, for binding it to something
+    pub fn pressed_input(rl: &mut RaylibHandle) -> Option<Input> {
+        if let Some(key) = rl.get_key_pressed() {
+            return Some(Input::Key(key));
+        }
+        match rl.get_gamepad_button_pressed() {
+            Some(GamepadButton::GAMEPAD_BUTTON_UNKNOWN) | None => None,
+            Some(button) => Some(Input::Button(button)),
+        }
+    }
+}
+
+impl Device for RaylibDevice<'_> {
+    fn is_down(&self, input: Input) -> bool {
+        match input {
+            Input::Key(key) => self.rl.is_key_down(key),
+            Input::Button(button) => {
+                self.rl.is_gamepad_available(self.gamepad)
+                    && self.rl.is_gamepad_button_down(self.gamepad, button)
+            }
+            Input::Stick(stick) => {
+                if !self.rl.is_gamepad_available(self.gamepad) {
+                    return false;
+                }
+                let x = self
+                    .rl
+                    .get_gamepad_axis_movement(self.gamepad, GamepadAxis::GAMEPAD_AXIS_LEFT_X);
+                let y = self
+                    .rl
+                    .get_gamepad_axis_movement(self.gamepad, GamepadAxis::GAMEPAD_AXIS_LEFT_Y);
+                stick.pushed(x, y, self.deadzone)
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// Names for keys and gamepad inputs, so that they can be written down in the config file
+use raylib::consts::{GamepadButton, KeyboardKey};
+
+use super::device::{Input, Stick};
+
+/// Every key that can be bound, named as in raylib without the `KEY_` prefix
+const KEY_NAMES: [(&str, KeyboardKey); 105] = [
+    ("APOSTROPHE", KeyboardKey::KEY_APOSTROPHE),
+    ("COMMA", KeyboardKey::KEY_COMMA),
+    ("MINUS", KeyboardKey::KEY_MINUS),
+    ("PERIOD", KeyboardKey::KEY_PERIOD),
+    ("SLASH", KeyboardKey::KEY_SLASH),
+    ("ZERO", KeyboardKey::KEY_ZERO),
+    ("ONE", KeyboardKey::KEY_ONE),
+    ("TWO", KeyboardKey::KEY_TWO),
+    ("THREE", KeyboardKey::KEY_THREE),
+    ("FOUR", KeyboardKey::KEY_FOUR),
+    ("FIVE", KeyboardKey::KEY_FIVE),
+    ("SIX", KeyboardKey::KEY_SIX),
+    ("SEVEN", KeyboardKey::KEY_SEVEN),
+    ("EIGHT", KeyboardKey::KEY_EIGHT),
+    ("NINE", KeyboardKey::KEY_NINE),
+    ("SEMICOLON", KeyboardKey::KEY_SEMICOLON),
+    ("EQUAL", KeyboardKey::KEY_EQUAL),
+    ("A", KeyboardKey::KEY_A),
+    ("B", KeyboardKey::KEY_B),
+    ("C", KeyboardKey::KEY_C),
+    ("D", KeyboardKey::KEY_D),
+    ("E", KeyboardKey::KEY_E),
+    ("F", KeyboardKey::KEY_F),
+    ("G", KeyboardKey::KEY_G),
+    ("H", KeyboardKey::KEY_H),
+    ("I", KeyboardKey::KEY_I),
+    ("J", KeyboardKey::KEY_J),
+    ("K", KeyboardKey::KEY_K),
+    ("L", KeyboardKey::KEY_L),
+    ("M", KeyboardKey::KEY_M),
+    ("N", KeyboardKey::KEY_N),
+    ("O", KeyboardKey::KEY_O),
+    ("P", KeyboardKey::KEY_P),
+    ("Q", KeyboardKey::KEY_Q),
+    ("R", KeyboardKey::KEY_R),
+    ("S", KeyboardKey::KEY_S),
+    ("T", KeyboardKey::KEY_T),
+    ("U", KeyboardKey::KEY_U),
+    ("V", KeyboardKey::KEY_V),
+    ("W", KeyboardKey::KEY_W),
+    ("X", KeyboardKey::KEY_X),
+    ("Y", KeyboardKey::KEY_Y),
+    ("Z", KeyboardKey::KEY_Z),
+    ("SPACE", KeyboardKey::KEY_SPACE),
+    ("ESCAPE", KeyboardKey::KEY_ESCAPE),
+    ("ENTER", KeyboardKey::KEY_ENTER),
+    ("TAB", KeyboardKey::KEY_TAB),
+    ("BACKSPACE", KeyboardKey::KEY_BACKSPACE),
+    ("INSERT", KeyboardKey::KEY_INSERT),
+    ("DELETE", KeyboardKey::KEY_DELETE),
+    ("RIGHT", KeyboardKey::KEY_RIGHT),
+    ("LEFT", KeyboardKey::KEY_LEFT),
+    ("DOWN", KeyboardKey::KEY_DOWN),
+    ("UP", KeyboardKey::KEY_UP),
+    ("PAGE_UP", KeyboardKey::KEY_PAGE_UP),
+    ("PAGE_DOWN", KeyboardKey::KEY_PAGE_DOWN),
+    ("HOME", KeyboardKey::KEY_HOME),
+    ("END", KeyboardKey::KEY_END),
+    ("CAPS_LOCK", KeyboardKey::KEY_CAPS_LOCK),
+    ("SCROLL_LOCK", KeyboardKey::KEY_SCROLL_LOCK),
+    ("NUM_LOCK", KeyboardKey::KEY_NUM_LOCK),
+    ("PRINT_SCREEN", KeyboardKey::KEY_PRINT_SCREEN),
+    ("PAUSE", KeyboardKey::KEY_PAUSE),
+    ("F1", KeyboardKey::KEY_F1),
+    ("F2", KeyboardKey::KEY_F2),
+    ("F3", KeyboardKey::KEY_F3),
+    ("F4", KeyboardKey::KEY_F4),
+    ("F5", KeyboardKey::KEY_F5),
+    ("F6", KeyboardKey::KEY_F6),
+    ("F7", KeyboardKey::KEY_F7),
+    ("F8", KeyboardKey::KEY_F8),
+    ("F9", KeyboardKey::KEY_F9),
+    ("F10", KeyboardKey::KEY_F10),
+    ("F11", KeyboardKey::KEY_F11),
+    ("F12", KeyboardKey::KEY_F12),
+    ("LEFT_SHIFT", KeyboardKey::KEY_LEFT_SHIFT),
+    ("LEFT_CONTROL", KeyboardKey::KEY_LEFT_CONTROL),
+    ("LEFT_ALT", KeyboardKey::KEY_LEFT_ALT),
+    ("LEFT_SUPER", KeyboardKey::KEY_LEFT_SUPER),
+    ("RIGHT_SHIFT", KeyboardKey::KEY_RIGHT_SHIFT),
+    ("RIGHT_CONTROL", KeyboardKey::KEY_RIGHT_CONTROL),
+    ("RIGHT_ALT", KeyboardKey::KEY_RIGHT_ALT),
+    ("RIGHT_SUPER", KeyboardKey::KEY_RIGHT_SUPER),
+    ("KB_MENU", KeyboardKey::KEY_KB_MENU),
+    ("LEFT_BRACKET", KeyboardKey::KEY_LEFT_BRACKET),
+    ("BACKSLASH", KeyboardKey::KEY_BACKSLASH),
+    ("RIGHT_BRACKET", KeyboardKey::KEY_RIGHT_BRACKET),
+    ("GRAVE", KeyboardKey::KEY_GRAVE),
+    ("KP_0", KeyboardKey::KEY_KP_0),
+    ("KP_1", KeyboardKey::KEY_KP_1),
+    ("KP_2", KeyboardKey::KEY_KP_2),
+    ("KP_3", KeyboardKey::KEY_KP_3),
+    ("KP_4", KeyboardKey::KEY_KP_4),
+    ("KP_5", KeyboardKey::KEY_KP_5),
+    ("KP_6", KeyboardKey::KEY_KP_6),
+    ("KP_7", KeyboardKey::KEY_KP_7),
+    ("KP_8", KeyboardKey::KEY_KP_8),
+    ("KP_9", KeyboardKey::KEY_KP_9),
+    ("KP_DECIMAL", KeyboardKey::KEY_KP_DECIMAL),
+    ("KP_DIVIDE", KeyboardKey::KEY_KP_DIVIDE),
+    ("KP_MULTIPLY", KeyboardKey::KEY_KP_MULTIPLY),
+    ("KP_SUBTRACT", KeyboardKey::KEY_KP_SUBTRACT),
+    ("KP_ADD", KeyboardKey::KEY_KP_ADD),
+    ("KP_ENTER", KeyboardKey::KEY_KP_ENTER),
+    ("KP_EQUAL", KeyboardKey::KEY_KP_EQUAL),
+];
+
+/// Gamepad buttons, named after the Xbox layout
+const BUTTON_NAMES: [(&str, GamepadButton); 17] = [
+    ("PAD_UP", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP),
+    ("PAD_RIGHT", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT),
+    ("PAD_DOWN", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN),
+    ("PAD_LEFT", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT),
+    ("PAD_Y", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP),
+    ("PAD_B", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT),
+    ("PAD_A", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN),
+    ("PAD_X", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT),
+    ("PAD_LB", GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1),
+    ("PAD_LT", GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_2),
+    ("PAD_RB", GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1),
+    ("PAD_RT", GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_2),
+    ("PAD_BACK", GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT),
+    ("PAD_GUIDE", GamepadButton::GAMEPAD_BUTTON_MIDDLE),
+    ("PAD_START", GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT),
+    ("PAD_LS", GamepadButton::GAMEPAD_BUTTON_LEFT_THUMB),
+    ("PAD_RS", GamepadButton::GAMEPAD_BUTTON_RIGHT_THUMB),
+];
+
+/// Directions of the left analog stick
+const STICK_NAMES: [(&str, Stick); 4] = [
+    ("STICK_LEFT", Stick::Left),
+    ("STICK_RIGHT", Stick::Right),
+    ("STICK_UP", Stick::Up),
+    ("STICK_DOWN", Stick::Down),
+];
+
+/// Looks up a key by name, ignoring case
+fn key_from_name(name: &str) -> Option<KeyboardKey> {
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+/// The name that `key` is written down as, if it can be bound
+fn key_name(key: KeyboardKey) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, named)| *named == key)
+        .map(|(name, _)| *name)
+}
+
+/// Looks up a key, gamepad button or stick direction by name, ignoring case
+pub fn input_from_name(name: &str) -> Option<Input> {
+    if let Some(key) = key_from_name(name) {
+        return Some(Input::Key(key));
+    }
+    BUTTON_NAMES
+        .iter()
+        .find(|(button_name, _)| button_name.eq_ignore_ascii_case(name))
+        .map(|(_, button)| Input::Button(*button))
+        .or_else(|| {
+            STICK_NAMES
+                .iter()
+                .find(|(stick_name, _)| stick_name.eq_ignore_ascii_case(name))
+                .map(|(_, stick)| Input::Stick(*stick))
+        })
+}
+
+/// The name that `input` is written down as, if it can be bound
+pub fn input_name(input: Input) -> Option<&'static str> {
+    match input {
+        Input::Key(key) => key_name(key),
+        Input::Button(button) => BUTTON_NAMES
+            .iter()
+            .find(|(_, named)| *named == button)
+            .map(|(name, _)| *name),
+        Input::Stick(stick) => STICK_NAMES
+            .iter()
+            .find(|(_, named)| *named == stick)
+            .map(|(name, _)| *name),
+    }
+}
@@ -1,76 +1,169 @@
+// Things the player can ask of the game
+mod action;
+// Keyboards, gamepads and anything else that inputs can be read from
+pub mod device;
+// Names for keys in the config file
+pub mod keys;
 // Utils for holding a key
 mod utils;
 
+pub use action::{Action, FrameInput};
+use device::{Device, Input, RaylibDevice};
 use utils::*;
 // The framework that keyboard input and keys are built on
 use raylib::prelude::*;
 
+use super::config::{GamepadSettings, Handling, KeySettings};
+use keys::input_from_name;
+
 pub trait InputInterface {
-    fn receive_key(&mut self);
+    fn receive_actions(&mut self);
+}
+
+/// Which keys, buttons and stick directions are bound to each action, any number of them can share an action
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings {
+    bindings: Vec<(Action, Vec<Input>)>,
+}
+
+impl Bindings {
+    /// Looks up each input named in the config, names that aren't inputs are skipped as `Settings::validate` already reports them
+    pub fn from_settings(keys: &KeySettings) -> Self {
+        let bindings = keys
+            .each()
+            .map(|(action, names)| {
+                (
+                    action,
+                    names
+                        .iter()
+                        .filter_map(|name| input_from_name(name))
+                        .collect(),
+                )
+            })
+            .collect();
+        Bindings { bindings }
+    }
+
+    /// Get the inputs bound to `action`.
+    pub fn inputs_for(&self, action: Action) -> &[Input] {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The action that `input` is bound to, if any
+    pub fn action_for(&self, input: Input) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, inputs)| inputs.contains(&input))
+            .map(|(action, _)| *action)
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings::from_settings(&KeySettings::default())
+    }
 }
+
 // Our implementation of tetrominos
+#[derive(Clone)]
 pub struct TetrominoControls {
-    // Not sure if fallrate really fits the agenda here
     controlled_keys: Vec<ControlledKey>,
-    queue: Vec<KeyboardKey>,
+    queue: Vec<Action>,
+    // How far the stick has to be pushed before it counts
+    deadzone: f32,
 }
 
-// This implementation isn't gonna work, if we have for example more functions that we want the keys to do than move the tetromino
 impl TetrominoControls {
-    pub fn clear_queue(&mut self) {
-        self.queue.clear();
+    pub fn get_queue(&self) -> Vec<Action> {
+        self.queue.clone()
     }
 
-    pub fn get_queue(&self) -> Vec<KeyboardKey> {
-        self.queue.clone()
+    /// Actions that act on spawn whose inputs are down, as of the last poll
+    pub fn down(&self) -> Vec<Action> {
+        let mut down: Vec<Action> = Vec::new();
+        for controlled_key in self.controlled_keys.iter() {
+            let action = controlled_key.action;
+            if controlled_key.was_down && action.acts_on_spawn() && !down.contains(&action) {
+                down.push(action);
+            }
+        }
+        down
     }
 
+    /// Replaces the queue with whatever actions fire this frame on the keyboard or gamepad
     pub fn tick(&mut self, rl: &RaylibHandle) {
+        let deadzone = self.deadzone;
+        self.poll(&RaylibDevice::new(rl, deadzone));
+    }
+
+    /// Replaces the queue with whatever actions fire this frame on `device`
+    pub fn poll<D: Device + ?Sized>(&mut self, device: &D) {
+        self.queue.clear();
         for controlled_key in self.controlled_keys.iter_mut() {
-            if controlled_key.tick(rl) {
-                self.queue.push(controlled_key.key)
+            if controlled_key.tick(device) {
+                self.queue.push(controlled_key.action)
             }
         }
     }
 }
 
-impl Default for TetrominoControls {
-    fn default() -> Self {
-        let controlled_keys = vec![
-            ControlledKey {
-                key: KeyboardKey::KEY_LEFT,
-                repeat: Repeat { delay: 8, rate: 4 },
-                ..Default::default()
-            },
-            ControlledKey {
-                key: KeyboardKey::KEY_RIGHT,
-                repeat: Repeat { delay: 8, rate: 4 },
-                ..Default::default()
-            },
-            ControlledKey {
-                key: KeyboardKey::KEY_DOWN,
-                repeat: Repeat { delay: 0, rate: 4 },
-                ..Default::default()
-            },
-            ControlledKey {
-                key: KeyboardKey::KEY_Z,
-                repeat: Repeat { delay: 8, rate: 8 },
-                ..Default::default()
-            },
-            ControlledKey {
-                key: KeyboardKey::KEY_C,
-                repeat: Repeat { delay: 8, rate: 8 },
-                ..Default::default()
-            },
-            ControlledKey {
-                key: KeyboardKey::KEY_SPACE,
-                repeat: Repeat { delay: 8, rate: 8 },
-                ..Default::default()
-            },
-        ];
+impl TetrominoControls {
+    pub fn new(bindings: &Bindings, handling: &Handling, gamepad: &GamepadSettings) -> Self {
+        let repeat = |action: Action| {
+            if !action.repeats() {
+                return None;
+            }
+            Some(match action {
+                Action::MoveLeft | Action::MoveRight => Repeat {
+                    delay: handling.das,
+                    rate: handling.arr,
+                },
+                Action::SoftDrop => Repeat {
+                    delay: 0,
+                    rate: handling.sdf,
+                },
+                Action::HardDrop => Repeat {
+                    delay: handling.hard_drop_delay,
+                    rate: handling.hard_drop_rate,
+                },
+                _ => Repeat {
+                    delay: handling.rotate_delay,
+                    rate: handling.rotate_rate,
+                },
+            })
+        };
+        let controlled_keys = Action::ALL
+            .iter()
+            .flat_map(|action| {
+                bindings
+                    .inputs_for(*action)
+                    .iter()
+                    .map(move |input| ControlledKey {
+                        input: *input,
+                        action: *action,
+                        repeat: repeat(*action),
+                        ..Default::default()
+                    })
+            })
+            .collect();
         TetrominoControls {
             controlled_keys,
             queue: Vec::new(),
+            deadzone: gamepad.deadzone,
         }
     }
 }
+
+impl Default for TetrominoControls {
+    fn default() -> Self {
+        TetrominoControls::new(
+            &Bindings::default(),
+            &Handling::default(),
+            &GamepadSettings::default(),
+        )
+    }
+}
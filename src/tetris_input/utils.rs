@@ -1,34 +1,39 @@
 // Should be some sort of component architecture
 // Wonder if we should make a tetromino util, call that from here
-use raylib::prelude::*;
+use super::device::{Device, Input};
+use super::Action;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub enum KeyboardState {
+    #[default]
     Initiation,
     Held,
 }
 
-impl Default for KeyboardState {
-    fn default() -> Self {
-        Self::Initiation
-    }
-}
-
+#[derive(Clone, Copy)]
 pub enum Buffer {
     Opened(u32),
     Closed,
 }
 
+#[derive(Clone, Copy)]
 pub struct Repeat {
     pub delay: u32,
     pub rate: u32,
 }
 
+#[derive(Clone)]
 pub struct ControlledKey {
-    pub key: raylib::consts::KeyboardKey,
+    // Key, button or stick direction that's polled
+    pub input: Input,
+    // Whether it was down last frame, so that presses can be told apart from holds
+    pub was_down: bool,
+    // Action that's queued when it fires
+    pub action: Action,
     pub state: KeyboardState,
     pub buffer: Buffer,
-    pub repeat: Repeat,
+    // Keys without a repeat only fire once per press
+    pub repeat: Option<Repeat>,
 }
 
 impl ControlledKey {
@@ -56,32 +61,43 @@ impl Default for ControlledKey {
     fn default() -> Self {
         // Defaults are, as per usual, scuffed
         ControlledKey {
-            key: raylib::consts::KeyboardKey::KEY_A,
+            input: Input::Key(raylib::consts::KeyboardKey::KEY_A),
+            was_down: false,
+            action: Action::MoveLeft,
             state: KeyboardState::default(),
             buffer: Buffer::Closed,
-            repeat: Repeat { delay: 8, rate: 4 },
+            repeat: Some(Repeat { delay: 8, rate: 4 }),
         }
     }
 }
 
 impl ControlledKey {
-    pub fn tick(&mut self, rl: &RaylibHandle) -> bool {
-        if rl.is_key_pressed(self.key) {
+    pub fn tick<D: Device + ?Sized>(&mut self, device: &D) -> bool {
+        let down = device.is_down(self.input);
+        let pressed = down && !self.was_down;
+        self.was_down = down;
+
+        if pressed {
             // Reset buffer and move it right
             self.open_buffer();
             return true;
         }
 
+        let repeat = match self.repeat {
+            Some(repeat) => repeat,
+            None => return false,
+        };
+
         // Read our current controlled key buffer
         if let Buffer::Opened(buffer) = self.buffer {
             match self.state {
                 // If we're in the first stage after presseed
                 KeyboardState::Initiation => {
                     // If the key is being held
-                    if rl.is_key_down(self.key) {
+                    if down {
                         // Increment the buffer
                         self.increment_buffer();
-                        if buffer > self.repeat.delay {
+                        if buffer > repeat.delay {
                             self.state = KeyboardState::Held;
                         }
                     } else {
@@ -93,11 +109,11 @@ impl ControlledKey {
                 // If the key has been held for a suffcient amount of time
                 KeyboardState::Held => {
                     // If the key is held
-                    if rl.is_key_down(self.key) {
+                    if down {
                         // Calculate the amount of time that we've held the key
                         self.increment_buffer();
                         // If the # of key presses surpases repeat rate
-                        if buffer > self.repeat.rate {
+                        if buffer > repeat.rate {
                             // Move the tetromino, reset buffer count
                             self.open_buffer();
                             return true;
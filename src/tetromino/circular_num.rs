@@ -1,4 +1,6 @@
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CircularNum {
     rn: u32,
     max: u32,
@@ -7,7 +9,7 @@ mod rotations {
     use super::*;
 
     /// Based on
-    /// ```rust
+    /// ```ignore
     /// fn pos_neg_modulus(&mut self, x: u32, m: u32) -> u32 {
     ///     (x % m + m) % m
     /// }
@@ -18,13 +18,12 @@ impl Tetromino {
     }
 
     fn will_collide(f: &Tetromino, s: &Tetromino, dx: i32, dy: i32) -> bool {
-        let mut coords: HashSet<Coord> = HashSet::new();
-        for f_coord in f.coords.iter() {
-            coords.insert(Coord {
-                x: (f_coord.x as i32 + dx) as u32,
-                y: (f_coord.y as i32 + dy) as u32,
-            });
-        }
+        // A cell that can't be moved that far can't land on anything
+        let coords: HashSet<Coord> = f
+            .coords
+            .iter()
+            .filter_map(|f_coord| f_coord.offset([dx, dy]))
+            .collect();
         for s_coord in s.coords.iter() {
             if coords.contains(s_coord) {
                 return true;
@@ -34,13 +33,10 @@ impl Tetromino {
     }
 
     pub fn within_boundary(&self, dx_dy: [i32; 2], dim: &Dimensions) -> bool {
-        for coord in self.coords.iter() {
-            if !(0..(*dim.w() as i32)).contains(&(coord.x as i32 + dx_dy[0]))
-                || !(0..(dim.h() + 4) as i32).contains(&(coord.y as i32 + dx_dy[1]))
-            {
-                return false;
-            }
-        }
-        true
+        self.coords.iter().all(|coord| {
+            coord
+                .offset(dx_dy)
+                .is_some_and(|moved| dim.contains(&moved))
+        })
     }
 }
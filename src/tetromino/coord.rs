@@ -1,29 +1,35 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use serde::{Deserialize, Serialize};
+
+/// A cell, counted from the bottom left of the board. Signed so that anything moved off the board
+/// is just out of range rather than wrapping around, see `Dimensions::cell` for the checked way back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub struct Coord {
-    pub x: u32,
-    pub y: u32,
+    pub x: i32,
+    pub y: i32,
 }
 
 impl Coord {
-    pub fn new(x: u32, y: u32) -> Self {
+    pub fn new(x: i32, y: i32) -> Self {
         Coord { x, y }
     }
-    pub fn x(&self) -> &u32 {
+    pub fn x(&self) -> &i32 {
         &self.x
     }
-    pub fn mut_x(&mut self) -> &mut u32 {
+    pub fn mut_x(&mut self) -> &mut i32 {
         &mut self.x
     }
-    pub fn y(&self) -> &u32 {
+    pub fn y(&self) -> &i32 {
         &self.y
     }
-    pub fn mut_y(&mut self) -> &mut u32 {
+    pub fn mut_y(&mut self) -> &mut i32 {
         &mut self.y
     }
-}
 
-impl Default for Coord {
-    fn default() -> Self {
-        Coord { x: 0, y: 0 }
+    /// The coord moved by `dx_dy`, or `None` if that's past what a coord can hold
+    pub fn offset(&self, dx_dy: [i32; 2]) -> Option<Coord> {
+        Some(Coord::new(
+            self.x.checked_add(dx_dy[0])?,
+            self.y.checked_add(dx_dy[1])?,
+        ))
     }
-}
\ No newline at end of file
+}
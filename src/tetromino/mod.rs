@@ -2,6 +2,7 @@ pub mod circular_num;
 pub mod collisions;
 pub mod coord;
 pub mod direction;
+pub mod randomizer;
 pub mod tetromino_type;
 
 use circular_num::*;
@@ -13,9 +14,10 @@ use super::universe::{color::ColorPalette, Dimensions};
 use super::Config;
 
 use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// Built off tetromino coords
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Tetromino {
     coords: Vec<Coord>,
     tetromino_type: TetrominoType,
@@ -37,12 +39,9 @@ impl Tetromino {
         let coords = reference_coords
             .iter()
             .map(|coord| {
-                let dx: i32 = coord.x as i32 - reference_coords[0].x as i32;
-                let dy: i32 = coord.y as i32 - reference_coords[0].y as i32;
-                Coord::new(
-                    (spawn_coords.x as i32 + dx) as u32,
-                    (spawn_coords.y as i32 + dy) as u32,
-                )
+                let dx: i32 = coord.x - reference_coords[0].x;
+                let dy: i32 = coord.y - reference_coords[0].y;
+                Coord::new(spawn_coords.x + dx, spawn_coords.y + dy)
             })
             .collect();
 
@@ -53,10 +52,19 @@ impl Tetromino {
         }
     }
 
+    /// A tetromino made up of exactly `coords`, in its spawn orientation
+    pub fn new(coords: Vec<Coord>, tetromino_type: TetrominoType) -> Tetromino {
+        Tetromino {
+            coords,
+            tetromino_type,
+            rotation_state: CircularNum::default(),
+        }
+    }
+
     /// Gives true pixel value,
     /// since graphics use 4th quadrant instead of 1st
-    pub fn reversed_coord_y(canvas_y: u32, coord_y: u32, dy: u32) -> i32 {
-        (canvas_y - (coord_y * dy)) as i32
+    pub fn reversed_coord_y(canvas_y: u32, coord_y: i32, dy: u32) -> i32 {
+        canvas_y as i32 - coord_y * dy as i32
     }
 
     pub fn render(
@@ -66,23 +74,7 @@ impl Tetromino {
         dim: &Dimensions,
         color_palette: &ColorPalette,
     ) {
-        let dy = config.h() / *dim.h();
-        let dx = *config.actual_w() as u32 / *dim.w();
-
-        // For every coord in the tetromino (4 coords in total)
-        for (_idx, coord) in self.coords.iter().enumerate() {
-            if coord.y >= *dim.h() {
-                continue;
-            }
-            // Figure out what this means in terms of real coords
-            d.draw_rectangle(
-                (*config.canvas_l() as u32 + coord.x * dx) as i32,
-                (config.h() - (coord.y + 1) * dy) as i32,
-                dx as i32,
-                dy as i32,
-                color_palette.color_for(self.tetromino_type),
-            )
-        }
+        self.render_cells(d, config, dim, color_palette.color_for(self.tetromino_type));
     }
 
     pub fn render_alpha(
@@ -92,25 +84,69 @@ impl Tetromino {
         dim: &Dimensions,
         color_palette: &ColorPalette,
     ) {
-        let dy = config.h() / *dim.h();
-        let dx = *config.actual_w() as u32 / *dim.w();
+        self.render_cells(
+            d,
+            config,
+            dim,
+            color_palette.color_for(self.tetromino_type).fade(0.4),
+        );
+    }
+
+    /// Draws every cell that's on the visible board or the peeked at rows above it, the latter faded
+    fn render_cells(&self, d: &mut RaylibDrawHandle, config: &Config, dim: &Dimensions, color: Color) {
+        let (dx, dy) = Tetromino::cell_size(config, dim);
+        let rows = (dim.h() + config.settings().board.peek_rows) as i32;
 
         // For every coord in the tetromino (4 coords in total)
-        for (_idx, coord) in self.coords.iter().enumerate() {
-            if coord.y >= *dim.h() {
+        for coord in self.coords.iter() {
+            if coord.y >= rows {
                 continue;
             }
+            let color = if coord.y >= *dim.h() as i32 {
+                color.fade(0.5)
+            } else {
+                color
+            };
             // Figure out what this means in terms of real coords
             d.draw_rectangle(
-                (*config.canvas_l() as u32 + coord.x * dx) as i32,
-                (config.h() - (coord.y + 1) * dy) as i32,
+                *config.canvas_l() as i32 + coord.x * dx as i32,
+                Tetromino::reversed_coord_y(*config.h(), coord.y + 1, dy),
                 dx as i32,
                 dy as i32,
-                color_palette.color_for(self.tetromino_type).fade(0.4),
+                color,
             )
         }
     }
 
+    /// Width and height in pixels of a cell of the board, leaving room above it for the peeked at rows
+    pub fn cell_size(config: &Config, dim: &Dimensions) -> (u32, u32) {
+        let rows = dim.h() + config.settings().board.peek_rows;
+        (*config.actual_w() as u32 / *dim.w(), config.h() / rows)
+    }
+
+    /// Renders the tetromino off the board, with its bottom left corner at `x`, `y` in pixels,
+    /// used for the hold and next queues
+    pub fn render_preview(
+        &self,
+        d: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        size: i32,
+        color_palette: &ColorPalette,
+    ) {
+        let left = self.coords.iter().map(|coord| coord.x).min().unwrap_or(0);
+        let bottom = self.coords.iter().map(|coord| coord.y).min().unwrap_or(0);
+
+        for coord in self.coords.iter() {
+            d.draw_rectangle(
+                x + (coord.x - left) * size,
+                y - (coord.y - bottom + 1) * size,
+                size,
+                size,
+                color_palette.color_for(self.tetromino_type),
+            )
+        }
+    }
 
     pub fn get_dxdy(direction: Direction) -> [i32; 2] {
         match direction {
@@ -121,12 +157,16 @@ impl Tetromino {
         }
     }
 
-    pub fn move_by(&mut self, dx_dy: [i32; 2]) {
-        // Moves all real coords
-        self.coords.iter_mut().for_each(|c| {
-            c.x = (c.x as i32 + dx_dy[0]) as u32;
-            c.y = (c.y as i32 + dx_dy[1]) as u32;
-        });
+    /// Moves all real coords, unless any of them can't be moved that far, returning whether it moved
+    pub fn move_by(&mut self, dx_dy: [i32; 2]) -> bool {
+        let moved: Option<Vec<Coord>> = self.coords.iter().map(|c| c.offset(dx_dy)).collect();
+        match moved {
+            Some(moved) => {
+                self.coords = moved;
+                true
+            }
+            None => false,
+        }
     }
 }
 
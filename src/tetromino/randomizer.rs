@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use super::tetromino_type::TetrominoType;
+
+/// Number of upcoming tetrominos that are shown in the next queue
+pub const PREVIEW_LEN: usize = 5;
+
+/// Seeded source of tetrominos, so that a game can be played back exactly from its seed
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Randomizer {
+    seed: u64,
+    rng: Pcg32,
+    // Tetrominos that have been generated but not handed out yet
+    preview: VecDeque<TetrominoType>,
+    // Piece set that's drawn from, in order
+    #[serde(default = "tetrominos")]
+    pieces: Vec<TetrominoType>,
+}
+
+fn tetrominos() -> Vec<TetrominoType> {
+    TetrominoType::TETROMINOS.to_vec()
+}
+
+impl Randomizer {
+    pub fn new(seed: u64) -> Self {
+        Randomizer::with_pieces(seed, tetrominos())
+    }
+
+    /// Draws from `pieces` rather than the tetrominos, which mustn't be empty
+    pub fn with_pieces(seed: u64, pieces: Vec<TetrominoType>) -> Self {
+        let mut randomizer = Randomizer {
+            seed,
+            rng: Pcg32::seed_from_u64(seed),
+            preview: VecDeque::new(),
+            pieces,
+        };
+        for _ in 0..PREVIEW_LEN {
+            let next = randomizer.draw();
+            randomizer.preview.push_back(next);
+        }
+        randomizer
+    }
+
+    fn draw(&mut self) -> TetrominoType {
+        let idx = self.rng.gen_range(0..self.pieces.len() as u32);
+        self.pieces[idx as usize]
+    }
+
+    /// Hands out the first tetromino in the next queue, and generates another from the seeded stream to replace it
+    pub fn next_type(&mut self) -> TetrominoType {
+        let next = self.draw();
+        self.preview.push_back(next);
+        self.preview.pop_front().unwrap_or(next)
+    }
+
+    /// The next number of the seeded stream, without using it up, which tells apart randomizers that have drawn
+    /// different amounts from the same seed
+    pub fn peek(&self) -> u64 {
+        self.rng.clone().gen()
+    }
+
+    /// Get a reference to the pieces the randomizer draws from.
+    pub fn pieces(&self) -> &Vec<TetrominoType> {
+        &self.pieces
+    }
+
+    /// Get a reference to the randomizer's seed.
+    pub fn seed(&self) -> &u64 {
+        &self.seed
+    }
+
+    /// Get a reference to the tetrominos coming up next, in order.
+    pub fn preview(&self) -> &VecDeque<TetrominoType> {
+        &self.preview
+    }
+}
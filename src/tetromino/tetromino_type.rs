@@ -1,113 +1,79 @@
-#[derive(Clone, Copy)]
-pub enum TetrominoType {
-    I,
-    J,
-    L,
-    O,
-    S,
-    T,
-    Z,
-}
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A kind of piece, by its number in the piece set. The first seven are the tetrominos,
+/// anything after that is only known to the rotation system that describes it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TetrominoType(u8);
+
+impl TetrominoType {
+    pub const I: TetrominoType = TetrominoType(0);
+    pub const J: TetrominoType = TetrominoType(1);
+    pub const L: TetrominoType = TetrominoType(2);
+    pub const O: TetrominoType = TetrominoType(3);
+    pub const S: TetrominoType = TetrominoType(4);
+    pub const T: TetrominoType = TetrominoType(5);
+    pub const Z: TetrominoType = TetrominoType(6);
 
-mod spawn {
-    use super::super::*;
-    use rand::{distributions::Standard, prelude::Distribution, Rng};
+    /// The seven tetrominos, in the order they're numbered
+    pub const TETROMINOS: [TetrominoType; 7] = [
+        TetrominoType::I,
+        TetrominoType::J,
+        TetrominoType::L,
+        TetrominoType::O,
+        TetrominoType::S,
+        TetrominoType::T,
+        TetrominoType::Z,
+    ];
+    const LETTERS: [&'static str; 7] = ["I", "J", "L", "O", "S", "T", "Z"];
 
-    const SPAWN_Y: u32 = 21;
-    const JLSTZ_SPAWN_X: u32 = 4;
-    const IO_SPAWN_X: u32 = 3;
+    pub fn new(index: u8) -> Self {
+        TetrominoType(index)
+    }
+
+    /// Get the piece's number in the piece set.
+    pub fn index(&self) -> u8 {
+        self.0
+    }
 
-    impl Distribution<TetrominoType> for Standard {
-        fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> TetrominoType {
-            match rng.gen_range(0..7) {
-                0 => TetrominoType::I,
-                1 => TetrominoType::J,
-                2 => TetrominoType::L,
-                3 => TetrominoType::O,
-                4 => TetrominoType::S,
-                5 => TetrominoType::T,
-                6 => TetrominoType::Z,
-                _ => {
-                    panic!()
-                }
-            }
+    /// The letter of a tetromino, or the number of any other piece
+    pub fn name(&self) -> String {
+        match TetrominoType::LETTERS.get(self.0 as usize) {
+            Some(letter) => String::from(*letter),
+            None => self.0.to_string(),
         }
     }
+}
+
+// Tetrominos are written as their letter, so that files stay readable, and other pieces as their number
+impl Serialize for TetrominoType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match TetrominoType::LETTERS.get(self.0 as usize) {
+            Some(letter) => serializer.serialize_str(letter),
+            None => serializer.serialize_u8(self.0),
+        }
+    }
+}
 
-    impl TetrominoType {
-        pub fn generate_tetromino_rand() -> Tetromino {
-            TetrominoType::generate_tetromino_from_type(rand::random())
+impl<'de> Deserialize<'de> for TetrominoType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Written {
+            Letter(String),
+            Index(u8),
         }
-        /// Function that takes in a tetromino type and returns a spawned tetromino
-        /// Important to realize that the first index of reference coords are the center of the tetromino
-        /// Doesn't make any sense rn because it's not dependent on board width/height
-        pub fn generate_tetromino_from_type(tetromino_type: TetrominoType) -> Tetromino {
-            let (reference_coords, spawn_coords) = match tetromino_type {
-                TetrominoType::I => (
-                    vec![
-                        Coord::new(1, 0),
-                        Coord::new(0, 0),
-                        Coord::new(2, 0),
-                        Coord::new(3, 0),
-                    ],
-                    Coord::new(IO_SPAWN_X, SPAWN_Y),
-                ),
-                TetrominoType::J => (
-                    vec![
-                        Coord::new(1, 0),
-                        Coord::new(0, 0),
-                        Coord::new(2, 0),
-                        Coord::new(0, 1),
-                    ],
-                    Coord::new(JLSTZ_SPAWN_X, SPAWN_Y),
-                ),
-                TetrominoType::L => (
-                    vec![
-                        Coord::new(1, 0),
-                        Coord::new(0, 0),
-                        Coord::new(2, 0),
-                        Coord::new(2, 1),
-                    ],
-                    Coord::new(JLSTZ_SPAWN_X, SPAWN_Y),
-                ),
-                TetrominoType::O => (
-                    vec![
-                        Coord::new(0, 0),
-                        Coord::new(1, 0),
-                        Coord::new(0, 1),
-                        Coord::new(1, 1),
-                    ],
-                    Coord::new(IO_SPAWN_X, SPAWN_Y),
-                ),
-                TetrominoType::S => (
-                    vec![
-                        Coord::new(1, 0),
-                        Coord::new(0, 0),
-                        Coord::new(1, 1),
-                        Coord::new(2, 1),
-                    ],
-                    Coord::new(JLSTZ_SPAWN_X, SPAWN_Y),
-                ),
-                TetrominoType::T => (
-                    vec![
-                        Coord::new(1, 0),
-                        Coord::new(0, 0),
-                        Coord::new(1, 1),
-                        Coord::new(2, 0),
-                    ],
-                    Coord::new(JLSTZ_SPAWN_X, SPAWN_Y),
-                ),
-                TetrominoType::Z => (
-                    vec![
-                        Coord::new(1, 0),
-                        Coord::new(2, 0),
-                        Coord::new(0, 1),
-                        Coord::new(1, 1),
-                    ],
-                    Coord::new(5, 22),
-                ),
-            };
-            Tetromino::spawn_tetromino(reference_coords, spawn_coords, tetromino_type)
+        match Written::deserialize(deserializer)? {
+            Written::Index(index) => Ok(TetrominoType(index)),
+            Written::Letter(letter) => TetrominoType::LETTERS
+                .iter()
+                .position(|known| known.eq_ignore_ascii_case(&letter))
+                .map(|index| TetrominoType(index as u8))
+                .ok_or_else(|| {
+                    serde::de::Error::custom(format!(
+                        "unknown tetromino \"{}\", pieces other than tetrominos are numbered",
+                        letter
+                    ))
+                }),
         }
     }
 }
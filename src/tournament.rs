@@ -0,0 +1,523 @@
+// Versus matches between bots, round robin or Swiss, with Elo ratings and a replay of every board. Both sides of a
+// game get the same tetrominos and place one each per frame, the first to top out loses and otherwise whoever
+// cleared more lines by the piece limit wins. Every pairing plays the same seeds, each one twice with the sides
+// swapped, so that comparing two versions of a bot isn't down to which tetrominos they happened to get
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BotKind, BotSettings, Config, Settings};
+use crate::replay::{Recorder, ReplayError};
+use crate::sim::{parallel_map, write_csv, CsvRow};
+use crate::source::{bot_from_settings, play_piece, tbp::TbpError, Bot};
+use crate::universe::Universe;
+
+/// Rating that every entrant starts on
+pub const START_ELO: f32 = 1500.0;
+/// Most that a single game moves a rating by
+const K_FACTOR: f32 = 16.0;
+
+/// A bot taking part, under the name it goes by in the results
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entrant {
+    pub name: String,
+    pub bot: BotSettings,
+}
+
+impl Entrant {
+    /// Reads `<name>=<bot>` from the command line, where the bot is one of `heuristic` (with the weights in
+    /// `config`), `heuristic:<config file>` (with the weights in that file), `random` or `tbp:<command> [args]`
+    pub fn parse(arg: &str, config: &Config) -> Result<Self, String> {
+        let (name, spec) = arg
+            .split_once('=')
+            .ok_or_else(|| format!("{} should look like <name>=<bot>", arg))?;
+        if name.is_empty() {
+            return Err(format!("{} needs a name before the =", arg));
+        }
+        let (kind, rest) = spec.split_once(':').unwrap_or((spec, ""));
+        let mut bot = config.settings().bot.clone();
+        match kind {
+            "heuristic" => {
+                bot.kind = BotKind::Heuristic;
+                if !rest.is_empty() {
+                    bot.weights = Config::load(Path::new(rest))
+                        .map_err(|e| format!("couldn't load {}: {}", rest, e))?
+                        .settings()
+                        .bot
+                        .weights
+                        .clone();
+                }
+            }
+            "random" => bot.kind = BotKind::Random,
+            "tbp" => {
+                let mut words = rest.split_whitespace().map(String::from);
+                bot.kind = BotKind::Tbp;
+                bot.command = words.next().unwrap_or_default();
+                bot.args = words.collect();
+            }
+            _ => return Err(format!("there's no {} bot", kind)),
+        }
+        // Same checks as a bot in the config file gets, e.g. tbp bots only playing on 10 wide boards
+        Settings {
+            bot: bot.clone(),
+            ..config.settings().clone()
+        }
+        .validate()
+        .map_err(|e| format!("{}: {}", name, e))?;
+        Ok(Entrant {
+            name: String::from(name),
+            bot,
+        })
+    }
+}
+
+/// How a game went for the left side
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Left,
+    Right,
+    Draw,
+}
+
+impl Outcome {
+    /// Points the left side gets for the game, the right side gets the rest of 1
+    pub fn score(&self) -> f32 {
+        match self {
+            Outcome::Left => 1.0,
+            Outcome::Right => 0.0,
+            Outcome::Draw => 0.5,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Outcome::Left => "left",
+            Outcome::Right => "right",
+            Outcome::Draw => "draw",
+        }
+    }
+}
+
+/// How one side of a game played
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Side {
+    pub name: String,
+    pub lines: u32,
+    pub score: u32,
+    pub pieces: u32,
+    pub topped_out: bool,
+}
+
+/// A single game of a match
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameResult {
+    pub round: u32,
+    pub seed: u64,
+    pub left: Side,
+    pub right: Side,
+    pub outcome: Outcome,
+}
+
+/// Where an entrant stands so far
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub elo: f32,
+    pub played: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    // A point for a win and half of one for a draw, plus whatever byes were worth
+    pub points: f32,
+    pub byes: u32,
+}
+
+impl CsvRow for GameResult {
+    fn header() -> &'static str {
+        "round,seed,left,right,outcome,left_lines,right_lines,left_score,right_score,left_pieces,right_pieces,\
+         left_topped_out,right_topped_out"
+    }
+
+    fn row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.round,
+            self.seed,
+            self.left.name,
+            self.right.name,
+            self.outcome.name(),
+            self.left.lines,
+            self.right.lines,
+            self.left.score,
+            self.right.score,
+            self.left.pieces,
+            self.right.pieces,
+            self.left.topped_out,
+            self.right.topped_out
+        )
+    }
+}
+
+impl CsvRow for Standing {
+    fn header() -> &'static str {
+        "name,elo,played,wins,draws,losses,points,byes"
+    }
+
+    fn row(&self) -> String {
+        format!(
+            "{},{:.0},{},{},{},{},{},{}",
+            self.name,
+            self.elo,
+            self.played,
+            self.wins,
+            self.draws,
+            self.losses,
+            self.points,
+            self.byes
+        )
+    }
+}
+
+/// Chance of a side rated `elo` beating one rated `other`, by the Elo formula
+pub fn expected(elo: f32, other: f32) -> f32 {
+    1.0 / (1.0 + 10_f32.powf((other - elo) / 400.0))
+}
+
+/// Entrants, what they've played so far and how they stand
+pub struct Tournament {
+    // Rules that every game is played by
+    config: Config,
+    entrants: Vec<Entrant>,
+    // Every pairing plays each of these seeds once from either side
+    seeds: Vec<u64>,
+    pieces: u32,
+    // Where replays of each board get written, if anywhere
+    replays: Option<PathBuf>,
+    round: u32,
+    games: Vec<GameResult>,
+    standings: Vec<Standing>,
+    // Pairings that have already played, smaller index first
+    met: HashSet<(usize, usize)>,
+}
+
+impl Tournament {
+    pub fn new(config: &Config, entrants: Vec<Entrant>, seeds: Vec<u64>, pieces: u32) -> Self {
+        let standings = entrants
+            .iter()
+            .map(|entrant| Standing {
+                name: entrant.name.clone(),
+                elo: START_ELO,
+                played: 0,
+                wins: 0,
+                draws: 0,
+                losses: 0,
+                points: 0.0,
+                byes: 0,
+            })
+            .collect();
+        Tournament {
+            config: config.clone(),
+            entrants,
+            seeds,
+            pieces,
+            replays: None,
+            round: 0,
+            games: Vec::new(),
+            standings,
+            met: HashSet::new(),
+        }
+    }
+
+    /// Saves a replay of both boards of every game into `dir`
+    pub fn with_replays(mut self, dir: &Path) -> Self {
+        self.replays = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Plays every entrant against every other one, in a single round
+    pub fn round_robin(&mut self) -> Result<(), TbpError> {
+        let count = self.entrants.len();
+        let pairings: Vec<(usize, usize)> = (0..count)
+            .flat_map(|a| (a + 1..count).map(move |b| (a, b)))
+            .collect();
+        self.play_round(&pairings)
+    }
+
+    /// Plays `rounds` rounds of Swiss, where each round pairs entrants on similar points that haven't met yet
+    pub fn swiss(&mut self, rounds: u32) -> Result<(), TbpError> {
+        for _ in 0..rounds {
+            let (pairings, bye) = self.swiss_pairings();
+            if let Some(bye) = bye {
+                // A bye is worth as much as winning every game of a pairing, and leaves the rating alone
+                let standing = &mut self.standings[bye];
+                standing.byes += 1;
+                standing.points += 2.0 * self.seeds.len() as f32;
+            }
+            self.play_round(&pairings)?;
+        }
+        Ok(())
+    }
+
+    /// Pairs entrants for the next round of Swiss, best first, along with whoever sits it out when there's an odd
+    /// number of them. Pairings that have already played are only repeated when there's nobody else left
+    pub fn swiss_pairings(&self) -> (Vec<(usize, usize)>, Option<usize>) {
+        let mut order: Vec<usize> = (0..self.entrants.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.standings[a], &self.standings[b]);
+            b.points.total_cmp(&a.points).then(b.elo.total_cmp(&a.elo))
+        });
+        // The lowest placed entrant that hasn't had a bye yet gets one
+        let bye = if order.len() % 2 == 1 {
+            let idx = order
+                .iter()
+                .rposition(|&entrant| self.standings[entrant].byes == 0)
+                .unwrap_or(order.len() - 1);
+            Some(order.remove(idx))
+        } else {
+            None
+        };
+
+        let pairings = pair_up(&order, &self.met).unwrap_or_else(|| {
+            // Everyone has met everyone else already, so go down the order as it is
+            order.chunks(2).map(|pair| (pair[0], pair[1])).collect()
+        });
+        (pairings, bye)
+    }
+
+    /// Plays every seed from both sides for each of `pairings`, over every core, then rates the games in order
+    pub fn play_round(&mut self, pairings: &[(usize, usize)]) -> Result<(), TbpError> {
+        self.round += 1;
+        let games: Vec<(usize, usize, u64)> = pairings
+            .iter()
+            .flat_map(|&(a, b)| {
+                self.seeds
+                    .iter()
+                    .flat_map(move |&seed| vec![(a, b, seed), (b, a, seed)])
+            })
+            .collect();
+        let results = parallel_map(&games, |&(left, right, seed)| {
+            self.versus(left, right, seed)
+        })
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+        for (&(left, right, _), result) in games.iter().zip(results) {
+            self.rate(left, right, result.outcome);
+            self.games.push(result);
+        }
+        for &(a, b) in pairings {
+            self.met.insert((a.min(b), a.max(b)));
+        }
+        Ok(())
+    }
+
+    /// Scores a game and moves both ratings by how surprising the outcome was
+    fn rate(&mut self, left: usize, right: usize, outcome: Outcome) {
+        let score = outcome.score();
+        let change =
+            K_FACTOR * (score - expected(self.standings[left].elo, self.standings[right].elo));
+        for (idx, score, change) in [(left, score, change), (right, 1.0 - score, -change)].iter() {
+            let standing = &mut self.standings[*idx];
+            standing.elo += change;
+            standing.played += 1;
+            standing.points += score;
+            match outcome {
+                Outcome::Draw => standing.draws += 1,
+                _ if *score > 0.5 => standing.wins += 1,
+                _ => standing.losses += 1,
+            }
+        }
+    }
+
+    /// Plays `left` against `right` on `seed`, a tetromino each per frame until one tops out or both reach the
+    /// piece limit
+    fn versus(&self, left: usize, right: usize, seed: u64) -> Result<GameResult, TbpError> {
+        let handling = &self.config.settings().handling;
+        let mut boards = Vec::new();
+        for &idx in [left, right].iter() {
+            let entrant = &self.entrants[idx];
+            let bot: Box<dyn Bot> = bot_from_settings(&entrant.bot, seed)?;
+            let universe = Universe::from_config(seed, &self.config);
+            let recorder = Recorder::new(&universe, handling).with_player(&entrant.name);
+            boards.push((bot, universe, recorder));
+        }
+
+        let over = |universe: &Universe| !*universe.game().running();
+        while !boards.iter().any(|(_, universe, _)| over(universe))
+            && boards
+                .iter()
+                .any(|(_, universe, _)| *universe.game().pieces() < self.pieces)
+        {
+            for (bot, universe, recorder) in boards.iter_mut() {
+                if *universe.game().pieces() < self.pieces {
+                    let input = play_piece(universe, bot.as_mut());
+                    recorder.record(&input, universe);
+                }
+            }
+        }
+
+        let sides: Vec<Side> = boards
+            .iter()
+            .zip([left, right].iter())
+            .map(|((_, universe, _), &idx)| Side {
+                name: self.entrants[idx].name.clone(),
+                lines: *universe.game().lines_cleared(),
+                score: *universe.game().score(),
+                pieces: *universe.game().pieces(),
+                topped_out: universe.game().game_over().is_some(),
+            })
+            .collect();
+        let outcome = match (sides[0].topped_out, sides[1].topped_out) {
+            (false, true) => Outcome::Left,
+            (true, false) => Outcome::Right,
+            _ if sides[0].lines > sides[1].lines => Outcome::Left,
+            _ if sides[0].lines < sides[1].lines => Outcome::Right,
+            _ => Outcome::Draw,
+        };
+
+        // A replay that can't be written doesn't take the game down with it, same as in live play
+        if let Some(dir) = &self.replays {
+            for ((_, _, recorder), side) in boards.iter().zip(["left", "right"].iter()) {
+                let path = dir.join(format!(
+                    "r{}-{}-{}-vs-{}-{}.json",
+                    self.round,
+                    seed,
+                    file_name(&sides[0].name),
+                    file_name(&sides[1].name),
+                    side
+                ));
+                if let Err(e) = fs::create_dir_all(dir)
+                    .map_err(ReplayError::from)
+                    .and_then(|_| recorder.replay().save(&path))
+                {
+                    eprintln!("failed to save replay to {}: {}", path.display(), e);
+                }
+            }
+        }
+
+        let mut sides = sides.into_iter();
+        Ok(GameResult {
+            round: self.round,
+            seed,
+            left: sides.next().unwrap(),
+            right: sides.next().unwrap(),
+            outcome,
+        })
+    }
+
+    /// Standings so far, best first
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings = self.standings.clone();
+        standings.sort_by(|a, b| b.points.total_cmp(&a.points).then(b.elo.total_cmp(&a.elo)));
+        standings
+    }
+
+    /// Get a reference to every game played so far.
+    pub fn games(&self) -> &[GameResult] {
+        &self.games
+    }
+
+    /// Get a reference to the tournament's entrants.
+    pub fn entrants(&self) -> &[Entrant] {
+        &self.entrants
+    }
+
+    /// Writes `standings.csv`, `standings.json`, `games.csv` and `games.json` into `dir`, making it if it isn't there
+    pub fn write_results(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let standings = self.standings();
+        write_csv(
+            &mut fs::File::create(dir.join("standings.csv"))?,
+            &standings,
+        )?;
+        write_csv(&mut fs::File::create(dir.join("games.csv"))?, &self.games)?;
+        fs::write(
+            dir.join("standings.json"),
+            serde_json::to_string_pretty(&standings)?,
+        )?;
+        fs::write(
+            dir.join("games.json"),
+            serde_json::to_string_pretty(&self.games)?,
+        )?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Tournament {
+    /// The standings as a table, best first
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let standings = self.standings();
+        let width = standings
+            .iter()
+            .map(|standing| standing.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        writeln!(
+            f,
+            "{:>4}  {:<width$}  {:>5}  {:>6}  {:>4}  {:>4}  {:>4}  {:>6}",
+            "rank",
+            "name",
+            "elo",
+            "played",
+            "won",
+            "drew",
+            "lost",
+            "points",
+            width = width
+        )?;
+        for (rank, standing) in standings.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>4}  {:<width$}  {:>5.0}  {:>6}  {:>4}  {:>4}  {:>4}  {:>6.1}",
+                rank + 1,
+                standing.name,
+                standing.elo,
+                standing.played,
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.points,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Pairs up `order` so that nobody plays someone they've `met`, the first of them against the highest placed
+/// opponent that still lets everyone below be paired. Nothing if there's no way of doing that
+fn pair_up(order: &[usize], met: &HashSet<(usize, usize)>) -> Option<Vec<(usize, usize)>> {
+    let (&first, rest) = match order.split_first() {
+        Some(split) => split,
+        None => return Some(Vec::new()),
+    };
+    rest.iter().enumerate().find_map(|(idx, &other)| {
+        if met.contains(&(first.min(other), first.max(other))) {
+            return None;
+        }
+        let mut left = rest.to_vec();
+        left.remove(idx);
+        let mut pairings = pair_up(&left, met)?;
+        pairings.insert(0, (first, other));
+        Some(pairings)
+    })
+}
+
+/// `name` with anything that doesn't belong in a file name swapped for `_`
+pub fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
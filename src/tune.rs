@@ -0,0 +1,212 @@
+// Tuning the heuristic bot's weights by evolving them over generations. Each generation samples candidates around
+// the current mean, scores every one of them on the same seeds, and moves the mean and spread over to the best of
+// them. This is the cross-entropy method, or CMA-ES that only keeps the diagonal of the covariance
+use std::{fs, io, path::Path};
+
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BotWeights, Config, Settings};
+use crate::sim::{self, parallel_map};
+use crate::source::HeuristicBot;
+use crate::universe::Universe;
+
+/// Smallest spread a weight is sampled with, so that the search never stops moving altogether
+const MIN_SIGMA: f32 = 0.05;
+/// How much of the spread carries over from one generation to the next, the rest comes from the elite
+const SIGMA_MEMORY: f32 = 0.3;
+
+/// A set of weights, and how well the bot played with them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub weights: BotWeights,
+    pub fitness: f32,
+}
+
+/// The games that every candidate is scored on
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Trial {
+    pub seeds: Vec<u64>,
+    // Tetrominos that each game is stopped after
+    pub pieces: u32,
+    // Rules the games are played by, whose bot weights are where the search starts from
+    pub settings: Settings,
+}
+
+/// Where the search has got to, which is everything a checkpoint needs to carry on from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Tuner {
+    // Kept with the search, so that carrying on from a checkpoint scores candidates on the same games
+    trial: Trial,
+    generation: u32,
+    // Centre of the next generation
+    mean: BotWeights,
+    // Spread of each weight around the mean, in the order of `BotWeights::each`
+    sigma: Vec<f32>,
+    // Candidates sampled each generation, and how many of the best of them the next generation is built from
+    population: usize,
+    elite: usize,
+    // Every candidate of the last generation, best first
+    last: Vec<Candidate>,
+    // Best candidate of any generation so far
+    best: Option<Candidate>,
+    rng: Pcg32,
+}
+
+impl Tuner {
+    /// Starts searching around the weights of `trial`'s settings, with every weight spread by `sigma`
+    pub fn new(trial: Trial, sigma: f32, population: usize, elite: usize, seed: u64) -> Self {
+        let population = population.max(2);
+        let start = trial.settings.bot.weights.clone();
+        Tuner {
+            trial,
+            generation: 0,
+            sigma: vec![sigma.max(MIN_SIGMA); start.each().len()],
+            mean: start,
+            population,
+            elite: elite.clamp(1, population),
+            last: Vec::new(),
+            best: None,
+            rng: Pcg32::seed_from_u64(seed),
+        }
+    }
+
+    /// Reads a checkpoint written by `save`, checking that its rules can still be played by, rotation system file
+    /// included, and that the search can carry on from it
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let tuner: Tuner = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        tuner
+            .trial
+            .settings
+            .validate()
+            .map_err(|e| invalid(e.to_string()))?;
+        if tuner.population < 2 || !(1..=tuner.population).contains(&tuner.elite) {
+            return Err(invalid(format!(
+                "an elite of {} can't be picked from a population of {}",
+                tuner.elite, tuner.population
+            )));
+        }
+        if tuner.sigma.len() != tuner.mean.each().len() {
+            return Err(invalid(String::from(
+                "there should be a spread for every weight",
+            )));
+        }
+        Ok(tuner)
+    }
+
+    /// Writes a checkpoint to `path`, all at once so that stopping halfway through never leaves half of one
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let partial = path.with_extension("partial");
+        fs::write(&partial, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&partial, path)
+    }
+
+    /// A normally distributed number, by the Box-Muller transform
+    fn gaussian(&mut self) -> f32 {
+        // Never exactly 0, which has no logarithm
+        let u1 = 1.0 - self.rng.gen::<f32>();
+        let u2 = self.rng.gen::<f32>();
+        (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+    }
+
+    /// Samples a generation, scores each candidate with `fitness` over every core, and moves towards the best of
+    /// them. Returns the best candidate of the generation
+    pub fn step(&mut self, fitness: impl Fn(&BotWeights) -> f32 + Sync) -> &Candidate {
+        let samples: Vec<BotWeights> = (0..self.population)
+            .map(|_| {
+                let mut weights = self.mean.clone();
+                for ((_, weight), sigma) in weights.each_mut().iter_mut().zip(self.sigma.clone()) {
+                    **weight += sigma * self.gaussian();
+                }
+                weights
+            })
+            .collect();
+        let mut candidates: Vec<Candidate> = samples
+            .iter()
+            .zip(parallel_map(&samples, &fitness))
+            .map(|(weights, fitness)| Candidate {
+                weights: weights.clone(),
+                // A candidate that can't be scored is as bad as it gets
+                fitness: if fitness.is_nan() {
+                    f32::NEG_INFINITY
+                } else {
+                    fitness
+                },
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+
+        let elite = &candidates[..self.elite];
+        let count = elite.len() as f32;
+        for (idx, (_, mean)) in self.mean.each_mut().iter_mut().enumerate() {
+            let values = || {
+                elite
+                    .iter()
+                    .map(|candidate| candidate.weights.each()[idx].1)
+            };
+            // Spread is measured from where the generation was sampled around, so it stays wide while the elite
+            // keeps heading the same way and only narrows once it's gathered around the mean
+            let spread =
+                (values().map(|value| (value - **mean).powi(2)).sum::<f32>() / count).sqrt();
+            **mean = values().sum::<f32>() / count;
+            self.sigma[idx] =
+                (SIGMA_MEMORY * self.sigma[idx] + (1.0 - SIGMA_MEMORY) * spread).max(MIN_SIGMA);
+        }
+
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|best| candidates[0].fitness > best.fitness)
+        {
+            self.best = Some(candidates[0].clone());
+        }
+        self.last = candidates;
+        self.generation += 1;
+        &self.last[0]
+    }
+
+    /// Get a reference to the games the tuner scores candidates on.
+    pub fn trial(&self) -> &Trial {
+        &self.trial
+    }
+
+    /// Get the number of generations run so far.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Get a reference to the tuner's mean.
+    pub fn mean(&self) -> &BotWeights {
+        &self.mean
+    }
+
+    /// Get a reference to the candidates of the last generation, best first.
+    pub fn last(&self) -> &[Candidate] {
+        &self.last
+    }
+
+    /// Get a reference to the best candidate so far.
+    pub fn best(&self) -> Option<&Candidate> {
+        self.best.as_ref()
+    }
+}
+
+/// Mean lines cleared by the heuristic bot with `weights`, over a game on each of `seeds` by the rules of
+/// `config`, each stopped after `pieces` tetrominos. Topping out early clears fewer lines, so survival is built in
+pub fn lines_fitness(config: &Config, weights: &BotWeights, seeds: &[u64], pieces: u32) -> f32 {
+    let lines: u32 = seeds
+        .iter()
+        .map(|seed| {
+            let mut universe = Universe::from_config(*seed, config);
+            sim::play(
+                &mut universe,
+                &mut HeuristicBot::new(weights.clone()),
+                pieces,
+            )
+            .lines
+        })
+        .sum();
+    lines as f32 / seeds.len().max(1) as f32
+}
@@ -1,7 +1,11 @@
+use std::collections::HashMap;
+
 use raylib::color::Color;
 
+use crate::config::{parse_color, ColorSettings};
 use crate::tetromino::tetromino_type::TetrominoType;
 
+#[derive(Clone)]
 pub struct ColorPalette {
     line: Color,
     grid: Color,
@@ -12,19 +16,22 @@ pub struct ColorPalette {
     z: Color,
     i: Color,
     o: Color,
+    // Colours that the piece set gives its pieces, over the ones above
+    pieces: HashMap<TetrominoType, Color>,
 }
 
 impl ColorPalette {
     pub fn color_for(&self, tetrimino_type: TetrominoType) -> Color {
-        match tetrimino_type {
-            TetrominoType::I => self.i,
-            TetrominoType::J => self.j,
-            TetrominoType::L => self.l,
-            TetrominoType::O => self.o,
-            TetrominoType::S => self.s,
-            TetrominoType::T => self.t,
-            TetrominoType::Z => self.z,
+        if let Some(color) = self.pieces.get(&tetrimino_type) {
+            return *color;
         }
+        // Pieces without a colour of their own take turns with the tetrominos' colours
+        [self.i, self.j, self.l, self.o, self.s, self.t, self.z][tetrimino_type.index() as usize % 7]
+    }
+
+    /// Set the colours that the piece set gives its pieces.
+    pub fn set_piece_colors(&mut self, pieces: HashMap<TetrominoType, Color>) {
+        self.pieces = pieces;
     }
 
     /// Get a reference to the color palette's line color.
@@ -36,6 +43,45 @@ impl ColorPalette {
     pub fn grid(&self) -> &Color {
         &self.grid
     }
+
+    /// The same palette with every tetromino colour faded to `alpha`
+    pub fn faded(&self, alpha: f32) -> Self {
+        ColorPalette {
+            j: self.j.fade(alpha),
+            l: self.l.fade(alpha),
+            s: self.s.fade(alpha),
+            t: self.t.fade(alpha),
+            z: self.z.fade(alpha),
+            i: self.i.fade(alpha),
+            o: self.o.fade(alpha),
+            pieces: self
+                .pieces
+                .iter()
+                .map(|(piece, color)| (*piece, color.fade(alpha)))
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl ColorPalette {
+    /// Builds a palette from the config, keeping the default for any colour that doesn't parse
+    pub fn from_settings(colors: &ColorSettings) -> Self {
+        let default = ColorPalette::default();
+        let color = |hex: &String, fallback: Color| parse_color(hex).unwrap_or(fallback);
+        ColorPalette {
+            grid: color(&colors.grid, default.grid),
+            line: color(&colors.line, default.line),
+            j: color(&colors.j, default.j),
+            l: color(&colors.l, default.l),
+            s: color(&colors.s, default.s),
+            t: color(&colors.t, default.t),
+            z: color(&colors.z, default.z),
+            i: color(&colors.i, default.i),
+            o: color(&colors.o, default.o),
+            pieces: HashMap::new(),
+        }
+    }
 }
 
 impl Default for ColorPalette {
@@ -50,6 +96,7 @@ impl Default for ColorPalette {
             z: Color::from_hex("4C6085").unwrap(),
             i: Color::from_hex("34344A").unwrap(),
             o: Color::from_hex("D4BEBE").unwrap(),
+            pieces: HashMap::new(),
         }
     }
 }
@@ -1,4 +1,7 @@
-// Single, double, triple, tetris, based off of gameboy
+use serde::{Deserialize, Serialize};
+
+// Single, double, triple, tetris, based off of gameboy. Pieces larger than a tetromino can clear more lines at once,
+// which score the same as a tetris
 const SCORE: [u32; 4] = [40, 100, 300, 1200];
 // Speeds for levels 3-20, based off of gameboy
 const FRAMES_PER_FALL: [u32; 21] = [
@@ -7,17 +10,54 @@ const FRAMES_PER_FALL: [u32; 21] = [
 const LVL_CAP: u32 = 20;
 const LINES_PER_LEVEL: u32 = 10;
 
+/// How a game was lost
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameOverReason {
+    // A new tetromino spawned overlapping the stack
+    BlockOut,
+    // A tetromino locked entirely above the visible board
+    LockOut,
+    // A tetromino locked partly above the visible board, when that's turned on
+    PartialLockOut,
+}
+
+impl GameOverReason {
+    /// What to call the reason on the game over screen
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameOverReason::BlockOut => "block out",
+            GameOverReason::LockOut => "lock out",
+            GameOverReason::PartialLockOut => "partial lock out",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Game {
     // Internal game tick
     ticks: u32,
     // Game running
     running: bool,
+    // Paused by the player, the game doesn't advance until it's unpaused
+    paused: bool,
     // Score
     lines_cleared: u32,
     // level
     level: u32,
     // score
     score: u32,
+    // Number of tetrominos locked into the board
+    pieces: u32,
+    // Why the game ended, once it has
+    #[serde(default)]
+    game_over: Option<GameOverReason>,
+    // Tetrominos in a row that have cleared lines, 0 when the last one didn't
+    #[serde(default)]
+    combo: u32,
+    // Whether the last clear was a tetris, so that another one would be back to back
+    #[serde(default)]
+    back_to_back: bool,
 }
 impl Game {
     /// Get a reference to the game's running.
@@ -25,6 +65,11 @@ impl Game {
         &self.running
     }
 
+    /// Get a reference to the game's paused.
+    pub fn paused(&self) -> &bool {
+        &self.paused
+    }
+
     /// Get a reference to the universe's ticks.
     pub fn ticks(&self) -> &u32 {
         &self.ticks
@@ -44,20 +89,65 @@ impl Game {
     pub fn score(&self) -> &u32 {
         &self.score
     }
+
+    /// Get a reference to the game's number of pieces placed.
+    pub fn pieces(&self) -> &u32 {
+        &self.pieces
+    }
+
+    /// Get a reference to the game's lines cleared.
+    pub fn lines_cleared(&self) -> &u32 {
+        &self.lines_cleared
+    }
+
+    /// Get a reference to the reason the game ended, if it has.
+    pub fn game_over(&self) -> &Option<GameOverReason> {
+        &self.game_over
+    }
+
+    /// Get a reference to the game's combo.
+    pub fn combo(&self) -> &u32 {
+        &self.combo
+    }
+
+    /// Get a reference to whether the game is back to back.
+    pub fn back_to_back(&self) -> &bool {
+        &self.back_to_back
+    }
 }
 
 impl Game {
-    /// Sets running state to false
-    pub fn pause(&mut self) {
+    /// Sets running state to false, ending the game for `reason`
+    pub fn end(&mut self, reason: GameOverReason) {
         self.running = false;
+        self.game_over = Some(reason);
     }
     /// Sets running state to true
     pub fn resume(&mut self) {
         self.running = true;
+        self.game_over = None;
     }
 
-    /// Updates score, # of lines cleared, and level
+    /// Pauses the game if it's going, and unpauses it if it's paused
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Counts a tetromino that just locked into the board
+    pub fn place_piece(&mut self) {
+        self.pieces += 1;
+    }
+
+    /// Updates the combo, back to back, score, # of lines cleared, and level after a tetromino locks
     pub fn update(&mut self, lines_cleared: u32) {
+        if lines_cleared == 0 {
+            self.combo = 0;
+            return;
+        }
+        self.combo += 1;
+        // Anything short of a tetris breaks the back to back, and not clearing at all leaves it be
+        self.back_to_back = lines_cleared >= 4;
+
         // First update score
         self.update_score(lines_cleared);
         // Next update lines cleared
@@ -69,7 +159,7 @@ impl Game {
     /// Should never get called with 0 probably
     fn update_score(&mut self, lines_cleared: u32) {
         // Update score depending on lines cleared
-        self.score += SCORE[(lines_cleared - 1) as usize];
+        self.score += SCORE[(lines_cleared.min(SCORE.len() as u32) - 1) as usize];
     }
 
     /// Changes level based on self. num of lines cleared
@@ -103,7 +193,8 @@ impl Game {
 
     /// Determines whether to fall tetrimino
     pub fn should_fall(&mut self) -> bool {
-        self.ticks % FRAMES_PER_FALL[self.level as usize] == 0
+        self.ticks
+            .is_multiple_of(FRAMES_PER_FALL[self.level as usize])
     }
 }
 
@@ -112,9 +203,14 @@ impl Default for Game {
         Game {
             ticks: 0,
             running: true,
+            paused: false,
             lines_cleared: 0,
             level: 0,
             score: 0,
+            pieces: 0,
+            game_over: None,
+            combo: 0,
+            back_to_back: false,
         }
     }
 }
@@ -1,52 +1,42 @@
 use super::rotations::rotation_direction::RotationDirection;
 use super::{direction::*, Tetromino};
-use super::{InputInterface, Universe};
-
-use raylib::prelude::*;
+use super::{Action, InputInterface, Universe};
 
 impl InputInterface for Universe {
-    fn receive_key(&mut self) {
-        for key in self.tetromino_controls.get_queue() {
-            match key {
-                KeyboardKey::KEY_LEFT => {
-                    let dxdy = Tetromino::get_dxdy(Direction::Left);
-                    if self.focused_tetromino.within_boundary(dxdy, &self.dim)
-                        && !Tetromino::will_collide_all(
-                            &self.focused_tetromino,
-                            &self.stagnant_tetrominos,
-                            dxdy,
-                        )
-                    {
-                        self.focused_tetromino.move_by(dxdy)
-                    }
+    fn receive_actions(&mut self) {
+        let pieces = *self.game.pieces();
+        for action in std::mem::take(&mut self.queue) {
+            // Whatever's left was aimed at a tetromino that has since locked, or the game is over, so it's dropped
+            // rather than landing on the next tetromino. Holding doesn't lock, so a plan can hold and carry on
+            if !self.game.running() || *self.game.pieces() != pieces {
+                break;
+            }
+            match action {
+                Action::MoveLeft => {
+                    self.try_move(Tetromino::get_dxdy(Direction::Left));
                 }
-                KeyboardKey::KEY_RIGHT => {
-                    let dxdy = Tetromino::get_dxdy(Direction::Right);
-                    if self.focused_tetromino.within_boundary(dxdy, &self.dim)
-                        && !Tetromino::will_collide_all(
-                            &self.focused_tetromino,
-                            &self.stagnant_tetrominos,
-                            dxdy,
-                        )
-                    {
-                        self.focused_tetromino.move_by(dxdy)
-                    }
+                Action::MoveRight => {
+                    self.try_move(Tetromino::get_dxdy(Direction::Right));
                 }
-                KeyboardKey::KEY_DOWN => {
+                Action::SoftDrop => {
                     self.fall_focused();
                     self.game.fast_move_down_score()
                 }
-                KeyboardKey::KEY_Z => self.rotate_focused(RotationDirection::CounterClockwise),
-                KeyboardKey::KEY_C => self.rotate_focused(RotationDirection::Clockwise),
-                KeyboardKey::KEY_SPACE => {
-                    let lines = self.focused_tetromino.coords()[0].y - self.ghost.coords()[0].y;
-                    self.focused_tetromino = self.ghost.clone();
+                Action::RotateCcw => self.rotate_focused(RotationDirection::CounterClockwise),
+                Action::RotateCw => self.rotate_focused(RotationDirection::Clockwise),
+                Action::Rotate180 => self.rotate_focused(RotationDirection::Half),
+                Action::HardDrop => {
+                    // The ghost is only brought up to date once a frame, before any actions
+                    self.full_fall_focused();
+                    let lines = (self.focused.origin[1] - self.ghost.origin[1]) as u32;
+                    self.focused = self.ghost;
                     self.fall_focused();
                     self.game.hard_move_down_score(lines);
                 }
-                _ => {}
+                Action::Hold => self.hold(),
+                // Pausing is handled before anything else in `update`, and restarting is up to whoever runs the universe
+                Action::Pause | Action::Restart => {}
             }
         }
-        self.tetromino_controls.clear_queue();
     }
 }
@@ -1,44 +1,108 @@
 pub mod color;
-mod game;
+pub mod game;
 mod input;
-mod rotations;
+pub mod moves;
+pub mod rotations;
+
+pub use rotations::system::RotationSystem;
 
 use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
 
+use rotations::placement::Placement;
 use rotations::rotation_direction::RotationDirection;
+use tetromino::coord::Coord;
+use tetromino::randomizer::Randomizer;
 use tetromino::tetromino_type::TetrominoType;
 
+use crate::config::Kicks180;
 use color::ColorPalette;
-use game::Game;
+use game::{Game, GameOverReason};
 
 use super::*;
 use direction::*;
 
 const INITIAL_WIDTH: u32 = 10;
 const INITIAL_HEIGHT: u32 = 20;
-
+// Hidden rows above the visible ones, as in the guideline
+pub const DEFAULT_BUFFER: u32 = 20;
+/// Columns a board can have. Tetrominos spawn centred on it, which has to at least fit an I
+pub const WIDTHS: RangeInclusive<u32> = 4..=40;
+/// Visible rows a board can have
+pub const HEIGHTS: RangeInclusive<u32> = 10..=60;
+/// Hidden rows a board can have. Tetrominos spawn two rows into them, and need room to rotate there
+pub const BUFFERS: RangeInclusive<u32> = 4..=40;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Universe {
     // Board
     dim: Dimensions,
     // Player controlled tetrimino
-    focused_tetromino: Tetromino,
-    ghost: Tetromino,
+    focused: Placement,
+    // Where the focused tetromino would land if it were hard dropped
+    ghost: Placement,
     // Tetriminos on board
     stagnant_tetrominos: Vec<Tetromino>,
-    // Controls for tetrimino
-    tetromino_controls: TetrominoControls,
+    // Actions waiting for the next update, from whichever input source drives the universe
+    #[serde(skip)]
+    queue: Vec<Action>,
+    // Actions held down this frame, for IRS and IHS
+    #[serde(skip)]
+    down: Vec<Action>,
     // Static color palette for game
+    #[serde(skip)]
     color_palette: ColorPalette,
     // Game mechanics
     game: Game,
+    // Seeded source of new tetrominos
+    randomizer: Randomizer,
+    // Tetromino put aside with hold, if any
+    held: Option<TetrominoType>,
+    // Hold can only be used once per tetromino
+    can_hold: bool,
+    // Kicks tried for 180° rotations, fixed for the whole game
+    #[serde(default)]
+    kicks_180: Kicks180,
+    // How each tetromino spawns and rotates
+    #[serde(default)]
+    rotation_system: RotationSystem,
+    // Initial rotation system, rotating tetrominos that spawn with a rotation held down
+    #[serde(default)]
+    irs: bool,
+    // Initial hold system, holding tetrominos that spawn with hold held down
+    #[serde(default)]
+    ihs: bool,
+    // Whether locking any part of a tetromino above the visible board loses the game, not just all of it
+    #[serde(default)]
+    partial_lock_out: bool,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Dimensions {
     w: u32,
+    // Visible rows
     h: u32,
+    // Hidden rows above the visible ones, where tetrominos spawn
+    #[serde(default = "default_buffer")]
+    buffer: u32,
+}
+
+fn default_buffer() -> u32 {
+    DEFAULT_BUFFER
 }
 
 impl Dimensions {
+    pub fn new(w: u32, h: u32) -> Self {
+        Dimensions::with_buffer(w, h, DEFAULT_BUFFER)
+    }
+
+    pub fn with_buffer(w: u32, h: u32, buffer: u32) -> Self {
+        Dimensions { w, h, buffer }
+    }
+
     /// Get a reference to the dimensions's w.
     pub fn w(&self) -> &u32 {
         &self.w
@@ -48,6 +112,64 @@ impl Dimensions {
     pub fn h(&self) -> &u32 {
         &self.h
     }
+
+    /// Get a reference to the dimensions's buffer.
+    pub fn buffer(&self) -> &u32 {
+        &self.buffer
+    }
+
+    /// Checks that the board is a size the game can be played on
+    pub fn validate(&self) -> Result<(), String> {
+        let sizes = [
+            ("width", self.w, WIDTHS),
+            ("height", self.h, HEIGHTS),
+            ("buffer", self.buffer, BUFFERS),
+        ];
+        for (name, size, range) in sizes.iter() {
+            if !range.contains(size) {
+                return Err(format!(
+                    "board {} must be between {} and {}, got {}",
+                    name,
+                    range.start(),
+                    range.end(),
+                    size
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rows of the whole board, visible and hidden
+    pub fn total_h(&self) -> u32 {
+        self.h + self.buffer
+    }
+
+    /// Row that tetrominos spawn on, the second one of the buffer
+    pub fn spawn_row(&self) -> u32 {
+        self.h + 1
+    }
+
+    /// Column and row of `coord` on the board, or `None` if it's off the board
+    pub fn cell(&self, coord: &Coord) -> Option<(u32, u32)> {
+        let x = u32::try_from(coord.x).ok()?;
+        let y = u32::try_from(coord.y).ok()?;
+        if x < self.w && y < self.total_h() {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    /// Whether `coord` is on the board, hidden rows included
+    pub fn contains(&self, coord: &Coord) -> bool {
+        self.cell(coord).is_some()
+    }
+}
+
+impl Default for Dimensions {
+    fn default() -> Self {
+        Dimensions::new(INITIAL_WIDTH, INITIAL_HEIGHT)
+    }
 }
 
 impl Universe {
@@ -55,103 +177,264 @@ impl Universe {
         dims: Dimensions,
         focused_tetromino: Tetromino,
         stagnant_tetrominos: Vec<Tetromino>,
-        tetromino_controls: TetrominoControls,
         color_palette: ColorPalette,
         game: Game,
-        ghost: Tetromino,
+        randomizer: Randomizer,
     ) -> Self {
+        let rotation_system = RotationSystem::default();
+        let focused = rotation_system.placement_of(&focused_tetromino);
         Universe {
             dim: dims,
-            focused_tetromino,
+            focused,
+            ghost: focused,
             stagnant_tetrominos,
-            tetromino_controls,
+            queue: Vec::new(),
+            down: Vec::new(),
             color_palette,
             game,
-            ghost,
+            randomizer,
+            held: None,
+            can_hold: true,
+            kicks_180: Kicks180::default(),
+            irs: false,
+            ihs: false,
+            partial_lock_out: false,
+            rotation_system,
+        }
+    }
+
+    /// Creates a fresh universe whose tetrominos are generated from `seed`
+    pub fn from_seed(seed: u64) -> Self {
+        let mut randomizer = Randomizer::new(seed);
+        let dim = Dimensions::default();
+        Universe::new(
+            dim.clone(),
+            RotationSystem::default().spawn(randomizer.next_type(), &dim),
+            vec![],
+            ColorPalette::default(),
+            Game::default(),
+            randomizer,
+        )
+    }
+
+    /// Creates a fresh universe with the board size and colours from the config
+    pub fn from_config(seed: u64, config: &Config) -> Self {
+        let settings = config.settings();
+        let mut randomizer = Randomizer::new(seed);
+        let dim = Dimensions::with_buffer(
+            settings.board.width,
+            settings.board.height,
+            settings.board.buffer,
+        );
+        let mut universe = Universe::new(
+            dim.clone(),
+            RotationSystem::default().spawn(randomizer.next_type(), &dim),
+            vec![],
+            ColorPalette::from_settings(&settings.colors),
+            Game::default(),
+            randomizer,
+        );
+        universe.set_kicks_180(settings.rotation.kicks_180);
+        universe.set_rotation_system(config.rotation_system().clone());
+        universe.set_irs(settings.game.irs);
+        universe.set_ihs(settings.game.ihs);
+        universe.set_partial_lock_out(settings.game.partial_lock_out);
+        universe
+    }
+
+    /// Swaps in the colours from the config, for universes that were loaded rather than created
+    pub fn configure(&mut self, config: &Config) {
+        self.color_palette = ColorPalette::from_settings(&config.settings().colors);
+        self.color_palette
+            .set_piece_colors(self.rotation_system.colors());
+    }
+
+    /// Takes on the game state of `state`, while keeping our own colours
+    pub fn restore(&mut self, state: &Universe) {
+        let mut color_palette = self.color_palette.clone();
+        *self = state.clone();
+        self.queue.clear();
+        self.down.clear();
+        color_palette.set_piece_colors(self.rotation_system.colors());
+        self.color_palette = color_palette;
+    }
+
+    /// Whether `cells` are all on the board and clear of the stack
+    fn cells_fit(&self, cells: &[Coord]) -> bool {
+        cells.iter().all(|coord| self.dim.contains(coord))
+            && !self
+                .stagnant_tetrominos
+                .iter()
+                .any(|tetromino| tetromino.coords().iter().any(|coord| cells.contains(coord)))
+    }
+
+    /// Whether `placement` is on the board and clear of the stack
+    fn fits(&self, placement: &Placement) -> bool {
+        self.cells_fit(&self.rotation_system.cells(placement))
+    }
+
+    /// Moves the focused tetromino by `dxdy` if it fits there, returning whether it did
+    fn try_move(&mut self, dxdy: [i32; 2]) -> bool {
+        let moved = self.focused.moved(dxdy);
+        if self.fits(&moved) {
+            self.focused = moved;
         }
+        self.focused == moved
     }
 
     fn fall_focused(&mut self) {
         // Code that determines moving the pieces down
-        let within_boundary = self
-            .focused_tetromino
-            .within_boundary(Tetromino::get_dxdy(Direction::Down), &self.dim);
-        let mut collision = false;
-
-        if within_boundary {
-            collision = Tetromino::will_collide_all(
-                &self.focused_tetromino,
-                &self.stagnant_tetrominos,
-                Tetromino::get_dxdy(Direction::Down),
-            );
+        if !self.try_move(Tetromino::get_dxdy(Direction::Down)) {
+            self.lock_focused();
         }
+    }
 
-        if !collision && within_boundary {
-            self.focused_tetromino
-                .move_by(Tetromino::get_dxdy(Direction::Down));
-        } else {
-            // Solidify the old current
-            self.stagnant_tetrominos
-                .push(self.focused_tetromino.clone());
-            // Generate a new current
-            self.focused_tetromino = TetrominoType::generate_tetromino_rand();
-
-            // If it generates into a piece, game ova
-            if Tetromino::will_collide_all(
-                &self.focused_tetromino,
-                &self.stagnant_tetrominos,
-                [0, 0],
-            ) {
-                // Game over
-                self.game.pause();
-            }
+    /// Locks the focused tetromino into the stack, clearing lines and spawning the next one unless that loses the game
+    fn lock_focused(&mut self) {
+        let h = self.dim.h as i32;
+        let focused = self.focused_tetromino();
+        let locked_out = focused.coords().iter().all(|coord| coord.y >= h);
+
+        // Solidify the old current
+        self.stagnant_tetrominos.push(focused);
+        self.game.place_piece();
+        self.can_hold = true;
+
+        if locked_out {
+            self.game.end(GameOverReason::LockOut);
+            return;
+        }
+
+        self.clear_lines();
+
+        // Checked after clearing, since clearing lines below may bring the tetromino back down into view
+        if self.partial_lock_out
+            && self
+                .stagnant_tetrominos
+                .iter()
+                .any(|tetromino| tetromino.coords().iter().any(|coord| coord.y >= h))
+        {
+            self.game.end(GameOverReason::PartialLockOut);
+            return;
         }
+
+        // Generate a new current
+        let next = self.randomizer.next_type();
+        self.spawn(self.rotation_system.spawn_placement(next, &self.dim));
     }
 
-    /// Implmentation of hard drop preview
-    pub fn full_fall_focused(&mut self) {
-        self.ghost = self.focused_tetromino.clone();
-        loop {
-            // Code that determines moving the pieces down
-            let within_boundary = self
-                .ghost
-                .within_boundary(Tetromino::get_dxdy(Direction::Down), &self.dim);
-
-            let mut collision = false;
-
-            if within_boundary {
-                collision = Tetromino::will_collide_all(
-                    &self.ghost,
-                    &self.stagnant_tetrominos,
-                    Tetromino::get_dxdy(Direction::Down),
-                );
-            }
+    /// Makes `placement` the focused tetromino, ending the game if it spawns into the stack.
+    /// With IHS or IRS on, whatever's held down is applied first, so it can save a spawn that would otherwise top out
+    fn spawn(&mut self, placement: Placement) {
+        self.focused = placement;
 
-            if !collision && within_boundary {
-                self.ghost
-                    .move_by(Tetromino::get_dxdy(Direction::Down));
+        // Holding spawns the next tetromino in turn, which takes care of the rest
+        if self.ihs && self.can_hold && self.down.contains(&Action::Hold) {
+            self.hold();
+            return;
+        }
+        if self.irs {
+            let direction = if self.down.contains(&Action::RotateCw) {
+                Some(RotationDirection::Clockwise)
+            } else if self.down.contains(&Action::RotateCcw) {
+                Some(RotationDirection::CounterClockwise)
+            } else if self.down.contains(&Action::Rotate180) {
+                Some(RotationDirection::Half)
             } else {
-                return;
+                None
+            };
+            if let Some(direction) = direction {
+                // Kicks apply as usual, and if nothing fits it just spawns unrotated
+                self.rotate_focused(direction);
             }
         }
+
+        // If it generates into a piece, game ova
+        if !self.fits(&self.focused) {
+            self.game.end(GameOverReason::BlockOut);
+        }
+    }
+
+    /// Puts the focused tetromino aside, bringing back the one that was held before or the next one if there wasn't any
+    fn hold(&mut self) {
+        if !self.can_hold {
+            return;
+        }
+        self.can_hold = false;
+
+        let next = match self.held.replace(self.focused.piece) {
+            Some(held) => held,
+            None => self.randomizer.next_type(),
+        };
+        self.spawn(self.rotation_system.spawn_placement(next, &self.dim));
+    }
+
+    /// Implmentation of hard drop preview
+    pub fn full_fall_focused(&mut self) {
+        let down = Tetromino::get_dxdy(Direction::Down);
+        self.ghost = self.focused;
+        while self.fits(&self.ghost.moved(down)) {
+            self.ghost = self.ghost.moved(down);
+        }
     }
 
     fn clear(&mut self) {
         self.stagnant_tetrominos.clear();
     }
 
-    fn game_over(&mut self, rl: &RaylibHandle) {
-        if rl.is_key_down(KeyboardKey::KEY_R) {
-            // Clear board
-            self.clear();
-            // Create new game instance
-            self.game = Game::default();
-        }
+    /// Starts a new game on the same board, with tetrominos generated from `seed`
+    pub fn restart(&mut self, seed: u64) {
+        // Clear board
+        self.clear();
+        // Create new game instance
+        self.game = Game::default();
+        self.randomizer = Randomizer::with_pieces(seed, self.rotation_system.types());
+        self.focused = self
+            .rotation_system
+            .spawn_placement(self.randomizer.next_type(), &self.dim);
+        self.ghost = self.focused;
+        self.held = None;
+        self.can_hold = true;
+        self.queue.clear();
+    }
+
+    /// Actions queued for the next `update`
+    pub fn pending_actions(&self) -> Vec<Action> {
+        self.queue.clone()
+    }
+
+    /// Queues an action for the next `update`
+    pub fn push_action(&mut self, action: Action) {
+        self.queue.push(action);
+    }
+
+    /// Queues a frame's actions for the next `update`, along with what's held down during it
+    pub fn push_input(&mut self, input: &FrameInput) {
+        self.queue.extend(input.actions.iter().copied());
+        self.down = input.down.clone();
+    }
+
+    /// Removes every queued `action`, returning whether there were any
+    fn take_action(&mut self, action: Action) -> bool {
+        let before = self.queue.len();
+        self.queue.retain(|queued| *queued != action);
+        self.queue.len() != before
     }
 
-    pub fn tick(&mut self, rl: &RaylibHandle) {
+    /// Advances the game by one frame using whatever actions are queued,
+    /// doesn't touch the window so that it can be driven by any input source
+    pub fn update(&mut self) {
         if !self.game.running() {
-            self.game_over(rl);
+            // Nothing to do with keys pressed after the game ended
+            self.queue.clear();
+            return;
+        }
+
+        if self.take_action(Action::Pause) {
+            self.game.toggle_pause();
+        }
+        if *self.game.paused() {
+            self.queue.clear();
             return;
         }
 
@@ -162,8 +445,7 @@ impl Universe {
         // update preview/ghost
         self.full_fall_focused();
 
-        self.tetromino_controls.tick(rl);
-        self.receive_key();
+        self.receive_actions();
 
         // Literally just move current .y down
         // Falls at the rate of 6 per second
@@ -171,7 +453,10 @@ impl Universe {
         if self.game.should_fall() {
             self.fall_focused();
         }
+    }
 
+    /// Clears every full row, moving whatever's above them down
+    fn clear_lines(&mut self) {
         let mut levels: HashMap<u32, u32> = HashMap::new();
 
         // Setup hash
@@ -179,8 +464,10 @@ impl Universe {
         for tetromino in self.stagnant_tetrominos.iter() {
             for coord in tetromino.coords() {
                 // Store the number of tetris parts in each y level
-                let e = levels.entry(coord.y).or_insert(0);
-                *e += 1;
+                if let Some((_, row)) = self.dim.cell(coord) {
+                    let e = levels.entry(row).or_insert(0);
+                    *e += 1;
+                }
             }
         }
 
@@ -190,8 +477,9 @@ impl Universe {
             .filter_map(|l| if *l.1 == self.dim.w { Some(*l.0) } else { None })
             .collect::<HashSet<u32>>();
 
-        // Nothing to do if there aren't any full rows
+        // Nothing to do if there aren't any full rows, other than breaking the combo
         if levels.is_empty() {
+            self.game.update(0);
             return;
         }
 
@@ -202,7 +490,8 @@ impl Universe {
         while i != self.stagnant_tetrominos.len() {
             let mut j = 0;
             while j != self.stagnant_tetrominos[i].coords().len() {
-                if levels.contains(&self.stagnant_tetrominos[i].coords()[j].y) {
+                let cell = self.dim.cell(&self.stagnant_tetrominos[i].coords()[j]);
+                if cell.is_some_and(|(_, row)| levels.contains(&row)) {
                     self.stagnant_tetrominos[i].coords_mut().remove(j);
                 } else {
                     j += 1;
@@ -217,7 +506,7 @@ impl Universe {
         }
 
         // Then prepare to move the other tetriminos down (gravity)
-        let mut diff = vec![0; self.dim.h as usize];
+        let mut diff = vec![0; self.dim.total_h() as usize];
         for level in levels.iter() {
             Universe::change_arr_from_idx(&mut diff, *level, 1);
         }
@@ -226,9 +515,10 @@ impl Universe {
         // fk, we're iterating over stagnant tetrominos like 3 times. We honestly only need to really do it twice if we store the hashmap
         // If we implemented it with an array we would only need to iterate over the board once
         for i in 0..self.stagnant_tetrominos.len() {
-            for j in 0..self.stagnant_tetrominos[i].coords().len() {
-                self.stagnant_tetrominos[i].coords_mut()[j].y -=
-                    diff[self.stagnant_tetrominos[i].coords()[j].y as usize];
+            for coord in self.stagnant_tetrominos[i].coords_mut().iter_mut() {
+                if let Some((_, row)) = self.dim.cell(coord) {
+                    coord.y -= diff[row as usize] as i32;
+                }
             }
         }
 
@@ -265,6 +555,24 @@ impl Universe {
             );
         }
 
+        // Mark where the visible board ends, when the hidden rows above it are peeked at
+        if config.settings().board.peek_rows > 0 {
+            let (_, dy) = Tetromino::cell_size(config, &self.dim);
+            let top = (*config.h() - self.dim.h * dy) as f32;
+            d.draw_line_ex(
+                Vector2 {
+                    x: *config.canvas_l() as f32,
+                    y: top,
+                },
+                Vector2 {
+                    x: (*config.canvas_l() as u32 + self.dim.w * dx) as f32,
+                    y: top,
+                },
+                2_f32,
+                self.color_palette.line(),
+            );
+        }
+
         // for x in (0..=self.w).into_iter() {
         //     // For every implement of x, draw from the ground to the ceiling
         //     let current_x = x * dx + *config.canvas_l() as u32;
@@ -302,6 +610,11 @@ impl Universe {
         // Clear background
         d.clear_background(self.color_palette.grid());
 
+        self.render_board(d, config);
+    }
+
+    /// Renders everything but the background, so that several boards can share a window
+    pub fn render_board(&self, d: &mut RaylibDrawHandle, config: &Config) {
         // Render grid
         self.render_grid(d, config);
 
@@ -318,26 +631,37 @@ impl Universe {
         self.ghost()
             .render_alpha(d, config, &self.dim, &self.color_palette);
 
+        self.render_queues(d, config);
+
         // If game is in an 'over' state
         if !self.game.running() {
             d.draw_text(
                 "GAME",
-                150,
+                *config.text_l() as i32,
                 (*config.h() as f64 / 2_f64) as i32,
                 100,
                 self.color_palette.line(),
             );
             d.draw_text(
                 "OVER",
-                (*config.w() - 400) as i32,
+                *config.text_r() as i32,
                 (*config.h() as f64 / 2_f64) as i32,
                 100,
                 self.color_palette.line(),
             );
+            if let Some(reason) = self.game.game_over() {
+                d.draw_text(
+                    reason.name(),
+                    *config.text_l() as i32,
+                    (*config.h() as f64 / 2_f64) as i32 + 100,
+                    30,
+                    self.color_palette.line(),
+                );
+            }
             d.draw_text(
                 "Press \"r\" to restart",
-                150,
-                (*config.h() as f64 / 2_f64) as i32 + 100,
+                *config.text_l() as i32,
+                (*config.h() as f64 / 2_f64) as i32 + 140,
                 20,
                 self.color_palette.line(),
             );
@@ -345,39 +669,83 @@ impl Universe {
             // Display level
             d.draw_text(
                 &format!("LEVEL: {}", self.game.level()),
-                150,
-                150,
+                *config.text_l() as i32,
+                *config.text_top() as i32,
                 50,
                 self.color_palette.line(),
             );
             // Display score
             d.draw_text(
                 &format!("score: {}", self.game.score()),
-                150,
-                200,
+                *config.text_l() as i32,
+                *config.text_top() as i32 + 50,
                 30,
                 self.color_palette.line(),
-            )
+            );
+            if *self.game.paused() {
+                d.draw_text(
+                    "PAUSED",
+                    *config.text_l() as i32,
+                    (*config.h() as f64 / 2_f64) as i32,
+                    100,
+                    self.color_palette.line(),
+                );
+            }
         }
     }
 
+    /// Renders the held tetromino to the left of the board, and the next queue to the right of it
+    fn render_queues(&self, d: &mut RaylibDrawHandle, config: &Config) {
+        // Previews are drawn at half the size of the board's cells
+        let size = (*config.actual_w() as u32 / self.dim.w / 2) as i32;
+        let color = self.color_palette.line();
+
+        let hold_x = *config.canvas_l() as i32 - 6 * size;
+        d.draw_text("HOLD", hold_x, size, size, color);
+        if let Some(held) = self.held {
+            let held = self.rotation_system.spawn(held, &self.dim);
+            let palette = if self.can_hold {
+                self.color_palette.clone()
+            } else {
+                self.color_palette.faded(0.4)
+            };
+            held.render_preview(d, hold_x, 5 * size, size, &palette);
+        }
+
+        let next_x = *config.canvas_r() as i32 + 2 * size;
+        d.draw_text("NEXT", next_x, size, size, color);
+        // Each one goes a row below the last, however tall that was
+        let mut bottom = 2 * size;
+        for tetromino_type in self.randomizer.preview().iter() {
+            let next = self.rotation_system.spawn(*tetromino_type, &self.dim);
+            let ys = next.coords().iter().map(|coord| coord.y);
+            let height = ys.clone().max().unwrap_or(0) - ys.min().unwrap_or(0) + 1;
+            bottom += (height + 1) * size;
+            next.render_preview(d, next_x, bottom, size, &self.color_palette);
+        }
+    }
 }
 
 // Getters and setters
 impl Universe {
-    /// Get a reference to the universe's current.
-    pub fn focused_tetromino(&self) -> &Tetromino {
-        &self.focused_tetromino
+    /// Get a reference to the universe's current placement.
+    pub fn focused(&self) -> &Placement {
+        &self.focused
     }
 
-    /// Get a mutable reference to the universe's current.
-    pub fn focused_tetromino_mut(&mut self) -> &mut Tetromino {
-        &mut self.focused_tetromino
+    /// The cells of the universe's current, looked up from its placement.
+    pub fn focused_tetromino(&self) -> Tetromino {
+        self.rotation_system.tetromino(&self.focused)
     }
 
-    /// Get a reference to the universe's ghost.
-    pub fn ghost(&self) -> &Tetromino {
-        &self.ghost
+    /// The cells of the universe's ghost, looked up from its placement.
+    pub fn ghost(&self) -> Tetromino {
+        self.rotation_system.tetromino(&self.ghost)
+    }
+
+    /// Get a reference to the universe's board.
+    pub fn dim(&self) -> &Dimensions {
+        &self.dim
     }
 
     /// Get a reference to the universe's stagnant tetrominos.
@@ -388,21 +756,184 @@ impl Universe {
     pub fn stagnant_tetrominos_mut(&mut self) -> &mut Vec<Tetromino> {
         &mut self.stagnant_tetrominos
     }
+
+    /// Get a reference to the universe's game.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Get the seed that the universe's tetrominos are generated from.
+    pub fn seed(&self) -> u64 {
+        *self.randomizer.seed()
+    }
+
+    /// Get a reference to the universe's held tetromino.
+    pub fn held(&self) -> &Option<TetrominoType> {
+        &self.held
+    }
+
+    /// Get whether the universe's focused tetromino can still be held.
+    pub fn can_hold(&self) -> bool {
+        self.can_hold
+    }
+
+    /// Whether the focused tetromino is resting on something, so that it locks the next time it falls
+    pub fn grounded(&self) -> bool {
+        !self.fits(&self.focused.moved(Tetromino::get_dxdy(Direction::Down)))
+    }
+
+    /// Get a reference to the tetrominos coming up next, in order.
+    pub fn preview(&self) -> &std::collections::VecDeque<TetrominoType> {
+        self.randomizer.preview()
+    }
+
+    /// Get a reference to the universe's randomizer.
+    pub fn randomizer(&self) -> &Randomizer {
+        &self.randomizer
+    }
+
+    /// Get which kicks the universe's 180° rotations use.
+    pub fn kicks_180(&self) -> Kicks180 {
+        self.kicks_180
+    }
+
+    /// Set which kicks the universe's 180° rotations use.
+    pub fn set_kicks_180(&mut self, kicks_180: Kicks180) {
+        self.kicks_180 = kicks_180;
+    }
+
+    /// Get whether the universe's tetrominos spawn rotated while a rotation is held down.
+    pub fn irs(&self) -> bool {
+        self.irs
+    }
+
+    /// Set whether the universe's tetrominos spawn rotated while a rotation is held down.
+    pub fn set_irs(&mut self, irs: bool) {
+        self.irs = irs;
+    }
+
+    /// Get whether the universe's tetrominos spawn straight into hold while hold is held down.
+    pub fn ihs(&self) -> bool {
+        self.ihs
+    }
+
+    /// Set whether the universe's tetrominos spawn straight into hold while hold is held down.
+    pub fn set_ihs(&mut self, ihs: bool) {
+        self.ihs = ihs;
+    }
+
+    /// Get whether locking a tetromino partly above the visible board ends the universe's game.
+    pub fn partial_lock_out(&self) -> bool {
+        self.partial_lock_out
+    }
+
+    /// Set whether locking a tetromino partly above the visible board ends the universe's game.
+    pub fn set_partial_lock_out(&mut self, partial_lock_out: bool) {
+        self.partial_lock_out = partial_lock_out;
+    }
+
+    /// Get a reference to the universe's rotation system.
+    pub fn rotation_system(&self) -> &RotationSystem {
+        &self.rotation_system
+    }
+
+    /// Checks that the board is one that can be played on, and that every piece the universe refers to is one its
+    /// rules describe, in an orientation they have and on the board, for universes read back from a file that could
+    /// have been edited
+    pub fn validate(&self) -> Result<(), String> {
+        self.dim.validate()?;
+        self.rotation_system.validate()?;
+        if self.rotation_system.widest() > self.dim.w {
+            return Err(format!(
+                "rotation system \"{}\" has pieces wider than the board",
+                self.rotation_system.name
+            ));
+        }
+        let types = self.rotation_system.types();
+        let known = |piece: &TetrominoType| {
+            if types.contains(piece) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "piece {} isn't one of rotation system \"{}\"'s",
+                    piece.name(),
+                    self.rotation_system.name
+                ))
+            }
+        };
+        for placement in [&self.focused, &self.ghost].iter() {
+            known(&placement.piece)?;
+            let states = self.rotation_system.piece(placement.piece).states.len() as u32;
+            if placement.rotation >= states {
+                return Err(format!(
+                    "piece {} is in orientation {}, it only has {}",
+                    placement.piece.name(),
+                    placement.rotation,
+                    states
+                ));
+            }
+        }
+        if !self
+            .stagnant_tetrominos
+            .iter()
+            .flat_map(|tetromino| tetromino.coords().iter())
+            .chain(self.rotation_system.cells(&self.focused).iter())
+            .all(|coord| self.dim.contains(coord))
+        {
+            return Err(String::from("there are cells off the board"));
+        }
+        if self.randomizer.pieces().is_empty() {
+            return Err(String::from("there are no pieces to draw from"));
+        }
+        self.held
+            .iter()
+            .chain(self.randomizer.preview().iter())
+            .chain(self.randomizer.pieces().iter())
+            .try_for_each(known)
+    }
+
+    /// Copy of the universe with the rotation system's tables left out, for storing alongside a copy of the rules
+    /// that's kept once. It can't be played until `attach_rules` puts them back
+    pub(crate) fn without_rules(&self) -> Universe {
+        let mut universe = self.clone();
+        universe.rotation_system.pieces.clear();
+        universe
+    }
+
+    /// Puts back the rules that `without_rules` left out, leaving the pieces where they are
+    pub(crate) fn attach_rules(&mut self, rotation_system: &RotationSystem) {
+        self.rotation_system = rotation_system.clone();
+        self.color_palette
+            .set_piece_colors(self.rotation_system.colors());
+    }
+
+    /// Set how the universe's tetrominos spawn and rotate, respawning the focused one to match.
+    /// A system with a different piece set starts the randomizer over on it
+    pub fn set_rotation_system(&mut self, rotation_system: RotationSystem) {
+        self.rotation_system = rotation_system;
+        self.color_palette
+            .set_piece_colors(self.rotation_system.colors());
+        let types = self.rotation_system.types();
+        let mut tetromino_type = self.focused.piece;
+        if *self.randomizer.pieces() != types {
+            self.randomizer = Randomizer::with_pieces(*self.randomizer.seed(), types);
+            self.held = None;
+            tetromino_type = self.randomizer.next_type();
+        }
+        self.focused = self
+            .rotation_system
+            .spawn_placement(tetromino_type, &self.dim);
+        self.full_fall_focused();
+    }
+
+    /// Get a reference to the universe's color palette.
+    pub fn color_palette(&self) -> &ColorPalette {
+        &self.color_palette
+    }
 }
 
 impl Default for Universe {
     fn default() -> Self {
-        Universe::new(
-            Dimensions {
-                w: INITIAL_WIDTH,
-                h: INITIAL_HEIGHT,
-            },
-            TetrominoType::generate_tetromino_rand(),
-            vec![],
-            TetrominoControls::default(),
-            ColorPalette::default(),
-            Game::default(),
-            TetrominoType::generate_tetromino_rand(),
-        )
+        Universe::from_seed(rand::random())
     }
 }
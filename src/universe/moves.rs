@@ -0,0 +1,122 @@
+// Every placement a piece can be locked in, along with the inputs that get it there, for bots, hints and finesse
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+
+use super::rotations::{placement::Placement, rotation_direction::RotationDirection};
+use super::Universe;
+use crate::tetromino::{coord::Coord, direction::Direction, Tetromino};
+use crate::Action;
+
+/// A placement that a piece can be hard dropped into
+#[derive(Clone, Debug, PartialEq)]
+pub struct Move {
+    // Where the piece locks
+    pub placement: Placement,
+    // Whether the piece was rotated into place and can't move left, right or up from there
+    pub spin: bool,
+    // Fewest inputs that get the piece there from where it started, ending in the hard drop
+    pub inputs: Vec<Action>,
+}
+
+/// Inputs that move a piece without locking it, ties between equally short paths go to whichever comes first
+const STEPS: [Action; 6] = [
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::RotateCw,
+    Action::RotateCcw,
+    Action::Rotate180,
+    Action::SoftDrop,
+];
+
+impl Universe {
+    /// Every placement that a piece starting at `start` can be locked in on the current stack, tucks and kicks
+    /// included. Placements that cover the same cells are only listed once, unless one is a spin and the other
+    /// isn't. Inputs assume the piece doesn't fall on its own on the way there
+    pub fn moves(&self, start: &Placement) -> Vec<Move> {
+        let filled: HashSet<Coord> = self
+            .stagnant_tetrominos
+            .iter()
+            .flat_map(|tetromino| tetromino.coords().iter().copied())
+            .collect();
+        // Same rules as the focused tetromino plays by, just without looking through the stack every time
+        let fits = |cells: &[Coord]| {
+            cells.iter().all(|coord| self.dim.contains(coord))
+                && !cells.iter().any(|coord| filled.contains(coord))
+        };
+        let fits_at = |placement: &Placement| fits(&self.rotation_system.cells(placement));
+        if !fits_at(start) {
+            return Vec::new();
+        }
+
+        // Breadth first, so that the first way found to any placement is one of the shortest
+        let mut reached: HashMap<Placement, Option<(Placement, Action)>> = HashMap::new();
+        reached.insert(*start, None);
+        let mut order = vec![*start];
+        let mut queue = VecDeque::from(vec![*start]);
+        while let Some(current) = queue.pop_front() {
+            for step in STEPS.iter() {
+                let turn = |direction| {
+                    self.rotation_system
+                        .rotate(&current, direction, self.kicks_180, fits)
+                };
+                let shift = |direction| {
+                    Some(current.moved(Tetromino::get_dxdy(direction)))
+                        .filter(|moved| fits_at(moved))
+                };
+                let next = match step {
+                    Action::MoveLeft => shift(Direction::Left),
+                    Action::MoveRight => shift(Direction::Right),
+                    Action::SoftDrop => shift(Direction::Down),
+                    Action::RotateCw => turn(RotationDirection::Clockwise),
+                    Action::RotateCcw => turn(RotationDirection::CounterClockwise),
+                    _ => turn(RotationDirection::Half),
+                };
+                if let Some(next) = next {
+                    if let Entry::Vacant(entry) = reached.entry(next) {
+                        entry.insert(Some((current, *step)));
+                        order.push(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        let mut moves = Vec::new();
+        let mut seen = HashSet::new();
+        for placement in order {
+            let mut inputs = Vec::new();
+            let mut at = placement;
+            while let Some((from, step)) = reached[&at] {
+                inputs.push(step);
+                at = from;
+            }
+            inputs.reverse();
+
+            let mut landed = placement;
+            while fits_at(&landed.moved(Tetromino::get_dxdy(Direction::Down))) {
+                landed = landed.moved(Tetromino::get_dxdy(Direction::Down));
+            }
+            let rotated = matches!(
+                inputs.last(),
+                Some(Action::RotateCw) | Some(Action::RotateCcw) | Some(Action::Rotate180)
+            );
+            let spin = rotated
+                && landed == placement
+                && [Direction::Left, Direction::Right, Direction::Up]
+                    .map(Tetromino::get_dxdy)
+                    .iter()
+                    .all(|dxdy| !fits_at(&placement.moved(*dxdy)));
+            inputs.push(Action::HardDrop);
+
+            let mut cells = self.rotation_system.cells(&landed);
+            cells.sort_by_key(|coord| (coord.x, coord.y));
+            if seen.insert((cells, spin)) {
+                moves.push(Move {
+                    placement: landed,
+                    spin,
+                    inputs,
+                });
+            }
+        }
+        moves
+    }
+}
@@ -0,0 +1,40 @@
+// A piece as where it is rather than which cells it covers. The cells are looked up from the rotation system's
+// table of orientations whenever they're needed, so moving and rotating never edit coordinates in place
+use serde::{Deserialize, Serialize};
+
+use crate::tetromino::tetromino_type::TetrominoType;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Placement {
+    pub piece: TetrominoType,
+    // Orientation, indexing the piece's states
+    pub rotation: u32,
+    // Where the corner of the piece's box is on the board
+    pub origin: [i32; 2],
+}
+
+impl Placement {
+    pub fn new(piece: TetrominoType, rotation: u32, origin: [i32; 2]) -> Self {
+        Placement {
+            piece,
+            rotation,
+            origin,
+        }
+    }
+
+    /// The same placement moved by `dx_dy`
+    pub fn moved(&self, dx_dy: [i32; 2]) -> Self {
+        Placement {
+            origin: [self.origin[0] + dx_dy[0], self.origin[1] + dx_dy[1]],
+            ..*self
+        }
+    }
+
+    /// The same placement turned to `rotation` and moved by `dx_dy`
+    pub fn turned(&self, rotation: u32, dx_dy: [i32; 2]) -> Self {
+        Placement {
+            rotation,
+            ..self.moved(dx_dy)
+        }
+    }
+}
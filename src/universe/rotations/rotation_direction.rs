@@ -1,13 +1,6 @@
 pub enum RotationDirection {
     Clockwise,
     CounterClockwise,
+    // Turning around, 180°
+    Half,
 }
-impl RotationDirection {
-    // Gets reverse
-    pub fn flip(direction: RotationDirection) -> RotationDirection {
-        match direction {
-            RotationDirection::Clockwise => RotationDirection::CounterClockwise,
-            RotationDirection::CounterClockwise => RotationDirection::Clockwise,
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,762 @@
+// Rotation systems as data: the orientations of every piece, where it's rotated around and which kicks get tried,
+// so that SRS, ARS, NRS and anything else can be played under the same rules. A system is also the piece set that's
+// played with, which needn't be tetrominos at all
+use std::{collections::HashMap, fs, path::Path};
+
+use raylib::color::Color;
+use serde::{Deserialize, Serialize};
+
+use super::placement::Placement;
+use super::rotation_direction::RotationDirection;
+use super::{I_OFFSET_DATA, JLSTZ_OFFSET_DATA, O_OFFSET_DATA};
+use crate::config::{parse_color, ConfigError, Kicks180};
+use crate::tetromino::{coord::Coord, tetromino_type::TetrominoType, Tetromino};
+use crate::universe::Dimensions;
+
+/// Names of the rotation systems that are built in, anything else is read from a file
+pub const BUILTIN_SYSTEMS: [&str; 7] = [
+    "srs",
+    "ars",
+    "nrs",
+    "classic",
+    "pentominoes",
+    "trominoes",
+    "mixed",
+];
+
+/// Cells of each tetromino as it spawns under SRS, in the order of `TetrominoType::TETROMINOS`, relative to the
+/// cell it turns around, which is listed first
+const SRS_SPAWN_STATES: [[[i32; 2]; 4]; 7] = [
+    [[0, 0], [-1, 0], [1, 0], [2, 0]],
+    [[0, 0], [-1, 0], [1, 0], [-1, 1]],
+    [[0, 0], [-1, 0], [1, 0], [1, 1]],
+    [[0, 0], [1, 0], [0, 1], [1, 1]],
+    [[0, 0], [-1, 0], [0, 1], [1, 1]],
+    [[0, 0], [-1, 0], [0, 1], [1, 0]],
+    [[0, 0], [1, 0], [-1, 1], [0, 1]],
+];
+
+/// Most cells that a single piece can have
+pub const MAX_CELLS: usize = 16;
+
+/// Tests tried in order when rotating from one orientation to another, the first that fits is used
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Kick {
+    pub from: u32,
+    pub to: u32,
+    pub tests: Vec<[i32; 2]>,
+}
+
+/// How a single piece rotates. Cells are relative to the corner of its bounding box, y going up
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PieceRotation {
+    pub piece: TetrominoType,
+    // Cell that `shape` is turned around, and whose column is the centre column
+    pub pivot: [i32; 2],
+    // Cells of each of the 4 orientations, always listed in the same order. Left out when there's a `shape`
+    #[serde(default)]
+    pub states: Vec<Vec<[i32; 2]>>,
+    // Kicks are skipped when the first blocked cell, reading from the top left, is in the pivot's column (ARS)
+    #[serde(default)]
+    pub centre_column: bool,
+    // Hex colour, otherwise one of the palette's is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    // Orientation that the piece spawns in
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub spawn: u32,
+    // Cells of the first orientation, which the others are worked out from by turning them around the pivot
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shape: Vec<[i32; 2]>,
+    // Rotations without any kicks only fit in place. Last, since TOML tables have to come after plain values
+    #[serde(default)]
+    pub kicks: Vec<Kick>,
+}
+
+fn is_zero(spawn: &u32) -> bool {
+    *spawn == 0
+}
+
+impl PieceRotation {
+    /// A piece whose orientations are `shape` turned clockwise around `pivot`, trying each of `tests` on every
+    /// quarter turn
+    pub fn from_shape(
+        piece: TetrominoType,
+        shape: Vec<[i32; 2]>,
+        pivot: [i32; 2],
+        tests: &[[i32; 2]],
+        color: &str,
+    ) -> Self {
+        let mut rotation = PieceRotation {
+            piece,
+            pivot,
+            states: Vec::new(),
+            centre_column: false,
+            kicks: every_quarter_turn(tests),
+            color: Some(String::from(color)),
+            spawn: 0,
+            shape,
+        };
+        rotation.turn_shape();
+        rotation
+    }
+
+    /// Fills in the orientations from the shape, if they weren't given
+    fn turn_shape(&mut self) {
+        if !self.states.is_empty() || self.shape.is_empty() {
+            return;
+        }
+        let [px, py] = self.pivot;
+        let mut state = self.shape.clone();
+        for _ in 0..4 {
+            self.states.push(state.clone());
+            // Clockwise
+            state = state
+                .iter()
+                .map(|[x, y]| [px + (y - py), py - (x - px)])
+                .collect();
+        }
+    }
+
+    /// Tests for rotating from orientation `from` to `to`
+    pub fn tests(&self, from: u32, to: u32) -> &[[i32; 2]] {
+        self.kicks
+            .iter()
+            .find(|kick| kick.from == from && kick.to == to)
+            .map(|kick| kick.tests.as_slice())
+            .unwrap_or(&[[0, 0]])
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RotationSystem {
+    pub name: String,
+    pub pieces: Vec<PieceRotation>,
+}
+
+/// Kicks of the built in pieces that aren't tetrominos, a step either way and then up
+const POLYOMINO_TESTS: [[i32; 2]; 6] = [[0, 0], [-1, 0], [1, 0], [0, 1], [-2, 0], [2, 0]];
+
+/// Pentominos that can't be turned into each other, drawn with their pivot as `@`, then their colour
+const PENTOMINOS: [(&[&str], &str); 18] = [
+    (&[".##", "#@.", ".#."], "E07A5F"),
+    (&["##.", ".@#", ".#."], "D3705A"),
+    (&["#####"], "3D405B"),
+    (&["#...", "#@##"], "81B29A"),
+    (&["...#", "##@#"], "6FA38A"),
+    (&[".###", "#@.."], "F2CC8F"),
+    (&["###.", "..@#"], "E6BE7E"),
+    (&["##", "@#", "#."], "9C89B8"),
+    (&["##", "#@", ".#"], "8D79AB"),
+    (&["###", ".@.", ".#."], "F0A6CA"),
+    (&["#.#", "#@#"], "B8BEDD"),
+    (&["#..", "#..", "@##"], "5E8C61"),
+    (&["#..", "#@.", ".##"], "4E6E5D"),
+    (&[".#.", "#@#", ".#."], "EF476F"),
+    (&["..#.", "#@##"], "FFD166"),
+    (&[".#..", "##@#"], "F3C05A"),
+    (&["##.", ".@.", ".##"], "06D6A0"),
+    (&[".##", ".@.", "##."], "0BB38A"),
+];
+
+/// Trominos that can't be turned into each other
+const TROMINOS: [(&[&str], &str); 2] = [(&["#@#"], "118AB2"), (&["#.", "@#"], "073B4C")];
+
+/// Parses a piece drawn as rows of `#` and `.`, top row first, with its pivot as `@`
+fn shape(rows: &[&str]) -> (Vec<[i32; 2]>, [i32; 2]) {
+    let mut pivot = [0, 0];
+    let mut cells = Vec::new();
+    for (row, line) in rows.iter().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            let cell = [x as i32, (rows.len() - 1 - row) as i32];
+            if c == '@' {
+                pivot = cell;
+                // The pivot goes first, so that it's the cell that every orientation is placed by
+                cells.insert(0, cell);
+            } else if c == '#' {
+                cells.push(cell);
+            }
+        }
+    }
+    (cells, pivot)
+}
+
+/// Pieces drawn as in `PENTOMINOS`, numbered from `first`
+fn polyominos(drawings: &[(&[&str], &str)], first: u8) -> Vec<PieceRotation> {
+    drawings
+        .iter()
+        .enumerate()
+        .map(|(idx, (rows, color))| {
+            let (cells, pivot) = shape(rows);
+            PieceRotation::from_shape(
+                TetrominoType::new(first + idx as u8),
+                cells,
+                pivot,
+                &POLYOMINO_TESTS,
+                color,
+            )
+        })
+        .collect()
+}
+
+/// Parses cells drawn as rows of `#` and `.`, top row first, into coords with y going up
+fn cells(rows: &[&str]) -> Vec<[i32; 2]> {
+    let mut cells = Vec::new();
+    for (row, line) in rows.iter().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            if c == '#' {
+                cells.push([x as i32, (rows.len() - 1 - row) as i32]);
+            }
+        }
+    }
+    cells
+}
+
+/// All 4 orientations from drawings of each, for pieces whose cells don't keep their order under rotation
+fn drawn(states: [&[&str]; 4]) -> Vec<Vec<[i32; 2]>> {
+    states.iter().map(|rows| cells(rows)).collect()
+}
+
+/// Kicks that try each of `tests` for every quarter turn
+fn every_quarter_turn(tests: &[[i32; 2]]) -> Vec<Kick> {
+    (0..4)
+        .flat_map(|from| {
+            [1, 3].iter().map(move |turn| Kick {
+                from,
+                to: (from + turn) % 4,
+                tests: tests.to_vec(),
+            })
+        })
+        .collect()
+}
+
+impl RotationSystem {
+    /// Super Rotation System, as in guideline games. Tetrominos rotate around one of their cells, and the offset
+    /// tables both kick them and move them back onto their true center
+    pub fn srs() -> Self {
+        let pieces = TetrominoType::TETROMINOS
+            .iter()
+            .zip(SRS_SPAWN_STATES.iter())
+            .map(|(piece, spawn_state)| {
+                let mut state = spawn_state.to_vec();
+                let mut states = Vec::new();
+                for _ in 0..4 {
+                    states.push(state.clone());
+                    // Clockwise
+                    state = state.iter().map(|[x, y]| [*y, -*x]).collect();
+                }
+
+                let offset_data = match *piece {
+                    TetrominoType::I => &I_OFFSET_DATA[..],
+                    TetrominoType::O => &O_OFFSET_DATA[..],
+                    _ => &JLSTZ_OFFSET_DATA[..],
+                };
+                // Current - Next, for every test
+                let tests = |from: usize, to: usize, count: usize| {
+                    offset_data
+                        .iter()
+                        .take(count)
+                        .map(|test| [test[from][0] - test[to][0], test[from][1] - test[to][1]])
+                        .collect()
+                };
+                let kicks = (0..4)
+                    .flat_map(|from| {
+                        // 180° turns only get the first test, their kicks come from the kicks_180 setting
+                        [(1, offset_data.len()), (2, 1), (3, offset_data.len())]
+                            .iter()
+                            .map(move |(turn, count)| {
+                                let to = (from + turn) % 4;
+                                Kick {
+                                    from: from as u32,
+                                    to: to as u32,
+                                    tests: tests(from, to, *count),
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+
+                PieceRotation {
+                    piece: *piece,
+                    pivot: [0, 0],
+                    states,
+                    centre_column: false,
+                    kicks,
+                    color: None,
+                    spawn: 0,
+                    shape: Vec::new(),
+                }
+            })
+            .collect();
+        RotationSystem {
+            name: String::from("srs"),
+            pieces,
+        }
+    }
+
+    /// SRS without any kicks, tetrominos only turn where they are
+    pub fn classic() -> Self {
+        let mut system = RotationSystem::srs();
+        system.name = String::from("classic");
+        for piece in system.pieces.iter_mut() {
+            // The first test only moves the tetromino back onto its center
+            for kick in piece.kicks.iter_mut() {
+                kick.tests.truncate(1);
+            }
+        }
+        system
+    }
+
+    /// Arika Rotation System, as in TGM. Tetrominos sit at the bottom of their box, spawn flat side up,
+    /// and kick one cell right and then left, except when the centre column rule says otherwise
+    pub fn ars() -> Self {
+        let kicks = every_quarter_turn(&[[0, 0], [1, 0], [-1, 0]]);
+        let piece = |piece, states, centre_column| PieceRotation {
+            piece,
+            pivot: [1, 1],
+            states,
+            centre_column,
+            kicks: kicks.clone(),
+            color: None,
+            spawn: 0,
+            shape: Vec::new(),
+        };
+        let pieces = vec![
+            PieceRotation {
+                piece: TetrominoType::I,
+                pivot: [1, 2],
+                states: drawn([
+                    &["....", "####", "....", "...."],
+                    &["..#.", "..#.", "..#.", "..#."],
+                    &["....", "####", "....", "...."],
+                    &["..#.", "..#.", "..#.", "..#."],
+                ]),
+                centre_column: false,
+                kicks: Vec::new(),
+                color: None,
+                spawn: 0,
+                shape: Vec::new(),
+            },
+            piece(
+                TetrominoType::J,
+                drawn([
+                    &["...", "###", "..#"],
+                    &[".#.", ".#.", "##."],
+                    &["...", "#..", "###"],
+                    &[".##", ".#.", ".#."],
+                ]),
+                true,
+            ),
+            piece(
+                TetrominoType::L,
+                drawn([
+                    &["...", "###", "#.."],
+                    &["##.", ".#.", ".#."],
+                    &["...", "..#", "###"],
+                    &[".#.", ".#.", ".##"],
+                ]),
+                true,
+            ),
+            PieceRotation {
+                piece: TetrominoType::O,
+                pivot: [0, 0],
+                states: drawn([&["##", "##"], &["##", "##"], &["##", "##"], &["##", "##"]]),
+                centre_column: false,
+                kicks: Vec::new(),
+                color: None,
+                spawn: 0,
+                shape: Vec::new(),
+            },
+            piece(
+                TetrominoType::S,
+                drawn([
+                    &["...", ".##", "##."],
+                    &["#..", "##.", ".#."],
+                    &["...", ".##", "##."],
+                    &["#..", "##.", ".#."],
+                ]),
+                false,
+            ),
+            piece(
+                TetrominoType::T,
+                drawn([
+                    &["...", "###", ".#."],
+                    &[".#.", "##.", ".#."],
+                    &["...", ".#.", "###"],
+                    &[".#.", ".##", ".#."],
+                ]),
+                true,
+            ),
+            piece(
+                TetrominoType::Z,
+                drawn([
+                    &["...", "##.", ".##"],
+                    &["..#", ".##", ".#."],
+                    &["...", "##.", ".##"],
+                    &["..#", ".##", ".#."],
+                ]),
+                false,
+            ),
+        ];
+        RotationSystem {
+            name: String::from("ars"),
+            pieces,
+        }
+    }
+
+    /// Nintendo Rotation System, as in the NES game. No kicks at all, and the pieces with only two orientations
+    /// lean right when standing up
+    pub fn nrs() -> Self {
+        let piece = |piece, pivot, states| PieceRotation {
+            piece,
+            pivot,
+            states,
+            centre_column: false,
+            kicks: Vec::new(),
+            color: None,
+            spawn: 0,
+            shape: Vec::new(),
+        };
+        let pieces = vec![
+            piece(
+                TetrominoType::I,
+                [1, 1],
+                drawn([
+                    &["....", "....", "####", "...."],
+                    &["..#.", "..#.", "..#.", "..#."],
+                    &["....", "....", "####", "...."],
+                    &["..#.", "..#.", "..#.", "..#."],
+                ]),
+            ),
+            piece(
+                TetrominoType::J,
+                [1, 1],
+                drawn([
+                    &["...", "###", "..#"],
+                    &[".#.", ".#.", "##."],
+                    &["#..", "###", "..."],
+                    &[".##", ".#.", ".#."],
+                ]),
+            ),
+            piece(
+                TetrominoType::L,
+                [1, 1],
+                drawn([
+                    &["...", "###", "#.."],
+                    &["##.", ".#.", ".#."],
+                    &["..#", "###", "..."],
+                    &[".#.", ".#.", ".##"],
+                ]),
+            ),
+            piece(
+                TetrominoType::O,
+                [0, 0],
+                drawn([&["##", "##"], &["##", "##"], &["##", "##"], &["##", "##"]]),
+            ),
+            piece(
+                TetrominoType::S,
+                [1, 1],
+                drawn([
+                    &["...", ".##", "##."],
+                    &[".#.", ".##", "..#"],
+                    &["...", ".##", "##."],
+                    &[".#.", ".##", "..#"],
+                ]),
+            ),
+            piece(
+                TetrominoType::T,
+                [1, 1],
+                drawn([
+                    &["...", "###", ".#."],
+                    &[".#.", "##.", ".#."],
+                    &[".#.", "###", "..."],
+                    &[".#.", ".##", ".#."],
+                ]),
+            ),
+            piece(
+                TetrominoType::Z,
+                [1, 1],
+                drawn([
+                    &["...", "##.", ".##"],
+                    &["..#", ".##", ".#."],
+                    &["...", "##.", ".##"],
+                    &["..#", ".##", ".#."],
+                ]),
+            ),
+        ];
+        RotationSystem {
+            name: String::from("nrs"),
+            pieces,
+        }
+    }
+
+    /// The 18 pentominos, numbered from 7 so that they can be mixed with the tetrominos
+    pub fn pentominoes() -> Self {
+        RotationSystem {
+            name: String::from("pentominoes"),
+            pieces: polyominos(&PENTOMINOS, 7),
+        }
+    }
+
+    /// The 2 trominos, numbered after the pentominos
+    pub fn trominoes() -> Self {
+        RotationSystem {
+            name: String::from("trominoes"),
+            pieces: polyominos(&TROMINOS, 7 + PENTOMINOS.len() as u8),
+        }
+    }
+
+    /// Tetrominos rotating with SRS, together with every pentomino and tromino
+    pub fn mixed() -> Self {
+        let mut pieces = RotationSystem::srs().pieces;
+        pieces.extend(RotationSystem::pentominoes().pieces);
+        pieces.extend(RotationSystem::trominoes().pieces);
+        RotationSystem {
+            name: String::from("mixed"),
+            pieces,
+        }
+    }
+
+    /// One of the built in systems by name
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "srs" => Some(RotationSystem::srs()),
+            "ars" => Some(RotationSystem::ars()),
+            "nrs" => Some(RotationSystem::nrs()),
+            "classic" => Some(RotationSystem::classic()),
+            "pentominoes" => Some(RotationSystem::pentominoes()),
+            "trominoes" => Some(RotationSystem::trominoes()),
+            "mixed" => Some(RotationSystem::mixed()),
+            _ => None,
+        }
+    }
+
+    /// Reads and validates a rotation system written as TOML
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let mut system: RotationSystem = toml::from_str(&fs::read_to_string(path)?)?;
+        for piece in system.pieces.iter_mut() {
+            piece.turn_shape();
+        }
+        system.validate().map_err(ConfigError::Invalid)?;
+        Ok(system)
+    }
+
+    /// A built in system, or else one read from the file at `name`
+    pub fn from_setting(name: &str) -> Result<Self, ConfigError> {
+        match RotationSystem::builtin(name) {
+            Some(system) => Ok(system),
+            None => RotationSystem::load(Path::new(name)),
+        }
+    }
+
+    /// Checks that there's at least one piece, that none is described twice,
+    /// and that each has 4 orientations of the same number of cells
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pieces.is_empty() {
+            return Err(format!("rotation system \"{}\" has no pieces", self.name));
+        }
+        for piece in self.pieces.iter() {
+            let name = piece.piece.name();
+            let count = self.pieces.iter().filter(|p| p.piece == piece.piece).count();
+            if count != 1 {
+                return Err(format!(
+                    "rotation system \"{}\" describes piece {} {} times, it should be exactly once",
+                    self.name, name, count
+                ));
+            }
+            if piece.states.len() != 4 {
+                return Err(format!(
+                    "piece {} has {} orientations, it should have 4",
+                    name,
+                    piece.states.len()
+                ));
+            }
+            let cells = piece.states[0].len();
+            if cells == 0 || cells > MAX_CELLS || piece.states.iter().any(|state| state.len() != cells) {
+                return Err(format!(
+                    "every orientation of piece {} should have the same number of cells, from 1 to {}",
+                    name, MAX_CELLS
+                ));
+            }
+            if piece.spawn > 3 {
+                return Err(format!(
+                    "piece {} spawns in orientation {}, it should be between 0 and 3",
+                    name, piece.spawn
+                ));
+            }
+            if let Some(color) = piece.color.as_ref().filter(|color| parse_color(color).is_none()) {
+                return Err(format!(
+                    "piece {} has colour \"{}\", it should be 6 hex digits like \"FF8800\"",
+                    name, color
+                ));
+            }
+            if let Some(kick) = piece
+                .kicks
+                .iter()
+                .find(|kick| kick.from > 3 || kick.to > 3 || kick.tests.is_empty())
+            {
+                return Err(format!(
+                    "kick of piece {} from {} to {} should be between orientations 0 to 3, with at least one test",
+                    name, kick.from, kick.to
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every piece in the set, in the order they're numbered
+    pub fn types(&self) -> Vec<TetrominoType> {
+        let mut types: Vec<TetrominoType> = self.pieces.iter().map(|piece| piece.piece).collect();
+        types.sort();
+        types
+    }
+
+    /// Colours of the pieces that have their own
+    pub fn colors(&self) -> HashMap<TetrominoType, Color> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| Some((piece.piece, parse_color(piece.color.as_ref()?)?)))
+            .collect()
+    }
+
+    /// Columns taken up by the widest piece as it spawns
+    pub fn widest(&self) -> u32 {
+        self.pieces
+            .iter()
+            .map(|piece| {
+                let cells = &piece.states[piece.spawn as usize];
+                let left = cells.iter().map(|[x, _]| *x).min().unwrap_or(0);
+                let right = cells.iter().map(|[x, _]| *x).max().unwrap_or(0);
+                (right - left + 1) as u32
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Get how `tetromino_type` rotates.
+    pub fn piece(&self, tetromino_type: TetrominoType) -> &PieceRotation {
+        self.pieces
+            .iter()
+            .find(|piece| piece.piece == tetromino_type)
+            .expect("pieces are only ever drawn from the rotation system's own set")
+    }
+
+    /// Where a new `tetromino_type` goes on a board of `dim`, in its spawn orientation,
+    /// centred (rounding left) with its bottom on the spawn row
+    pub fn spawn_placement(&self, tetromino_type: TetrominoType, dim: &Dimensions) -> Placement {
+        let piece = self.piece(tetromino_type);
+        let cells = &piece.states[piece.spawn as usize];
+        let left = cells.iter().map(|[x, _]| *x).min().unwrap_or(0);
+        let right = cells.iter().map(|[x, _]| *x).max().unwrap_or(0);
+        let bottom = cells.iter().map(|[_, y]| *y).min().unwrap_or(0);
+        let x = (*dim.w() as i32 - (right - left + 1)) / 2;
+        Placement::new(
+            tetromino_type,
+            piece.spawn,
+            [x - left, dim.spawn_row() as i32 - bottom],
+        )
+    }
+
+    /// A new `tetromino_type` as it spawns on a board of `dim`
+    pub fn spawn(&self, tetromino_type: TetrominoType, dim: &Dimensions) -> Tetromino {
+        self.tetromino(&self.spawn_placement(tetromino_type, dim))
+    }
+
+    /// Cells that `placement` covers
+    pub fn cells(&self, placement: &Placement) -> Vec<Coord> {
+        let piece = self.piece(placement.piece);
+        place(&piece.states[placement.rotation as usize], placement.origin)
+    }
+
+    /// The tetromino at `placement`
+    pub fn tetromino(&self, placement: &Placement) -> Tetromino {
+        let mut tetromino = Tetromino::new(self.cells(placement), placement.piece);
+        tetromino
+            .rotation_state_mut()
+            .increment(placement.rotation as i32);
+        tetromino
+    }
+
+    /// Where `tetromino` is, going by the cell that's listed first
+    pub fn placement_of(&self, tetromino: &Tetromino) -> Placement {
+        let piece = self.piece(*tetromino.tetromino_type());
+        let rotation = *tetromino.rotation_state().rn();
+        let first = tetromino.coords()[0];
+        let cell = piece.states[rotation as usize][0];
+        Placement::new(
+            piece.piece,
+            rotation,
+            [first.x - cell[0], first.y - cell[1]],
+        )
+    }
+
+    /// Where `placement` ends up turned in `direction`, trying each kick in turn until `fits` says that its cells
+    /// fit, or `None` if none of them do
+    pub fn rotate(
+        &self,
+        placement: &Placement,
+        direction: RotationDirection,
+        kicks_180: Kicks180,
+        fits: impl Fn(&[Coord]) -> bool,
+    ) -> Option<Placement> {
+        let turn = match direction {
+            RotationDirection::Clockwise => 1,
+            RotationDirection::CounterClockwise => 3,
+            RotationDirection::Half => 2,
+        };
+        let piece = self.piece(placement.piece);
+        let current = placement.rotation;
+        let next = (current + turn) % 4;
+
+        // Checkout <https://harddrop.com/wiki/SRS#How_Guideline_SRS_Really_Works> for more information on how the offset wallkicks are derived
+        let mut tests = piece.tests(current, next).to_vec();
+        if let RotationDirection::Half = direction {
+            // The 180° kicks apply on top of wherever the first test puts the tetromino
+            let base = tests[0];
+            tests.extend(
+                kicks_180
+                    .kicks(placement.piece, current)
+                    .iter()
+                    .map(|kick| [base[0] + kick[0], base[1] + kick[1]]),
+            );
+        }
+
+        for (idx, dx_dy) in tests.iter().enumerate() {
+            let rotated = placement.turned(next, *dx_dy);
+            let cells = self.cells(&rotated);
+            if fits(&cells) {
+                return Some(rotated);
+            }
+
+            // ARS won't kick a tetromino that's only blocked in the middle, so that it can't climb out of holes
+            if idx == 0 && piece.centre_column {
+                let mut blocked: Vec<&[i32; 2]> = cells
+                    .iter()
+                    .zip(piece.states[next as usize].iter())
+                    .filter(|(coord, _)| !fits(&[**coord]))
+                    .map(|(_, cell)| cell)
+                    .collect();
+                // Reading order, from the top left
+                blocked.sort_by_key(|[x, y]| (-y, *x));
+                if blocked.first().is_some_and(|[x, _]| *x == piece.pivot[0]) {
+                    return None;
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Default for RotationSystem {
+    fn default() -> Self {
+        RotationSystem::srs()
+    }
+}
+
+/// Coords of `cells` with the corner of their box at `origin`
+pub fn place(cells: &[[i32; 2]], origin: [i32; 2]) -> Vec<Coord> {
+    cells
+        .iter()
+        .map(|[x, y]| Coord::new(origin[0] + x, origin[1] + y))
+        .collect()
+}
@@ -0,0 +1,51 @@
+// Runs the simulator binary the way it's meant to be run, and checks what it writes out
+use std::{fs, process::Command};
+
+use tetris_raylib_rs::sim::GameStats;
+
+#[test]
+fn test_tetris_sim() {
+    let out = std::env::temp_dir().join(format!("tetris-sim-{}", std::process::id()));
+    let status = Command::new(env!("CARGO_BIN_EXE_tetris-sim"))
+        .args([
+            "--bot", "random", "--games", "5", "--seed", "40", "--pieces", "50",
+        ])
+        .arg("--out")
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let csv = fs::read_to_string(out.join("games.csv")).unwrap();
+    assert_eq!(csv.lines().count(), 6);
+    let games: Vec<GameStats> =
+        serde_json::from_str(&fs::read_to_string(out.join("games.json")).unwrap()).unwrap();
+    let seeds: Vec<u64> = games.iter().map(|game| game.seed).collect();
+    assert_eq!(seeds, vec![40, 41, 42, 43, 44]);
+    assert!(games.iter().all(|game| game.pieces <= 50));
+    assert_eq!(
+        fs::read_to_string(out.join("summary.csv"))
+            .unwrap()
+            .lines()
+            .count(),
+        2
+    );
+    assert!(out.join("summary.json").exists());
+
+    // Anything it doesn't understand gets the usage instead, numbers too big to keep or count up to included
+    for args in [
+        &["--bot", "genius"][..],
+        &["--pieces", "4294967296"],
+        &["--seed", "18446744073709551615", "--games", "2"],
+    ]
+    .iter()
+    {
+        let output = Command::new(env!("CARGO_BIN_EXE_tetris-sim"))
+            .args(*args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("usage"));
+    }
+    fs::remove_dir_all(&out).unwrap();
+}
@@ -0,0 +1,39 @@
+// Plays against the reference bot in src/bin as a program of its own, the way the game runs external bots
+use std::{fs, thread, time::Duration};
+
+use tetris_raylib_rs::{
+    source::{play_headless, TbpBot},
+    universe::Universe,
+};
+
+#[test]
+fn test_reference_bot() {
+    let mut bot = TbpBot::launch(env!("CARGO_BIN_EXE_tbp_reference_bot"), &[]).unwrap();
+    assert_eq!(bot.name(), "reference");
+
+    let mut universe = Universe::from_seed(3);
+    play_headless(&mut universe, &mut bot, 100);
+    assert!(*universe.game().running());
+    assert_eq!(*universe.game().pieces(), 100);
+    assert!(*universe.game().lines_cleared() >= 20);
+    assert_eq!(bot.starts(), 1);
+
+    // A game that starts over gets the bot started over with it
+    universe.restart(4);
+    play_headless(&mut universe, &mut bot, 10);
+    assert_eq!(*universe.game().pieces(), 10);
+    assert_eq!(bot.starts(), 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_bot_that_isnt_ready_is_stopped() {
+    let marker = std::env::temp_dir().join("tetris-rs-tbp-not-ready");
+    let _ = fs::remove_file(&marker);
+    // Says something that isn't TBP, and then would leave a mark if it were left running
+    let script = format!("echo hello; sleep 1; touch '{}'", marker.display());
+    assert!(TbpBot::launch("sh", &["-c".to_string(), script]).is_err());
+
+    thread::sleep(Duration::from_secs(2));
+    assert!(!marker.exists());
+}
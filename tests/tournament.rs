@@ -0,0 +1,68 @@
+// Runs the tournament binary between the built in bots and the reference TBP bot, and checks what it writes out
+use std::{fs, process::Command};
+
+#[test]
+fn test_tetris_tournament() {
+    let out = std::env::temp_dir().join(format!("tetris-tournament-{}", std::process::id()));
+    let reference = format!("reference=tbp:{}", env!("CARGO_BIN_EXE_tbp_reference_bot"));
+    let output = Command::new(env!("CARGO_BIN_EXE_tetris-tournament"))
+        .args([
+            "--bot",
+            "heuristic=heuristic",
+            "--bot",
+            "random=random",
+            "--bot",
+        ])
+        .arg(&reference)
+        .args([
+            "--format", "swiss", "--rounds", "2", "--games", "1", "--pieces", "30",
+        ])
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let table: Vec<&str> = stdout.lines().collect();
+    assert_eq!(table.len(), 4);
+    assert!(table[0].contains("elo"));
+    for bot in ["heuristic", "random", "reference"].iter() {
+        assert!(table.iter().any(|line| line.contains(bot)));
+    }
+
+    let standings = fs::read_to_string(out.join("standings.csv")).unwrap();
+    assert_eq!(standings.lines().count(), 4);
+    // 1 pairing a round, 1 seed played from either side
+    let games = fs::read_to_string(out.join("games.csv")).unwrap();
+    assert_eq!(games.lines().count(), 1 + 2 * 2);
+    // A replay for each board of each game
+    assert_eq!(
+        fs::read_dir(out.join("replays")).unwrap().count(),
+        2 * 2 * 2
+    );
+    fs::remove_dir_all(&out).unwrap();
+
+    // Leaving out the bots is a usage error, as is naming two of them the same in their replays' file names
+    for args in [
+        &[][..],
+        &["--bot", "a b=heuristic", "--bot", "a/b=random"],
+        &[
+            "--bot",
+            "a=heuristic",
+            "--bot",
+            "b=random",
+            "--pieces",
+            "-1",
+        ],
+    ]
+    .iter()
+    {
+        let output = Command::new(env!("CARGO_BIN_EXE_tetris-tournament"))
+            .args(*args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("usage"));
+    }
+}
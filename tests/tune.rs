@@ -0,0 +1,57 @@
+// Runs the tuner binary for a generation of tiny games, and checks that it checkpoints and reports its weights
+use std::{fs, process::Command};
+
+use tetris_raylib_rs::{config::BotWeights, tune::Tuner};
+
+#[test]
+fn test_tetris_tune() {
+    let checkpoint = std::env::temp_dir().join(format!("tetris-tune-{}.json", std::process::id()));
+    let tune = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_tetris-tune"))
+            .arg("--checkpoint")
+            .arg(&checkpoint)
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+
+    let output = tune(&[
+        "--population",
+        "2",
+        "--elite",
+        "1",
+        "--seeds",
+        "1",
+        "--pieces",
+        "3",
+        "--generations",
+        "1",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("generation 1:"));
+    // The best weights come out ready to paste into the config file
+    let weights = stdout.split("[bot.weights]\n").nth(1).unwrap();
+    let weights: BotWeights = toml::from_str(weights).unwrap();
+    assert_eq!(
+        Tuner::load(&checkpoint).unwrap().best().unwrap().weights,
+        weights
+    );
+
+    // Resuming picks up where the checkpoint left off
+    let output = tune(&["--generations", "2", "--resume"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("generation 1:"));
+    assert!(stdout.contains("generation 2:"));
+    let tuner = Tuner::load(&checkpoint).unwrap();
+    assert_eq!(tuner.generation(), 2);
+    // On the same games as it started with, which can't be changed part way through
+    assert_eq!(tuner.trial().seeds, vec![0]);
+    assert_eq!(tuner.trial().pieces, 3);
+    let output = tune(&["--generations", "3", "--resume", "--seeds", "4"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--seeds can't be given"));
+    assert_eq!(Tuner::load(&checkpoint).unwrap().generation(), 2);
+    fs::remove_file(&checkpoint).unwrap();
+}